@@ -0,0 +1,659 @@
+//! The in-memory caches of the pokemon, the translations and the sub-resources, and the loading of the species list.
+//!
+//! The cached values expire with a jittered TTL, see `CachedValue`, and can be compressed with `COMPRESS_CACHE=true`.
+//! The pokemon and the translations missing in memory are looked up in the cache backend, see `backend`.
+
+pub(crate) mod backend;
+
+use std::sync::{Arc, Mutex};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{OnceCell, Semaphore};
+use serde::Serialize;
+use serde_json::Value;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use tracing::{info, warn};
+use rand::Rng;
+use crate::cache::backend::{get_from_cache_backend, set_in_cache_backend};
+use crate::clients::is_dry_run;
+use crate::config::Config;
+use crate::clients::pokeapi::{PokemonNotFound, SpeciesUnavailable, fetch_pokemon_descriptions_from_api, fetch_pokemon_from_api, fetch_species_names_from_api};
+use crate::models::{Descriptions, PokemonFetchError, PokemonResponse, Translation};
+use crate::telemetry;
+use crate::utils::{get_env_u32, retry_with_backoff, validate_pokemon_name};
+
+/// The kinds of the pokemon sub-resources, the data of the pokemon/pokemon_name/... endpoints.
+/// 
+/// The moves of a version group are keyed by the pokemon and the version group names, e.g. `pikachu/scarlet-violet`.
+/// The data of a move, the effects of an ability, the species of a habitat and the pokemon of a type are cached
+/// with them too, keyed by the move, the ability, the habitat and the type name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum ResourceKind {
+    Moves,
+    MoveDetails,
+    Cries,
+    FlavorTexts,
+    EvolutionChain,
+    Sprites,
+    Stats,
+    Abilities,
+    AbilityEffect,
+    HabitatSpecies,
+    TypePokemon,
+}
+
+/// A cache of the server, shared by the routes, with the time each value was cached.
+/// 
+/// The values past the TTL of their cache are not served and `evict_expired_entries` removes them in the background,
+/// so the caches don't grow forever.
+pub(crate) type Cache<K, V> = Arc<Mutex<HashMap<K, CachedValue<V>>>>;
+
+/// The cache of the pokemon sub-resources, keyed by the pokemon name and the kind of the resource.
+/// 
+/// The values are stored as JSON, so a single cache is shared by all the sub-resource endpoints.
+pub(crate) type SubResourceCache = Cache<(String, ResourceKind), Value>;
+
+/// Get a sub-resource of the pokemon from the cache, or fetch it and cache it.
+/// 
+/// The errors of the fetch are returned as they are and not cached, so the next request tries again.
+pub(crate) async fn get_or_fetch_sub_resource<T, E, F>(cache: &SubResourceCache, pokemon_name: &str, kind: ResourceKind, config: &Config, fetch: impl FnOnce() -> F) -> Result<T, E>
+where
+    T: Serialize + serde::de::DeserializeOwned,
+    F: std::future::Future<Output = Result<T, E>>,
+{
+    let key = (pokemon_name.to_string(), kind);
+    let value_in_cache = cache.lock().unwrap().get(&key).filter(|value| !value.is_expired(get_pokemon_cache_ttl(config))).map(CachedValue::get);
+    if let Some(value) = value_in_cache {
+        return Ok(serde_json::from_value(value).unwrap()); // Suppose to be safe to unwrap, the value was serialized from the same type
+    }
+
+    let fetch_started = Instant::now();
+    let resource = fetch().await?;
+    let value = serde_json::to_value(&resource).unwrap(); // Suppose to be safe to unwrap, the resources are plain data
+    let value = cache_fetched_value(cache, key, value, fetch_started);
+    Ok(serde_json::from_value(value).unwrap()) // Suppose to be safe to unwrap, the value was serialized from the same type
+}
+
+/// The preload jobs by id, with the results of each name once the job is done.
+/// 
+/// The jobs are kept until the server restarts, they are only a handful of names each.
+pub(crate) type PreloadJobs = Arc<Mutex<HashMap<String, Option<BTreeMap<String, PreloadResult>>>>>;
+
+/// Get the pokemon from the cache or fetch it from the PokeAPI.
+/// 
+/// This is the shared fetch helper used by the endpoints, it returns a `PokemonFetchError` if the pokemon can't be fetched.
+/// The names PokeAPI reports as not found are remembered in the negative cache and served as not found
+/// without calling the API again until the negative cache TTL expires, the other failures are not remembered.
+/// With `no_cache` both caches are skipped and the pokemon is fetched again, the fresh data still replaces the cached one.
+/// 
+/// The cached pokemon past the soft TTL are served right away and refreshed in the background,
+/// only the ones past the hard TTL are fetched again before replying, see `get_cache_freshness`.
+/// 
+/// The returned pokemon always has the canonical lowercase name returned by PokeAPI, whatever the case of the requested name.
+pub(crate) async fn get_or_fetch_pokemon(pokemon_name_to_search: String, no_cache: bool, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, config: Arc<Config>) -> Result<PokemonResponse, PokemonFetchError> {
+    // PokeAPI only knows the lowercase names, so `Pikachu` is looked up, and cached, as `pikachu`.
+    let pokemon_name_to_search = pokemon_name_to_search.to_lowercase();
+
+    let mut span = telemetry::Span::start("get_or_fetch_pokemon");
+    span.record("pokemon.name", &pokemon_name_to_search);
+
+    if !no_cache {
+        let canonical_name = get_canonical_name(pokemon_name_to_search.clone(), cache_aliases.clone());
+        if let Some((pokemon, freshness)) = get_pokemon_from_cache(canonical_name.clone(), cache_pokemon.clone(), &config) {
+            match freshness {
+                CacheFreshness::Fresh => {
+                    span.record_cache_hit(true);
+                    return Ok(pokemon);
+                }
+                CacheFreshness::Stale => {
+                    span.record_cache_hit(true);
+                    if start_refresh(&cache_pokemon, &canonical_name) {
+                        tokio::spawn(refresh_pokemon_in_cache(canonical_name, cache_pokemon.clone(), config.clone()));
+                    }
+                    return Ok(pokemon);
+                }
+                CacheFreshness::Expired => {}
+            }
+        }
+
+        if is_in_not_found_cache(pokemon_name_to_search.clone(), cache_not_found.clone()) {
+            span.record_cache_hit(true);
+            return Err(PokemonFetchError::NotFound);
+        }
+
+        // Another instance can have fetched the pokemon already, it is cached in memory too.
+        let fetch_started = Instant::now();
+        if let Some(pokemon) = get_from_cache_backend::<PokemonResponse>(&format!("pokemon:{}", pokemon_name_to_search)).await {
+            span.record_cache_hit(true);
+            if pokemon.name != pokemon_name_to_search {
+                cache_aliases.lock().unwrap().insert(pokemon_name_to_search, pokemon.name.clone());
+            }
+            return Ok(cache_fetched_value(&cache_pokemon, pokemon.name.clone(), pokemon, fetch_started));
+        }
+    }
+    span.record_cache_hit(false);
+
+    let fetch_started = Instant::now();
+    let pokemon = match fetch_pokemon_from_api(pokemon_name_to_search.clone(), &config).await {
+        Ok(pokemon) => pokemon,
+        // The pokemon exists when only its species failed, so the name is not remembered as not found.
+        Err(error) if error.is::<SpeciesUnavailable>() => {
+            warn!("Failed to fetch the pokemon {}: {}", pokemon_name_to_search, error);
+            return Err(PokemonFetchError::Upstream);
+        }
+        // Only the pokemon not found is remembered, the other errors are PokeAPI failing and the next request tries again.
+        Err(error) if error.is::<PokemonNotFound>() => {
+            cache_not_found.lock().unwrap().insert(pokemon_name_to_search, Instant::now());
+            return Err(PokemonFetchError::NotFound);
+        }
+        Err(error) => {
+            warn!("Failed to fetch the pokemon {}: {}", pokemon_name_to_search, error);
+            return Err(PokemonFetchError::Upstream);
+        }
+    };
+
+    // PokeAPI can resolve the requested name to a different pokemon name, like deoxys to deoxys-normal.
+    // The data is cached under the name returned by the API and the requested name becomes an alias of it.
+    let canonical_name = pokemon.name.clone();
+    if canonical_name != pokemon_name_to_search {
+        set_in_cache_backend(&format!("pokemon:{}", pokemon_name_to_search), &pokemon, get_pokemon_cache_ttl(&config)).await;
+        cache_aliases.lock().unwrap().insert(pokemon_name_to_search, canonical_name.clone());
+    }
+    set_in_cache_backend(&format!("pokemon:{}", canonical_name), &pokemon, get_pokemon_cache_ttl(&config)).await;
+    let pokemon = cache_fetched_value(&cache_pokemon, canonical_name, pokemon, fetch_started);
+
+    Ok(pokemon)
+}
+
+/// Get the time the pokemon was cached, or the current time if it is not in the cache.
+/// 
+/// `get_or_fetch_pokemon` caches the fetched pokemon, so the freshly fetched ones have the current time.
+pub(crate) fn get_pokemon_cached_at(pokemon_name: &str, cache: Cache<String, PokemonResponse>) -> SystemTime {
+    cache.lock().unwrap().get(pokemon_name).map(CachedValue::cached_at_system_time).unwrap_or_else(SystemTime::now)
+}
+
+/// Fetch the pokemon again and replace the cached one, used to refresh the stale pokemon in the background.
+/// 
+/// If the fetch fails the stale pokemon is kept, the next request will try again.
+/// The caller marks the pokemon as refreshing with `start_refresh`, the fresh pokemon replaces the mark.
+pub(crate) async fn refresh_pokemon_in_cache(pokemon_name: String, cache_pokemon: Cache<String, PokemonResponse>, config: Arc<Config>) {
+    let fetch_started = Instant::now();
+    match fetch_pokemon_from_api(pokemon_name.clone(), &config).await {
+        Ok(pokemon) => {
+            set_in_cache_backend(&format!("pokemon:{}", pokemon_name), &pokemon, get_pokemon_cache_ttl(&config)).await;
+            cache_fetched_value(&cache_pokemon, pokemon_name, pokemon, fetch_started);
+        }
+        Err(error) => {
+            warn!("Failed to refresh the cached pokemon {}: {}", pokemon_name, error);
+            if let Some(cached_pokemon) = cache_pokemon.lock().unwrap().get_mut(&pokemon_name) {
+                cached_pokemon.refreshing = false;
+            }
+        }
+    }
+}
+
+/// Mark the cached value as refreshing, `false` if it is already refreshing or not in the cache.
+/// 
+/// The stale hits all check the mark, so only the first one refreshes the value and the others don't call the API again.
+pub(crate) fn start_refresh<K, T>(cache: &Mutex<HashMap<K, CachedValue<T>>>, key: &K) -> bool
+where
+    K: Eq + std::hash::Hash,
+{
+    match cache.lock().unwrap().get_mut(key) {
+        Some(cached_value) if !cached_value.refreshing => {
+            cached_value.refreshing = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get the descriptions of the pokemon by language from the cache or fetch them from the PokeAPI.
+/// 
+/// The descriptions are cached under the pokemon name returned by PokeAPI, `None` if they can't be fetched.
+pub(crate) async fn get_or_fetch_descriptions(pokemon_name: String, no_cache: bool, cache_descriptions: Cache<String, Descriptions>, config: &Config) -> Option<Descriptions> {
+    if !no_cache {
+        let descriptions_in_cache = cache_descriptions.lock().unwrap().get(&pokemon_name).filter(|descriptions| !descriptions.is_expired(get_pokemon_cache_ttl(config))).map(CachedValue::get);
+        if descriptions_in_cache.is_some() {
+            return descriptions_in_cache;
+        }
+    }
+
+    let fetch_started = Instant::now();
+    match fetch_pokemon_descriptions_from_api(pokemon_name.clone(), config).await {
+        Ok(descriptions) => Some(cache_fetched_value(&cache_descriptions, pokemon_name, descriptions, fetch_started)),
+        Err(error) => {
+            warn!("Failed to fetch the descriptions of {}: {}", pokemon_name, error);
+            None
+        }
+    }
+}
+
+/// Cache the pokemon in a HashMap with the pokemon name as the key, the cached pokemon is returned with its freshness.
+/// 
+/// In real world application I should use a cache library like Redis.
+pub(crate) fn get_pokemon_from_cache(pokemon_name: String, cache: Cache<String, PokemonResponse>, config: &Config) -> Option<(PokemonResponse, CacheFreshness)> {
+    let cache_guard = cache.lock().unwrap();
+    if cache_guard.contains_key(&pokemon_name) {
+        let cached_pokemon = &cache_guard[&pokemon_name];
+        return Some((cached_pokemon.get(), get_cache_freshness(cached_pokemon, config)));
+    }
+    None
+}
+
+/// How fresh a cached pokemon is.
+#[derive(Debug, PartialEq)]
+pub(crate) enum CacheFreshness {
+    /// Younger than the soft TTL, served from the cache.
+    Fresh,
+    /// Between the soft and the hard TTL, served from the cache and refreshed in the background.
+    Stale,
+    /// Older than the hard TTL, fetched again before replying.
+    Expired,
+}
+
+/// Get the freshness of a cached pokemon from its expiry instants.
+/// 
+/// The soft TTL is set with the `POKEMON_CACHE_SOFT_TTL_SECONDS` env variable, or `pokemon_cache_soft_ttl_seconds`
+/// of the config file, the default is 1 day, and the hard TTL is the one of `get_pokemon_cache_ttl`.
+/// Both are stretched by the jitter of the entry, see `CachedValue::expires_at`.
+pub(crate) fn get_cache_freshness<T>(cached_value: &CachedValue<T>, config: &Config) -> CacheFreshness {
+    let soft_ttl = Duration::from_secs(get_env_u32("POKEMON_CACHE_SOFT_TTL_SECONDS", config.pokemon_cache_soft_ttl_seconds.unwrap_or(24 * 60 * 60)) as u64);
+    let now = Instant::now();
+
+    if cached_value.is_expired(get_pokemon_cache_ttl(config)) {
+        CacheFreshness::Expired
+    } else if now >= cached_value.expires_at(soft_ttl) {
+        CacheFreshness::Stale
+    } else {
+        CacheFreshness::Fresh
+    }
+}
+
+/// A value in the pokemon and sub-resource caches, with the time it was cached.
+/// 
+/// When the `COMPRESS_CACHE` env variable is `true` the values are stored as deflate compressed JSON
+/// and decompressed on read, trading CPU for memory. The callers get the same value either way.
+/// 
+/// Each value gets a random TTL jitter when it is cached, so the pokemon cached together,
+/// e.g. by a burst of requests after a restart, don't all expire and hit PokeAPI at the same time.
+#[derive(Clone, Debug)]
+pub(crate) struct CachedValue<T> {
+    pub(crate) data: CachedData<T>,
+    pub(crate) cached_at: Instant,
+    pub(crate) ttl_jitter: f64,
+    /// A background refresh of the value is in flight, see `start_refresh`.
+    pub(crate) refreshing: bool,
+}
+
+/// The TTLs of the cached values are stretched by a random factor in this range, i.e. ±10%.
+pub(crate) const CACHE_TTL_JITTER: std::ops::RangeInclusive<f64> = 0.9..=1.1;
+
+/// The data of a cached value, see `CachedValue`.
+#[derive(Clone, Debug)]
+pub(crate) enum CachedData<T> {
+    Plain(T),
+    Compressed(Vec<u8>),
+}
+
+impl<T: Clone + Serialize + serde::de::DeserializeOwned> CachedValue<T> {
+    pub(crate) fn new(value: T) -> Self {
+        let data = if is_cache_compression_enabled() {
+            CachedData::Compressed(compress_json(&value))
+        } else {
+            CachedData::Plain(value)
+        };
+
+        CachedValue { data, cached_at: Instant::now(), ttl_jitter: rand::thread_rng().gen_range(CACHE_TTL_JITTER), refreshing: false }
+    }
+
+    pub(crate) fn get(&self) -> T {
+        match &self.data {
+            CachedData::Plain(value) => value.clone(),
+            CachedData::Compressed(bytes) => decompress_json(bytes),
+        }
+    }
+}
+
+impl<T> CachedValue<T> {
+    /// The instant the value expires with the given TTL, after applying its jitter.
+    pub(crate) fn expires_at(&self, ttl: Duration) -> Instant {
+        self.cached_at + ttl.mul_f64(self.ttl_jitter)
+    }
+
+    /// Check if the value is past the given TTL, after applying its jitter.
+    pub(crate) fn is_expired(&self, ttl: Duration) -> bool {
+        Instant::now() >= self.expires_at(ttl)
+    }
+
+    /// The wall clock time the value was cached, for the replies, the `Instant` is only for measuring the age.
+    pub(crate) fn cached_at_system_time(&self) -> SystemTime {
+        SystemTime::now() - self.cached_at.elapsed()
+    }
+}
+
+/// Cache the value fetched since `fetch_started`, or get the value a concurrent request cached meanwhile.
+/// 
+/// Two concurrent misses for the same key both fetch the value, with the entry API only the first write wins
+/// and the later requests reply with its value, so they all share the same data.
+/// The values cached before the fetch started, e.g. the expired ones or the ones skipped by `no_cache`, are replaced.
+pub(crate) fn cache_fetched_value<K, T>(cache: &Mutex<HashMap<K, CachedValue<T>>>, key: K, value: T, fetch_started: Instant) -> T
+where
+    K: Eq + std::hash::Hash,
+    T: Clone + Serialize + serde::de::DeserializeOwned,
+{
+    match cache.lock().unwrap().entry(key) {
+        Entry::Occupied(entry) if entry.get().cached_at >= fetch_started => entry.get().get(),
+        Entry::Occupied(mut entry) => {
+            entry.insert(CachedValue::new(value.clone()));
+            value
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(CachedValue::new(value.clone()));
+            value
+        }
+    }
+}
+
+/// Get the TTL of the pokemon, their descriptions and their sub-resources in the caches.
+/// 
+/// The TTL is set with the `POKEMON_CACHE_HARD_TTL_SECONDS` env variable, or `pokemon_cache_hard_ttl_seconds`
+/// of the config file, the default is 7 days, past it the pokemon are fetched again before replying, see `get_cache_freshness`.
+pub(crate) fn get_pokemon_cache_ttl(config: &Config) -> Duration {
+    Duration::from_secs(get_env_u32("POKEMON_CACHE_HARD_TTL_SECONDS", config.pokemon_cache_hard_ttl_seconds.unwrap_or(7 * 24 * 60 * 60)) as u64)
+}
+
+/// Get the TTL of the translations in the cache from the `TRANSLATION_CACHE_TTL_SECONDS` env variable,
+/// or `translation_cache_ttl_seconds` of the config file, the default is 7 days.
+/// 
+/// The translations only change with the translator, so they are kept for long to save the Fun Translations quota.
+pub(crate) fn get_translation_cache_ttl(config: &Config) -> Duration {
+    Duration::from_secs(get_env_u32("TRANSLATION_CACHE_TTL_SECONDS", config.translation_cache_ttl_seconds.unwrap_or(7 * 24 * 60 * 60)) as u64)
+}
+
+/// Remove the values past the TTL from the cache, the number of values removed is returned.
+pub(crate) fn evict_expired_values<K, V>(cache: &Mutex<HashMap<K, CachedValue<V>>>, ttl: Duration) -> usize {
+    let mut cache_guard = cache.lock().unwrap();
+    let entries_before = cache_guard.len();
+    cache_guard.retain(|_, value| !value.is_expired(ttl));
+    entries_before - cache_guard.len()
+}
+
+/// Evict the expired entries of the caches every `CACHE_EVICTION_INTERVAL_SECONDS` (10 minutes by default), forever.
+/// 
+/// The expired entries are already ignored on read, the eviction only frees their memory,
+/// so the names requested once don't stay in the caches until the server restarts.
+/// The aliases of the evicted pokemon are dropped too, and the not found names past the negative cache TTL.
+pub(crate) async fn evict_expired_entries(cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, cache_descriptions: Cache<String, Descriptions>, cache_sub_resources: SubResourceCache, cache_translation: Cache<String, Translation>, config: Arc<Config>) {
+    let eviction_interval = Duration::from_secs(get_env_u32("CACHE_EVICTION_INTERVAL_SECONDS", 10 * 60).max(1) as u64);
+    let mut interval = tokio::time::interval(eviction_interval);
+    // The first tick completes right away, with nothing to evict yet.
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let mut evicted = evict_expired_values(&cache_pokemon, get_pokemon_cache_ttl(&config));
+        evicted += evict_expired_values(&cache_descriptions, get_pokemon_cache_ttl(&config));
+        evicted += evict_expired_values(&cache_sub_resources, get_pokemon_cache_ttl(&config));
+        evicted += evict_expired_values(&cache_translation, get_translation_cache_ttl(&config));
+
+        let cached_names: HashSet<String> = cache_pokemon.lock().unwrap().keys().cloned().collect();
+        cache_aliases.lock().unwrap().retain(|_, canonical_name| cached_names.contains(canonical_name));
+        cache_not_found.lock().unwrap().retain(|_, not_found_at| not_found_at.elapsed() < get_not_found_cache_ttl());
+
+        if evicted > 0 {
+            info!("Evicted {} expired entries from the caches", evicted);
+        }
+    }
+}
+
+/// Check if the translation cache is disabled with the `DISABLE_TRANSLATION_CACHE` env variable.
+/// 
+/// Without the cache every translation calls Fun Translations, e.g. for the demos of the rate limit,
+/// the pokemon data is still cached.
+pub(crate) fn is_translation_cache_disabled() -> bool {
+    std::env::var("DISABLE_TRANSLATION_CACHE").map(|value| value == "true").unwrap_or(false)
+}
+
+/// Check if the cache compression is enabled with the `COMPRESS_CACHE` env variable.
+pub(crate) fn is_cache_compression_enabled() -> bool {
+    std::env::var("COMPRESS_CACHE").map(|value| value == "true").unwrap_or(false)
+}
+
+/// Serialize the value to JSON and compress it with deflate.
+pub(crate) fn compress_json<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, value).unwrap(); // Suppose to be safe to unwrap, writing to a Vec can't fail
+    encoder.finish().unwrap()
+}
+
+/// Decompress and deserialize a value compressed by `compress_json`.
+pub(crate) fn decompress_json<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> T {
+    serde_json::from_reader(DeflateDecoder::new(bytes)).unwrap() // Suppose to be safe to unwrap, the bytes were compressed from the same type
+}
+
+/// Get the name the pokemon is cached under, the requested name can be an alias of it.
+pub(crate) fn get_canonical_name(pokemon_name: String, cache_aliases: Arc<Mutex<HashMap<String, String>>>) -> String {
+    match cache_aliases.lock().unwrap().get(&pokemon_name) {
+        Some(canonical_name) => canonical_name.clone(),
+        None => pokemon_name,
+    }
+}
+
+/// Cache the translation in a HashMap with the pokemon name as the key.
+/// 
+/// In real world application I should use a cache library like Redis.
+/// The translations past the TTL of `get_translation_cache_ttl` are not served, they are translated again.
+pub(crate) fn get_translation_from_cache(pokemon_name: String, cache: Cache<String, Translation>, config: &Config) -> Option<Translation> {
+    cache.lock().unwrap().get(&pokemon_name).filter(|translation| !translation.is_expired(get_translation_cache_ttl(config))).map(CachedValue::get)
+}
+
+/// Get the translation from the in-memory cache, or from the cache backend where another instance can have cached it.
+/// 
+/// The translations found in the backend are cached in memory too.
+pub(crate) async fn get_shared_translation(key: String, cache: Cache<String, Translation>, config: &Config) -> Option<Translation> {
+    if let Some(translation) = get_translation_from_cache(key.clone(), cache.clone(), config) {
+        return Some(translation);
+    }
+
+    let fetch_started = Instant::now();
+    let translation = get_from_cache_backend::<Translation>(&format!("translation:{}", key)).await?;
+    Some(cache_fetched_value(&cache, key, translation, fetch_started))
+}
+
+/// Get the names of all the pokemon species.
+/// 
+/// The names are loaded once, on first need, and shared by all the features that need the full list,
+/// see `fetch_species_names_with_disk_cache`.
+/// If the load fails the error is returned to the caller and the names are not stored.
+pub(crate) async fn get_species_names<'a>(species_names: &'a OnceCell<Vec<String>>, config: &Config) -> Result<&'a Vec<String>, rustemon::error::Error> {
+    load_species_names(species_names, || fetch_species_names_with_disk_cache(config)).await
+}
+
+/// Fetch the species names from the disk cache, or from the PokeAPI with retries and store them in the disk cache.
+/// 
+/// The list is a single large fetch, so it is retried `SPECIES_LIST_ATTEMPTS` times (3 by default) with an exponential
+/// backoff starting at `SPECIES_LIST_RETRY_DELAY_MS` (500 ms by default), see `retry_with_backoff`.
+/// The disk cache is only used with the `SPECIES_LIST_CACHE_DIR` env variable, see `get_species_list_cache_path`.
+pub(crate) async fn fetch_species_names_with_disk_cache(config: &Config) -> Result<Vec<String>, rustemon::error::Error> {
+    let cache_path = get_species_list_cache_path();
+    if let Some(names) = cache_path.as_deref().and_then(read_species_names_from_disk) {
+        info!("Loaded the pokemon species names from {}", cache_path.unwrap().display());
+        return Ok(names);
+    }
+
+    let attempts = get_env_u32("SPECIES_LIST_ATTEMPTS", 3);
+    let retry_delay = Duration::from_millis(get_env_u32("SPECIES_LIST_RETRY_DELAY_MS", 500) as u64);
+    let names = retry_with_backoff(attempts, retry_delay, || fetch_species_names_from_api(config)).await?;
+
+    if let Some(cache_path) = cache_path {
+        if let Err(error) = std::fs::write(&cache_path, serde_json::to_string(&names).unwrap()) {
+            warn!("Failed to write the pokemon species names to {}: {}", cache_path.display(), error);
+        }
+    }
+    Ok(names)
+}
+
+/// Get the path of the species list in the `SPECIES_LIST_CACHE_DIR` directory, `None` without the env variable.
+/// 
+/// The file is keyed by `SPECIES_LIST_CACHE_VERSION`, or by the current UTC date if it is not set,
+/// so by default the list is fetched again once a day. The dry run mode doesn't use the disk cache.
+pub(crate) fn get_species_list_cache_path() -> Option<std::path::PathBuf> {
+    if is_dry_run() {
+        return None;
+    }
+
+    let cache_dir = std::env::var("SPECIES_LIST_CACHE_DIR").ok()?;
+    let version = std::env::var("SPECIES_LIST_CACHE_VERSION")
+        .unwrap_or_else(|_| humantime::format_rfc3339_seconds(SystemTime::now()).to_string()[..10].to_string());
+    Some(std::path::Path::new(&cache_dir).join(format!("species-{}.json", version)))
+}
+
+/// Read the species names of the disk cache, `None` if the file is missing or invalid.
+pub(crate) fn read_species_names_from_disk(cache_path: &std::path::Path) -> Option<Vec<String>> {
+    let content = std::fs::read_to_string(cache_path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(names) => Some(names),
+        Err(error) => {
+            warn!("Ignoring the invalid pokemon species names in {}: {}", cache_path.display(), error);
+            None
+        }
+    }
+}
+
+/// Load the species names at startup, so the first request needing them doesn't wait.
+/// 
+/// If the PokeAPI is unreachable the server starts anyway: the failure is logged
+/// and the load is retried by the first feature calling `get_species_names`.
+pub(crate) async fn warm_up_species_names(species_names: Arc<OnceCell<Vec<String>>>, config: Arc<Config>) {
+    match get_species_names(&species_names, &config).await {
+        Ok(names) => info!("Loaded {} pokemon species names", names.len()),
+        Err(error) => warn!("Failed to load the pokemon species names, they will be loaded on first use: {}", error),
+    }
+}
+
+/// Get the pokemon names to warm up the cache with at startup.
+/// 
+/// The names are read from the `WARMUP_LIST` env variable and from the file at the `WARMUP_LIST_FILE` path,
+/// both separated by commas or newlines. A missing file is logged and ignored, the server starts anyway.
+pub(crate) fn get_warm_up_list() -> Vec<String> {
+    let mut names = parse_warm_up_list(&std::env::var("WARMUP_LIST").unwrap_or_default());
+
+    if let Ok(path) = std::env::var("WARMUP_LIST_FILE") {
+        match std::fs::read_to_string(&path) {
+            Ok(content) => names.extend(parse_warm_up_list(&content)),
+            Err(error) => warn!("Failed to read the warm-up list {}: {}", path, error),
+        }
+    }
+
+    names
+}
+
+/// Split a warm-up list separated by commas or newlines into the lowercase names.
+pub(crate) fn parse_warm_up_list(list: &str) -> Vec<String> {
+    list.split([',', '\n'])
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Get the semaphore limiting the concurrent fetches of the warm-up and of the admin preload,
+/// at most `WARMUP_CONCURRENCY` at a time (5 by default) to go easy on PokeAPI.
+pub(crate) fn get_preload_semaphore() -> Arc<Semaphore> {
+    Arc::new(Semaphore::new(get_env_u32("WARMUP_CONCURRENCY", 5).max(1) as usize))
+}
+
+/// The outcome of preloading a pokemon into the cache.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PreloadResult {
+    Cached,
+    NotFound,
+    InvalidName,
+    UpstreamError,
+}
+
+/// Fetch the pokemon into the cache, so their first requests don't wait for PokeAPI.
+/// 
+/// The pokemon are fetched concurrently, as many at a time as the semaphore allows, see `get_preload_semaphore`.
+/// The invalid and not found names are logged and skipped, the result of each name is returned.
+pub(crate) async fn preload_pokemon(names: Vec<String>, semaphore: Arc<Semaphore>, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, config: Arc<Config>) -> BTreeMap<String, PreloadResult> {
+    let mut results = BTreeMap::new();
+    let mut tasks = Vec::new();
+
+    for name in names {
+        if let Err(code) = validate_pokemon_name(&name) {
+            warn!("Skipping the invalid pokemon name {} of the preload: {}", name, code);
+            results.insert(name, PreloadResult::InvalidName);
+            continue;
+        }
+
+        let semaphore = Arc::clone(&semaphore);
+        let cache_pokemon = Arc::clone(&cache_pokemon);
+        let cache_aliases = Arc::clone(&cache_aliases);
+        let cache_not_found = Arc::clone(&cache_not_found);
+        let config = Arc::clone(&config);
+        tasks.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire().await.unwrap();
+            match get_or_fetch_pokemon(name.clone(), false, cache_pokemon, cache_aliases, cache_not_found, config).await {
+                Ok(_) => (name, PreloadResult::Cached),
+                Err(PokemonFetchError::NotFound) => {
+                    warn!("Skipping the pokemon {} of the preload, it was not found", name);
+                    (name, PreloadResult::NotFound)
+                }
+                Err(PokemonFetchError::Upstream) => (name, PreloadResult::UpstreamError),
+            }
+        }));
+    }
+
+    for (name, result) in futures::future::join_all(tasks).await.into_iter().flatten() {
+        results.insert(name, result);
+    }
+    results
+}
+
+/// Fetch the pokemon of the warm-up list into the cache at startup, see `get_warm_up_list`.
+pub(crate) async fn warm_up_pokemon_cache(names: Vec<String>, semaphore: Arc<Semaphore>, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, config: Arc<Config>) {
+    let results = preload_pokemon(names, semaphore, cache_pokemon, cache_aliases, cache_not_found, config).await;
+    let warmed_up = results.values().filter(|result| **result == PreloadResult::Cached).count();
+    info!("Warmed up the cache with {} pokemon", warmed_up);
+}
+
+/// Load the species names with the loader, only the first successful call runs the loader.
+/// 
+/// The concurrent calls wait for the running load instead of starting a new one.
+pub(crate) async fn load_species_names<F, Fut, E>(species_names: &OnceCell<Vec<String>>, loader: F) -> Result<&Vec<String>, E>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<String>, E>>,
+{
+    species_names.get_or_try_init(loader).await
+}
+
+/// Check if the pokemon name is in the negative cache.
+/// 
+/// The negative cache remembers the names not found in the PokeAPI, the entries expire after the TTL
+/// so the newly added pokemon are not blocked forever. The expired entries are removed from the cache.
+pub(crate) fn is_in_not_found_cache(pokemon_name: String, cache: Arc<Mutex<HashMap<String, Instant>>>) -> bool {
+    let mut cache_guard = cache.lock().unwrap();
+    match cache_guard.get(&pokemon_name) {
+        Some(not_found_at) if not_found_at.elapsed() < get_not_found_cache_ttl() => true,
+        Some(_) => {
+            cache_guard.remove(&pokemon_name);
+            false
+        }
+        None => false,
+    }
+}
+
+/// Get the TTL of the negative cache from the `NOT_FOUND_CACHE_TTL_SECONDS` env variable.
+/// 
+/// The TTL should be small, the default is 60 seconds.
+pub(crate) fn get_not_found_cache_ttl() -> Duration {
+    let seconds = std::env::var("NOT_FOUND_CACHE_TTL_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(60);
+    Duration::from_secs(seconds)
+}
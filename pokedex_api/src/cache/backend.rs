@@ -0,0 +1,179 @@
+//! The cache backends sharing the pokemon and the translations between the instances of the server.
+//!
+//! The in-memory caches of `cache` are always the first level, the backend is looked up only on their misses.
+//! The default backend keeps the values in memory only, `CACHE_BACKEND=redis` shares them through Redis,
+//! see `RedisBackend`, so the instances behind a load balancer don't fetch and translate the same pokemon each.
+
+use std::time::Duration;
+use futures::future::BoxFuture;
+use tracing::warn;
+use serde::Serialize;
+use tokio::sync::OnceCell;
+#[cfg(feature = "redis")]
+use tracing::info;
+
+/// A store of the cached values shared by the instances, the values are JSON strings expiring after their TTL.
+/// 
+/// The backend errors are logged and handled as misses, a failing store must not fail the requests.
+pub(crate) trait CacheBackend: Send + Sync {
+    /// Get the value of the key, `None` if it is missing, expired or the backend failed.
+    fn get(&self, key: &str) -> BoxFuture<'_, Option<String>>;
+    /// Store the value of the key for the TTL.
+    fn set(&self, key: &str, value: String, ttl: Duration) -> BoxFuture<'_, ()>;
+    /// Delete the keys starting with the prefix, returns the number of keys deleted, 0 if the backend failed.
+    fn delete_prefix(&self, prefix: &str) -> BoxFuture<'_, usize>;
+}
+
+/// The default backend: the values are only in the in-memory caches of the instance, nothing is shared.
+pub(crate) struct MemoryBackend;
+
+impl CacheBackend for MemoryBackend {
+    fn get(&self, _key: &str) -> BoxFuture<'_, Option<String>> {
+        Box::pin(async { None })
+    }
+
+    fn set(&self, _key: &str, _value: String, _ttl: Duration) -> BoxFuture<'_, ()> {
+        Box::pin(async {})
+    }
+
+    fn delete_prefix(&self, _prefix: &str) -> BoxFuture<'_, usize> {
+        Box::pin(async { 0 })
+    }
+}
+
+/// The Redis backend, enabled with the `redis` cargo feature and `CACHE_BACKEND=redis`.
+/// 
+/// The connection manager reconnects by itself when the connection drops, the calls fail meanwhile.
+#[cfg(feature = "redis")]
+pub(crate) struct RedisBackend {
+    connection: redis::aio::ConnectionManager,
+}
+
+#[cfg(feature = "redis")]
+impl RedisBackend {
+    /// Connect to the Redis server of the `REDIS_URL` env variable, `redis://127.0.0.1:6379` by default.
+    pub(crate) async fn connect() -> redis::RedisResult<RedisBackend> {
+        let redis_url = std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1:6379".to_string());
+        let client = redis::Client::open(redis_url)?;
+        let connection = redis::aio::ConnectionManager::new(client).await?;
+        Ok(RedisBackend { connection })
+    }
+}
+
+#[cfg(feature = "redis")]
+impl CacheBackend for RedisBackend {
+    fn get(&self, key: &str) -> BoxFuture<'_, Option<String>> {
+        let mut connection = self.connection.clone();
+        let key = key.to_string();
+        Box::pin(async move {
+            let value: redis::RedisResult<Option<String>> = redis::AsyncCommands::get(&mut connection, &key).await;
+            value.unwrap_or_else(|error| {
+                warn!("Failed to get {} from Redis: {}", key, error);
+                None
+            })
+        })
+    }
+
+    fn set(&self, key: &str, value: String, ttl: Duration) -> BoxFuture<'_, ()> {
+        let mut connection = self.connection.clone();
+        let key = key.to_string();
+        Box::pin(async move {
+            // Redis rejects an expiry of 0 seconds.
+            let result: redis::RedisResult<()> = redis::AsyncCommands::set_ex(&mut connection, &key, value, ttl.as_secs().max(1)).await;
+            if let Err(error) = result {
+                warn!("Failed to set {} in Redis: {}", key, error);
+            }
+        })
+    }
+
+    fn delete_prefix(&self, prefix: &str) -> BoxFuture<'_, usize> {
+        let mut connection = self.connection.clone();
+        let pattern = format!("{}*", prefix);
+        Box::pin(async move {
+            // SCAN walks the keys in batches instead of blocking Redis like KEYS, a cursor of 0 ends the walk.
+            let mut cursor = 0u64;
+            let mut deleted = 0;
+            loop {
+                let batch: redis::RedisResult<(u64, Vec<String>)> = redis::cmd("SCAN").arg(cursor).arg("MATCH").arg(&pattern).arg("COUNT").arg(100).query_async(&mut connection).await;
+                let (next_cursor, keys) = match batch {
+                    Ok(batch) => batch,
+                    Err(error) => {
+                        warn!("Failed to scan {} in Redis: {}", pattern, error);
+                        return deleted;
+                    }
+                };
+                if !keys.is_empty() {
+                    let result: redis::RedisResult<usize> = redis::AsyncCommands::del(&mut connection, &keys).await;
+                    match result {
+                        Ok(count) => deleted += count,
+                        Err(error) => warn!("Failed to delete {} from Redis: {}", pattern, error),
+                    }
+                }
+                if next_cursor == 0 {
+                    return deleted;
+                }
+                cursor = next_cursor;
+            }
+        })
+    }
+}
+
+/// The backend of the server, chosen on first use.
+static CACHE_BACKEND: OnceCell<Box<dyn CacheBackend>> = OnceCell::const_new();
+
+/// Get the cache backend of the `CACHE_BACKEND` env variable, `memory` by default or `redis`.
+/// 
+/// The backend is created once and shared by all the requests. If Redis can't be reached at the first use,
+/// or the server is built without the `redis` cargo feature, the server keeps working with the memory backend.
+pub(crate) async fn get_cache_backend() -> &'static dyn CacheBackend {
+    let backend = CACHE_BACKEND.get_or_init(|| async {
+        match std::env::var("CACHE_BACKEND").as_deref() {
+            Ok("redis") => connect_redis_backend().await,
+            Ok("memory") | Err(_) => Box::new(MemoryBackend),
+            Ok(backend) => {
+                warn!("Unknown CACHE_BACKEND {}, using the memory backend", backend);
+                Box::new(MemoryBackend)
+            }
+        }
+    }).await;
+    backend.as_ref()
+}
+
+/// Connect the Redis backend, or fall back to the memory backend if Redis can't be reached.
+#[cfg(feature = "redis")]
+async fn connect_redis_backend() -> Box<dyn CacheBackend> {
+    match RedisBackend::connect().await {
+        Ok(backend) => {
+            info!("Sharing the cached pokemon and translations through Redis");
+            Box::new(backend)
+        }
+        Err(error) => {
+            warn!("Failed to connect to Redis, using the memory backend: {}", error);
+            Box::new(MemoryBackend)
+        }
+    }
+}
+
+/// Without the `redis` cargo feature there is no Redis client, so the memory backend is used.
+#[cfg(not(feature = "redis"))]
+async fn connect_redis_backend() -> Box<dyn CacheBackend> {
+    warn!("CACHE_BACKEND=redis needs the server built with the redis cargo feature, using the memory backend");
+    Box::new(MemoryBackend)
+}
+
+/// Get a value of the cache backend, the values that don't deserialize, e.g. written by an older version, are misses.
+pub(crate) async fn get_from_cache_backend<T: serde::de::DeserializeOwned>(key: &str) -> Option<T> {
+    let value = get_cache_backend().await.get(key).await?;
+    serde_json::from_str(&value).ok()
+}
+
+/// Store a value in the cache backend for the TTL.
+pub(crate) async fn set_in_cache_backend<T: Serialize>(key: &str, value: &T, ttl: Duration) {
+    let value = serde_json::to_string(value).unwrap(); // Suppose to be safe to unwrap, the cached values are plain data
+    get_cache_backend().await.set(key, value, ttl).await;
+}
+
+/// Delete the values of the cache backend with the key prefix, e.g. `pokemon:`, returns the number of values deleted.
+pub(crate) async fn delete_prefix_from_cache_backend(prefix: &str) -> usize {
+    get_cache_backend().await.delete_prefix(prefix).await
+}
@@ -0,0 +1,678 @@
+//! The Fun Translations client and the selection of the translator of each pokemon.
+//!
+//! The translators are registered by name in the `TranslatorRegistry`, Yoda and Shakespeare for the default rule
+//! of `select_translator` and the other Fun Translations styles for the requests asking for one.
+//! The API allows only 10 requests per hour, so the calls are counted by `TranslationQuota`
+//! and stopped by `CircuitBreaker` while the API is failing.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::json;
+use tracing::{debug, info, warn};
+use crate::clients::{get_http_client, is_dry_run};
+use crate::config::Config;
+use crate::models::{PokemonResponse, Translation};
+use crate::telemetry;
+use crate::utils::{collapse_whitespace, get_env_u32};
+
+/// Fetch the Yoda translation from the Fun Translations API.
+/// 
+/// The Yoda API has a rate limit of 10 requests per hour and 60 requests per day.
+/// Be careful with the rate limit!
+/// 
+/// The API will return a 429 status code if the rate limit is reached.
+/// 
+/// In real world application, I should handle all possible errors, here I just return an error if the rate limit is reached.
+/// Also I will consider using API keys to increase the rate limit.
+pub(crate) async fn fetch_yoda_translation_from_api(pokemon_description: &str, config: &Config) -> Result<String, TranslationError> {
+    fetch_style_translation_from_api("yoda", pokemon_description, config).await
+}
+
+/// Fetch the translation from the Shakespeare API.
+/// 
+/// The Shakespeare API has a rate limit of 10 requests per hour and 60 requests per day.
+/// Be careful with the rate limit!
+/// 
+/// The API will return a 429 status code if the rate limit is reached.
+/// 
+/// In real world application, I should handle all possible errors, here I just return an error if the rate limit is reached.
+/// Also I will consider using API keys to increase the rate limit.
+pub(crate) async fn fetch_shakespeare_translation_from_api(pokemon_description: &str, config: &Config) -> Result<String, TranslationError> {
+    fetch_style_translation_from_api("shakespeare", pokemon_description, config).await
+}
+
+/// Fetch the translation in a Fun Translations style, e.g. `pirate`, from the `translate/{style}` API.
+/// 
+/// All the styles share the rate limit of 10 requests per hour and 60 requests per day.
+pub(crate) async fn fetch_style_translation_from_api(style: &str, pokemon_description: &str, config: &Config) -> Result<String, TranslationError> {
+    let client = get_http_client();
+
+    let res = client.post(format!("{}/translate/{}", get_funtranslations_base_url(config), style))
+        .json(&json!({ "text": pokemon_description }))
+        .send()
+        .await?;
+
+    debug!("Fun Translations replied with {}", res.status());
+
+    // If the rate limit is reached, the API will return a 429 status code.
+    // Return an error if the rate limit is reached.
+    if res.status() == 429 {
+        return Err(TranslationError::Request(res.error_for_status().err().unwrap().without_url()));
+    }
+
+    parse_translated_text(&res.text().await?)
+}
+
+/// Get the translated text from the body of a Fun Translations reply.
+/// 
+/// During the outages the API can reply with an HTML page or a different JSON,
+/// those replies are returned as `TranslationError::Malformed` instead of panicking.
+/// The API sometimes replies 200 with an error object, like `{"error": {"code": 429, "message": "..."}}`,
+/// those are returned as `TranslationError::Api` with the code of the error object.
+pub(crate) fn parse_translated_text(body: &str) -> Result<String, TranslationError> {
+    let data: serde_json::Value = serde_json::from_str(body)
+        .map_err(|error| TranslationError::Malformed(format!("invalid JSON: {}", error)))?;
+    if let Some(api_error) = data.get("error") {
+        return Err(TranslationError::Api {
+            code: api_error["code"].as_u64().and_then(|code| u16::try_from(code).ok()).unwrap_or(500),
+            message: api_error["message"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    let translated_text = data["contents"]["translated"]
+        .as_str()
+        .ok_or_else(|| TranslationError::Malformed("missing contents.translated".to_string()))?;
+
+    Ok(collapse_whitespace(translated_text))
+}
+
+/// Get the translation of a description from the dry run fixtures.
+/// 
+/// The fixtures have the translations of the fixture pokemon descriptions, the other descriptions are returned unchanged.
+pub(crate) fn get_fixture_translation(translator: TranslatorKind, pokemon_description: &str) -> String {
+    let translations: HashMap<String, HashMap<String, String>> = serde_json::from_str(include_str!("../../fixtures/translations.json")).expect("Invalid translation fixtures");
+    translations
+        .get(translator.name())
+        .and_then(|translations| translations.get(pokemon_description))
+        .cloned()
+        .unwrap_or(pokemon_description.to_string())
+}
+
+/// Get the Fun Translations base URL.
+/// 
+/// The base URL can be changed with the `FUNTRANSLATIONS_BASE_URL` env variable, or `funtranslations_base_url` of the config file,
+/// the tests use it to call a mock server instead of the real Fun Translations API.
+pub(crate) fn get_funtranslations_base_url(config: &Config) -> String {
+    std::env::var("FUNTRANSLATIONS_BASE_URL").ok()
+        .or_else(|| config.funtranslations_base_url.clone())
+        .unwrap_or("https://api.funtranslations.com".to_string())
+}
+
+/// The errors of the Fun Translations API calls.
+#[derive(Debug)]
+pub(crate) enum TranslationError {
+    /// The request failed or the API replied with an error status, e.g. the rate limit.
+    Request(reqwest::Error),
+    /// The API replied with a body that is not a translation.
+    Malformed(String),
+    /// The API replied 200 with an error object in the body, the code is usually an HTTP status code.
+    Api { code: u16, message: String },
+}
+
+impl TranslationError {
+    /// The status code of the API reply, if the API replied with an error status.
+    pub(crate) fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            TranslationError::Request(error) => error.status(),
+            TranslationError::Malformed(_) => None,
+            TranslationError::Api { code, .. } => reqwest::StatusCode::from_u16(*code).ok(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for TranslationError {
+    fn from(error: reqwest::Error) -> Self {
+        TranslationError::Request(error)
+    }
+}
+
+impl std::fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranslationError::Request(error) => write!(f, "{}", error),
+            TranslationError::Malformed(reason) => write!(f, "malformed translation response: {}", reason),
+            TranslationError::Api { code, message } => write!(f, "translation error {}: {}", code, message),
+        }
+    }
+}
+
+/// The Fun Translations translators, serialized with their name, e.g. `yoda`.
+/// 
+/// Yoda and Shakespeare are the translators of the default rule, see `select_translator`,
+/// the other styles registered in the `TranslatorRegistry` are used only when a request asks for them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TranslatorKind {
+    Yoda,
+    Shakespeare,
+    /// Another registered style, e.g. `pirate`.
+    Style(&'static str),
+}
+
+impl TranslatorKind {
+    /// The name of the translator in the Fun Translations API.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            TranslatorKind::Yoda => "yoda",
+            TranslatorKind::Shakespeare => "shakespeare",
+            TranslatorKind::Style(name) => name,
+        }
+    }
+
+    /// Get the translator registered with the name, `None` if no translator has it.
+    pub(crate) fn from_name(name: &str) -> Option<TranslatorKind> {
+        match name {
+            "yoda" => Some(TranslatorKind::Yoda),
+            "shakespeare" => Some(TranslatorKind::Shakespeare),
+            name => get_translator_registry().get(name).map(|translator| TranslatorKind::Style(translator.name())),
+        }
+    }
+
+    /// The translator to fall back to when this one is rate limited, the other styles fall back to Shakespeare.
+    pub(crate) fn other(&self) -> TranslatorKind {
+        match self {
+            TranslatorKind::Yoda | TranslatorKind::Style(_) => TranslatorKind::Shakespeare,
+            TranslatorKind::Shakespeare => TranslatorKind::Yoda,
+        }
+    }
+}
+
+impl Serialize for TranslatorKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for TranslatorKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        TranslatorKind::from_name(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown translator {}", name)))
+    }
+}
+
+impl JsonSchema for TranslatorKind {
+    fn schema_name() -> String {
+        "TranslatorKind".to_string()
+    }
+
+    /// A string with the names of the registered translators.
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mut schema = String::json_schema(generator).into_object();
+        schema.enum_values = Some(get_translator_registry().names().into_iter().map(serde_json::Value::from).collect());
+        schema.into()
+    }
+}
+
+/// A translator of the texts, registered by name in the `TranslatorRegistry`.
+pub(crate) trait Translator: Send + Sync {
+    /// The name of the translator, the `style` of the requests, e.g. `yoda`.
+    fn name(&self) -> &'static str;
+    /// Translate the text in the style of the translator, calling the API of the config.
+    fn translate<'a>(&'a self, text: &'a str, config: &'a Config) -> BoxFuture<'a, Result<String, TranslationError>>;
+}
+
+/// The Yoda translator of the Fun Translations API.
+pub(crate) struct YodaTranslator;
+
+impl Translator for YodaTranslator {
+    fn name(&self) -> &'static str {
+        "yoda"
+    }
+
+    fn translate<'a>(&'a self, text: &'a str, config: &'a Config) -> BoxFuture<'a, Result<String, TranslationError>> {
+        Box::pin(fetch_yoda_translation_from_api(text, config))
+    }
+}
+
+/// The Shakespeare translator of the Fun Translations API.
+pub(crate) struct ShakespeareTranslator;
+
+impl Translator for ShakespeareTranslator {
+    fn name(&self) -> &'static str {
+        "shakespeare"
+    }
+
+    fn translate<'a>(&'a self, text: &'a str, config: &'a Config) -> BoxFuture<'a, Result<String, TranslationError>> {
+        Box::pin(fetch_shakespeare_translation_from_api(text, config))
+    }
+}
+
+/// Any other style of the Fun Translations API, e.g. `FunTranslationsStyle("pirate")`.
+pub(crate) struct FunTranslationsStyle(pub(crate) &'static str);
+
+impl Translator for FunTranslationsStyle {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn translate<'a>(&'a self, text: &'a str, config: &'a Config) -> BoxFuture<'a, Result<String, TranslationError>> {
+        Box::pin(fetch_style_translation_from_api(self.0, text, config))
+    }
+}
+
+/// The Fun Translations styles registered next to Yoda and Shakespeare.
+pub(crate) const FUN_TRANSLATIONS_STYLES: [&str; 10] = ["pirate", "minion", "dothraki", "valyrian", "klingon", "vulcan", "sith", "gungan", "huttese", "mandalorian"];
+
+/// The translators by name, in the order they were registered.
+pub(crate) struct TranslatorRegistry {
+    translators: Vec<Box<dyn Translator>>,
+}
+
+impl TranslatorRegistry {
+    /// An empty registry.
+    pub(crate) fn new() -> Self {
+        TranslatorRegistry { translators: Vec::new() }
+    }
+
+    /// Register the translator, replacing the one with the same name.
+    pub(crate) fn register(&mut self, translator: Box<dyn Translator>) {
+        self.translators.retain(|registered| registered.name() != translator.name());
+        self.translators.push(translator);
+    }
+
+    /// Get the translator with the name, `None` if it is not registered.
+    pub(crate) fn get(&self, name: &str) -> Option<&dyn Translator> {
+        self.translators.iter().find(|translator| translator.name() == name).map(Box::as_ref)
+    }
+
+    /// The names of the registered translators.
+    pub(crate) fn names(&self) -> Vec<&'static str> {
+        self.translators.iter().map(|translator| translator.name()).collect()
+    }
+}
+
+impl Default for TranslatorRegistry {
+    /// The registry with Yoda, Shakespeare and the `FUN_TRANSLATIONS_STYLES`.
+    fn default() -> Self {
+        let mut registry = TranslatorRegistry::new();
+        registry.register(Box::new(YodaTranslator));
+        registry.register(Box::new(ShakespeareTranslator));
+        for style in FUN_TRANSLATIONS_STYLES {
+            registry.register(Box::new(FunTranslationsStyle(style)));
+        }
+        registry
+    }
+}
+
+/// The translator registry of the server, built on first use.
+static TRANSLATOR_REGISTRY: OnceLock<TranslatorRegistry> = OnceLock::new();
+
+/// Get the translator registry shared by all the requests.
+pub(crate) fn get_translator_registry() -> &'static TranslatorRegistry {
+    TRANSLATOR_REGISTRY.get_or_init(TranslatorRegistry::default)
+}
+
+/// The pokemon data used to select the translator.
+pub(crate) struct TranslatorSelection<'a> {
+    pub(crate) habitat: Option<&'a str>,
+    pub(crate) is_legendary: bool,
+    pub(crate) types: &'a [String],
+}
+
+impl<'a> TranslatorSelection<'a> {
+    pub(crate) fn from_pokemon(pokemon: &'a PokemonResponse) -> TranslatorSelection<'a> {
+        TranslatorSelection {
+            habitat: pokemon.habitat.as_deref(),
+            is_legendary: pokemon.is_legendary,
+            types: &pokemon.types,
+        }
+    }
+}
+
+/// Why `select_translator` chose the translator, or `Requested` for the style asked by the request.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TranslatorReason {
+    /// The primary type of the pokemon has a `TRANSLATOR_BY_TYPE` rule.
+    PrimaryType(String),
+    Legendary,
+    CaveHabitat,
+    Default,
+    Requested,
+}
+
+impl TranslatorReason {
+    /// Explain the choice of the translator, e.g. `legendary pokemon → yoda`.
+    pub(crate) fn explain(&self, translator: TranslatorKind) -> String {
+        let reason = match self {
+            TranslatorReason::PrimaryType(pokemon_type) => format!("{} type rule", pokemon_type),
+            TranslatorReason::Legendary => "legendary pokemon".to_string(),
+            TranslatorReason::CaveHabitat => "cave habitat".to_string(),
+            TranslatorReason::Default => "default".to_string(),
+            TranslatorReason::Requested => "requested style".to_string(),
+        };
+        format!("{} → {}", reason, translator.name())
+    }
+}
+
+/// Select the translator based on the pokemon habitat and if the pokemon is legendary, with the reason of the choice.
+/// 
+/// If the primary type of the pokemon has a rule in `type_rules`, the translator of the rule is used.
+/// Otherwise, if the pokemon is legendary or its habitat is cave, the translation will be in Yoda,
+/// and in Shakespeare for all the others.
+pub(crate) fn select_translator(selection: &TranslatorSelection, type_rules: &HashMap<String, TranslatorKind>) -> (TranslatorKind, TranslatorReason) {
+    if let Some((primary_type, translator)) = selection.types.first().and_then(|primary_type| type_rules.get_key_value(primary_type)) {
+        return (*translator, TranslatorReason::PrimaryType(primary_type.clone()));
+    }
+
+    if selection.is_legendary {
+        (TranslatorKind::Yoda, TranslatorReason::Legendary)
+    } else if selection.habitat == Some("cave") {
+        (TranslatorKind::Yoda, TranslatorReason::CaveHabitat)
+    } else {
+        (TranslatorKind::Shakespeare, TranslatorReason::Default)
+    }
+}
+
+/// Get the translator rules by primary type from the `TRANSLATOR_BY_TYPE` env variable.
+/// 
+/// The rules are comma separated `type=translator` pairs, e.g. `psychic=yoda,fairy=shakespeare`,
+/// the translator can be any registered style, e.g. `water=pirate`.
+/// Without the env variable there are no rules and only the habitat and the legendary flag are used.
+/// The invalid rules are logged and ignored.
+pub(crate) fn get_translator_type_rules() -> HashMap<String, TranslatorKind> {
+    let rules = std::env::var("TRANSLATOR_BY_TYPE").unwrap_or_default();
+    let mut type_rules = HashMap::new();
+
+    for rule in rules.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+        let translator = rule.split_once('=').and_then(|(pokemon_type, translator)| {
+            TranslatorKind::from_name(translator.trim()).map(|translator| (pokemon_type.trim().to_lowercase(), translator))
+        });
+
+        match translator {
+            Some((pokemon_type, translator)) => {
+                type_rules.insert(pokemon_type, translator);
+            }
+            None => warn!("Ignoring the invalid TRANSLATOR_BY_TYPE rule: {}", rule),
+        }
+    }
+
+    type_rules
+}
+
+/// Get the correct translation based on the pokemon habitat, if the pokemon is legendary and its types.
+/// 
+/// The translator is chosen by `select_translator`.
+/// If the `TRANSLATION_FALLBACK` env variable is `true` and the preferred translator is rate limited,
+/// the other translator is used instead. The fallback is another call to the API: the rate limited call is recorded
+/// here and the fallback takes its own token of the quota, the caller records the last call as usual.
+pub(crate) async fn get_translation(pokemon_description: &str, selection: &TranslatorSelection<'_>, translation_quota: &Mutex<TranslationQuota>, config: &Config) -> Result<Translation, TranslationError> {
+    let (translator, _) = select_translator(selection, &get_translator_type_rules());
+    let provider = TranslationProvider::from_env();
+
+    let translated_text = provider.translate(translator, pokemon_description, config).await;
+
+    match translated_text {
+        Err(error) if is_translation_fallback_enabled() && error.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => {
+            let fallback_translator = translator.other();
+            let wait = {
+                let mut translation_quota = translation_quota.lock().unwrap();
+                translation_quota.record_call(false);
+                translation_quota.reserve_call()
+            };
+            // Without a token the fallback would burn the upstream quota past the local rate limit.
+            let Ok(wait) = wait else {
+                info!("The {} translator is rate limited and no token is left for the {} translator", translator.name(), fallback_translator.name());
+                return Err(error);
+            };
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+
+            info!("The {} translator is rate limited, falling back to the {} translator", translator.name(), fallback_translator.name());
+            let translated_text = provider.translate(fallback_translator, pokemon_description, config).await?;
+            Ok(Translation { text: translated_text, translator: fallback_translator })
+        }
+        translated_text => Ok(Translation { text: translated_text?, translator }),
+    }
+}
+
+/// The provider of the translations, selected with the `TRANSLATION_PROVIDER` env variable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TranslationProvider {
+    /// The Fun Translations API, `funtranslations`, the default.
+    FunTranslations,
+    /// The translations of the fixtures, `fixtures`, the texts without a fixture translation are returned unchanged.
+    /// It works without network access, so it is always used in dry run mode, see `is_dry_run`.
+    Fixtures,
+}
+
+impl TranslationProvider {
+    /// Get the provider from the `TRANSLATION_PROVIDER` env variable, the unknown providers fall back to Fun Translations.
+    pub(crate) fn from_env() -> TranslationProvider {
+        if is_dry_run() {
+            return TranslationProvider::Fixtures;
+        }
+
+        match std::env::var("TRANSLATION_PROVIDER").map(|value| value.to_lowercase()).as_deref() {
+            Ok("fixtures") => TranslationProvider::Fixtures,
+            Ok("funtranslations") | Err(_) => TranslationProvider::FunTranslations,
+            Ok(provider) => {
+                warn!("Unknown TRANSLATION_PROVIDER {}, using funtranslations", provider);
+                TranslationProvider::FunTranslations
+            }
+        }
+    }
+
+    /// Translate the text in the style of the translator.
+    pub(crate) async fn translate(self, translator: TranslatorKind, text: &str, config: &Config) -> Result<String, TranslationError> {
+        match self {
+            TranslationProvider::FunTranslations => fetch_translation_from_api(translator, text, config).await,
+            TranslationProvider::Fixtures => Ok(get_fixture_translation(translator, text)),
+        }
+    }
+}
+
+/// Fetch the translation from the Fun Translations API with the translator.
+pub(crate) async fn fetch_translation_from_api(translator: TranslatorKind, pokemon_description: &str, config: &Config) -> Result<String, TranslationError> {
+    let mut span = telemetry::Span::start("fetch_translation_from_api");
+    span.record("translator", translator.name());
+
+    // Suppose to be safe to unwrap, the translators are only built from the names of the registry.
+    let translated_text = get_translator_registry().get(translator.name()).unwrap().translate(pokemon_description, config).await;
+
+    // The fetchers only fail with a status code for the error replies, like the rate limit.
+    match &translated_text {
+        Ok(_) => span.record_upstream_status(200),
+        Err(error) => {
+            if let Some(status) = error.status() {
+                span.record_upstream_status(status.as_u16());
+            }
+        }
+    }
+    span.record_result(&translated_text);
+
+    translated_text
+}
+
+/// Check if the translation fallback is enabled with the `TRANSLATION_FALLBACK` env variable.
+pub(crate) fn is_translation_fallback_enabled() -> bool {
+    std::env::var("TRANSLATION_FALLBACK").map(|value| value == "true").unwrap_or(false)
+}
+
+/// Count the translation calls made in the current hour and day, and rate limit them locally.
+/// 
+/// The Fun Translations API has a rate limit of 10 requests per hour and 60 requests per day,
+/// the counters are used to log the estimated remaining quota after each translation call.
+/// The limits can be changed with the `TRANSLATION_QUOTA_PER_HOUR` and `TRANSLATION_QUOTA_PER_DAY` env variables.
+/// 
+/// The calls take a token of a token bucket, see `reserve_call`, so the hourly quota is not burnt by a burst of requests.
+pub(crate) struct TranslationQuota {
+    pub(crate) hour_started_at: Instant,
+    pub(crate) hour_calls: u32,
+    pub(crate) day_started_at: Instant,
+    pub(crate) day_calls: u32,
+    pub(crate) tokens: f64,
+    pub(crate) tokens_updated_at: Instant,
+}
+
+impl TranslationQuota {
+    pub(crate) fn new() -> Self {
+        TranslationQuota {
+            hour_started_at: Instant::now(),
+            hour_calls: 0,
+            day_started_at: Instant::now(),
+            day_calls: 0,
+            tokens: get_translation_tokens_per_hour(),
+            tokens_updated_at: Instant::now(),
+        }
+    }
+
+    /// Take a token for a translation call from the token bucket.
+    /// 
+    /// The bucket holds `TRANSLATION_QUOTA_PER_HOUR` tokens and it is refilled evenly over the hour.
+    /// Without a token left the call is queued: `Ok` has how long to wait for the token,
+    /// up to `TRANSLATION_QUEUE_SECONDS` (5 by default), and the token is reserved meanwhile.
+    /// If the wait is longer the call is rejected: `Err` has how long until a token is available.
+    pub(crate) fn reserve_call(&mut self) -> Result<Duration, Duration> {
+        let capacity = get_translation_tokens_per_hour();
+        let tokens_per_second = capacity / (60.0 * 60.0);
+        self.tokens = (self.tokens + self.tokens_updated_at.elapsed().as_secs_f64() * tokens_per_second).min(capacity);
+        self.tokens_updated_at = Instant::now();
+
+        let wait = Duration::from_secs_f64(((1.0 - self.tokens) / tokens_per_second).max(0.0));
+        if wait > Duration::from_secs(get_env_u32("TRANSLATION_QUEUE_SECONDS", 5) as u64) {
+            return Err(wait);
+        }
+
+        // The reserved tokens can take the bucket below zero, the next calls wait for them to be refilled.
+        self.tokens -= 1.0;
+        Ok(wait)
+    }
+
+    /// Record a translation call and log the estimated remaining quota.
+    pub(crate) fn record_call(&mut self, is_success: bool) {
+        if self.hour_started_at.elapsed() >= Duration::from_secs(60 * 60) {
+            self.hour_started_at = Instant::now();
+            self.hour_calls = 0;
+        }
+        if self.day_started_at.elapsed() >= Duration::from_secs(24 * 60 * 60) {
+            self.day_started_at = Instant::now();
+            self.day_calls = 0;
+        }
+
+        self.hour_calls += 1;
+        self.day_calls += 1;
+
+        info!(
+            "Translation call {}: {} calls this hour, {} calls this day, estimated remaining quota {} this hour and {} this day",
+            if is_success { "succeeded" } else { "failed" },
+            self.hour_calls,
+            self.day_calls,
+            self.remaining_hour_calls(),
+            self.remaining_day_calls()
+        );
+    }
+
+    pub(crate) fn remaining_hour_calls(&self) -> u32 {
+        get_env_u32("TRANSLATION_QUOTA_PER_HOUR", 10).saturating_sub(self.hour_calls)
+    }
+
+    pub(crate) fn remaining_day_calls(&self) -> u32 {
+        get_env_u32("TRANSLATION_QUOTA_PER_DAY", 60).saturating_sub(self.day_calls)
+    }
+}
+
+/// Get the size of the translation token bucket, the hourly quota, at least one token.
+pub(crate) fn get_translation_tokens_per_hour() -> f64 {
+    get_env_u32("TRANSLATION_QUOTA_PER_HOUR", 10).max(1) as f64
+}
+
+/// The states of the translation circuit breaker.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CircuitState {
+    /// The translation requests are sent.
+    Closed,
+    /// The translation requests fail immediately, without calling the API.
+    Open,
+    /// The cooldown is over, the next translation request tests if the API recovered.
+    HalfOpen,
+}
+
+/// Stop calling the Fun Translations API while it keeps failing.
+/// 
+/// After `CIRCUIT_BREAKER_FAILURES` consecutive failures (5 by default) the circuit opens for
+/// `CIRCUIT_BREAKER_COOLDOWN_SECONDS` (60 by default), then it is half-open: a single test request is sent,
+/// its success closes the circuit and its failure opens it again for another cooldown.
+pub(crate) struct CircuitBreaker {
+    pub(crate) consecutive_failures: u32,
+    pub(crate) opened_at: Option<Instant>,
+    /// When the test request of the half-open circuit was let through, until its result is recorded.
+    pub(crate) probe_started_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new() -> Self {
+        CircuitBreaker {
+            consecutive_failures: 0,
+            opened_at: None,
+            probe_started_at: None,
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() >= get_circuit_breaker_cooldown() => CircuitState::HalfOpen,
+            Some(_) => CircuitState::Open,
+        }
+    }
+
+    /// Check if a translation request can be sent, the half-open circuit lets a single test request through.
+    /// 
+    /// The other requests are rejected until the result of the test request is recorded.
+    /// A test request without a result, e.g. cancelled by the request timeout, is replaced after another cooldown.
+    pub(crate) fn allow_request(&mut self) -> bool {
+        match self.state() {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                if self.probe_started_at.is_some_and(|started_at| started_at.elapsed() < get_circuit_breaker_cooldown()) {
+                    return false;
+                }
+                self.probe_started_at = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Give back the test request of the half-open circuit when it is not sent after all, e.g. throttled by the quota,
+    /// so the next request takes it instead of waiting for another cooldown.
+    pub(crate) fn release_probe(&mut self) {
+        self.probe_started_at = None;
+    }
+
+    /// Record the result of a translation request and open or close the circuit.
+    pub(crate) fn record_result(&mut self, is_success: bool) {
+        self.probe_started_at = None;
+        if is_success {
+            self.consecutive_failures = 0;
+            self.opened_at = None;
+            return;
+        }
+
+        self.consecutive_failures += 1;
+        if self.state() == CircuitState::HalfOpen || self.consecutive_failures >= get_env_u32("CIRCUIT_BREAKER_FAILURES", 5) {
+            info!("Translation circuit opened after {} consecutive failures", self.consecutive_failures);
+            self.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Get the cooldown of the open circuit from the `CIRCUIT_BREAKER_COOLDOWN_SECONDS` env variable.
+pub(crate) fn get_circuit_breaker_cooldown() -> Duration {
+    Duration::from_secs(get_env_u32("CIRCUIT_BREAKER_COOLDOWN_SECONDS", 60) as u64)
+}
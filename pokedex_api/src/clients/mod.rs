@@ -0,0 +1,56 @@
+//! The clients of the external APIs, PokeAPI and Fun Translations.
+//!
+//! With `DRY_RUN=true` both clients use the fixtures in `pokedex_api/fixtures` instead, see `is_dry_run`.
+
+pub(crate) mod funtranslations;
+pub(crate) mod pokeapi;
+
+use crate::clients::funtranslations::{TranslatorKind, fetch_translation_from_api};
+use crate::clients::pokeapi::fetch_pokemon_from_api;
+use crate::config::Config;
+
+/// Check if the dry run mode is enabled with the `DRY_RUN` env variable.
+/// 
+/// In dry run mode the pokemon, the species list and the translations come from the fixtures embedded in the binary,
+/// so the server runs without network access, e.g. for the frontend development.
+/// The fixtures have a handful of pokemon, the other names are not found.
+pub(crate) fn is_dry_run() -> bool {
+    std::env::var("DRY_RUN").map(|value| value == "true").unwrap_or(false)
+}
+
+/// Create the reqwest client for the external APIs.
+/// 
+/// The client sends a descriptive `User-Agent` header, `pokedex-api/<version>` by default,
+/// it can be changed with the `USER_AGENT` env variable.
+/// The rustemon client doesn't allow to change its user agent, so the PokeAPI calls use the rustemon one.
+pub(crate) fn get_http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(get_user_agent())
+        .build()
+        .unwrap() // Suppose to be safe to unwrap, the client only fails to build if the TLS backend can't be initialized
+}
+
+/// Get the user agent of the outbound requests from the `USER_AGENT` env variable.
+pub(crate) fn get_user_agent() -> String {
+    std::env::var("USER_AGENT").unwrap_or(format!("pokedex-api/{}", env!("CARGO_PKG_VERSION")))
+}
+
+/// Look up a pokemon on the PokeAPI and translate a text with Fun Translations, printing if each call passed.
+/// 
+/// Used by `--check` to validate a deployment without starting the server, with the upstream URLs of the config,
+/// returns `false` if any call failed.
+pub async fn check_upstreams(config: &Config) -> bool {
+    let pokemon = fetch_pokemon_from_api("pikachu".to_string(), config).await;
+    match &pokemon {
+        Ok(_) => println!("PokeAPI: pass"),
+        Err(error) => println!("PokeAPI: fail ({})", error),
+    }
+
+    let translation = fetch_translation_from_api(TranslatorKind::Shakespeare, "Checking the translations are reachable.", config).await;
+    match &translation {
+        Ok(_) => println!("Fun Translations: pass"),
+        Err(error) => println!("Fun Translations: fail ({})", error),
+    }
+
+    pokemon.is_ok() && translation.is_ok()
+}
@@ -0,0 +1,474 @@
+//! The PokeAPI client, built on rustemon, for the pokemon, their species and the species list.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use rustemon::{model::resource::FlavorText, Follow};
+use tracing::warn;
+use crate::clients::{get_http_client, is_dry_run};
+use crate::config::Config;
+use crate::models::{AbilityEffect, CriesResponse, Descriptions, EvolutionStage, FlavorTextEntry, MoveDetails, PokemonAbility, PokemonMove, PokemonResponse, PokemonSprites, PokemonStats};
+use crate::routes::DESCRIPTION_LANGUAGES;
+use crate::telemetry;
+use crate::utils::collapse_whitespace;
+
+/// Fetch the pokemon data from the PokeAPI.
+/// 
+/// In real world application, I should handle all possible errors, here I just return an error if the pokemon is not found.
+/// With `DRY_RUN=true` the pokemon comes from the fixtures instead, see `is_dry_run`.
+/// The call is traced with a span, with its timing in the log lines, see `telemetry`.
+pub(crate) async fn fetch_pokemon_from_api(pokemon_name_to_search: String, config: &Config) -> Result<PokemonResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let mut span = telemetry::Span::start("fetch_pokemon_from_api");
+    span.record("pokemon.name", &pokemon_name_to_search);
+
+    let pokemon = request_pokemon_from_api(pokemon_name_to_search, config).await;
+    span.record_result(&pokemon);
+
+    pokemon
+}
+
+/// Request the pokemon and its species from the PokeAPI, see `fetch_pokemon_from_api`.
+/// 
+/// The name can be the national dex number, in the dry run mode only the names are in the fixtures.
+pub(crate) async fn request_pokemon_from_api(pokemon_name_to_search: String, config: &Config) -> Result<PokemonResponse, Box<dyn std::error::Error + Send + Sync>> {
+    if is_dry_run() {
+        return get_fixture_pokemon(&pokemon_name_to_search).ok_or_else(|| PokemonNotFound(pokemon_name_to_search).into());
+    }
+
+    // The national dex numbers, like `25`, are looked up by id, the pokemon has its canonical name anyway.
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = match pokemon_name_to_search.parse::<i64>() {
+        Ok(id) => rustemon::pokemon::pokemon::get_by_id(id, &rustemon_client).await,
+        Err(_) => rustemon::pokemon::pokemon::get_by_name(&pokemon_name_to_search, &rustemon_client).await,
+    };
+    // Only the 404 of PokeAPI means the pokemon doesn't exist, the other errors are PokeAPI failing.
+    let pokemon = match pokemon {
+        Ok(pokemon) => pokemon,
+        Err(rustemon::error::Error::Reqwest(error)) if error.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+            return Err(PokemonNotFound(pokemon_name_to_search).into());
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    let species_resource = pokemon.species;
+    let species = species_resource.follow(&rustemon_client).await.map_err(SpeciesUnavailable)?;
+
+    // Some pokemon, usually the newest ones, have no english description yet.
+    let descriptions = get_descriptions_by_language(species.flavor_text_entries);
+    let pokemon_description = get_description_for_language(&descriptions, &[], get_default_language().as_deref());
+    let description_available = pokemon_description.is_some();
+
+    let res = PokemonResponse {
+        description_available: (!description_available).then_some(false),
+        description: pokemon_description.or_else(|| get_default_description(&pokemon.name)),
+        name: pokemon.name,
+        // Some pokemon, like the ones introduced after Gen 3, have no habitat.
+        habitat: species.habitat.map(|habitat| habitat.name),
+        is_legendary: species.is_legendary,
+        types: pokemon.types.into_iter().map(|pokemon_type| pokemon_type.type_.name).collect(),
+        ..Default::default()
+    };
+
+    Ok(res)
+}
+
+/// Fetch the moves of the pokemon from the PokeAPI, without the data of the moves.
+/// 
+/// PokeAPI returns one entry for each version group a move is learnable in, so a pokemon can have thousands of entries.
+/// Here I keep a single entry for each move and learn method, with the level of the most recent version group,
+/// or of the given version group only.
+/// The type, the power and the accuracy are fetched for each move, see `fetch_move_details_from_api`.
+pub(crate) async fn fetch_pokemon_moves_from_api(pokemon_name_to_search: String, version_group: Option<String>, config: &Config) -> Result<Vec<PokemonMove>, Box<dyn std::error::Error + Send + Sync>> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+
+    let mut moves = Vec::with_capacity(pokemon.moves.len());
+    for pokemon_move in pokemon.moves {
+        let mut learn_methods: Vec<&str> = Vec::new();
+
+        // The most recent version groups are at the end of the list.
+        for version_group_detail in pokemon_move.version_group_details.iter().rev() {
+            if version_group.as_ref().is_some_and(|version_group| version_group_detail.version_group.name != *version_group) {
+                continue;
+            }
+            let learn_method = version_group_detail.move_learn_method.name.as_str();
+            if learn_methods.contains(&learn_method) {
+                continue;
+            }
+            learn_methods.push(learn_method);
+
+            moves.push(PokemonMove {
+                name: pokemon_move.move_.name.clone(),
+                learn_method: learn_method.to_string(),
+                level: (learn_method == "level-up").then_some(version_group_detail.level_learned_at),
+                version_group: version_group_detail.version_group.name.clone(),
+                move_type: None,
+                power: None,
+                accuracy: None,
+            });
+        }
+    }
+
+    Ok(moves)
+}
+
+/// Get the pokemon from the PokeAPI, for the fetchers of its sub-resources, e.g. its moves.
+/// 
+/// The 404 of PokeAPI is returned as `PokemonNotFound`, the other errors are PokeAPI failing and are returned as they are.
+/// The name can be the national dex number, like in `request_pokemon_from_api`.
+/// In the dry run mode PokeAPI is not called: the fixtures have no sub-resources, so the fixture pokemon
+/// are `DryRunUnavailable` and the others are `PokemonNotFound`.
+pub(crate) async fn get_pokemon_from_api(pokemon_name_to_search: &str, rustemon_client: &rustemon::client::RustemonClient) -> Result<rustemon::model::pokemon::Pokemon, Box<dyn std::error::Error + Send + Sync>> {
+    if is_dry_run() {
+        return match get_fixture_pokemon(pokemon_name_to_search) {
+            Some(_) => Err(DryRunUnavailable(pokemon_name_to_search.to_string()).into()),
+            None => Err(PokemonNotFound(pokemon_name_to_search.to_string()).into()),
+        };
+    }
+
+    let pokemon = match pokemon_name_to_search.parse::<i64>() {
+        Ok(id) => rustemon::pokemon::pokemon::get_by_id(id, rustemon_client).await,
+        Err(_) => rustemon::pokemon::pokemon::get_by_name(pokemon_name_to_search, rustemon_client).await,
+    };
+    match pokemon {
+        Ok(pokemon) => Ok(pokemon),
+        Err(rustemon::error::Error::Reqwest(error)) if error.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+            Err(PokemonNotFound(pokemon_name_to_search.to_string()).into())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Fetch the type, the power and the accuracy of the move from the PokeAPI.
+pub(crate) async fn fetch_move_details_from_api(move_name: String, config: &Config) -> Result<MoveDetails, rustemon::error::Error> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon_move = rustemon::moves::move_::get_by_name(&move_name, &rustemon_client).await?;
+
+    Ok(MoveDetails {
+        move_type: pokemon_move.type_.name,
+        power: pokemon_move.power,
+        accuracy: pokemon_move.accuracy,
+    })
+}
+
+/// Fetch the sprite URLs of the pokemon from the PokeAPI.
+/// 
+/// The official artwork is nested in the `other` sprites, so the sprites are read from their JSON, as PokeAPI returns them.
+pub(crate) async fn fetch_pokemon_sprites_from_api(pokemon_name_to_search: String, config: &Config) -> Result<PokemonSprites, Box<dyn std::error::Error + Send + Sync>> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+
+    let sprites = serde_json::to_value(&pokemon.sprites).unwrap_or_default();
+    let sprite = |pointer: &str| sprites.pointer(pointer).and_then(|url| url.as_str()).map(str::to_string);
+    Ok(PokemonSprites {
+        front_default: sprite("/front_default"),
+        front_shiny: sprite("/front_shiny"),
+        official_artwork: sprite("/other/official-artwork/front_default"),
+        official_artwork_shiny: sprite("/other/official-artwork/front_shiny"),
+    })
+}
+
+/// Fetch the base stats of the pokemon from the PokeAPI.
+/// 
+/// The stats are matched by their PokeAPI name, e.g. `special-attack`, the missing ones are 0.
+pub(crate) async fn fetch_pokemon_stats_from_api(pokemon_name_to_search: String, config: &Config) -> Result<PokemonStats, Box<dyn std::error::Error + Send + Sync>> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+
+    let mut stats = PokemonStats::default();
+    for pokemon_stat in pokemon.stats {
+        let stat = match pokemon_stat.stat.name.as_str() {
+            "hp" => &mut stats.hp,
+            "attack" => &mut stats.attack,
+            "defense" => &mut stats.defense,
+            "special-attack" => &mut stats.special_attack,
+            "special-defense" => &mut stats.special_defense,
+            "speed" => &mut stats.speed,
+            _ => continue,
+        };
+        *stat = pokemon_stat.base_stat;
+        stats.total += pokemon_stat.base_stat;
+    }
+    Ok(stats)
+}
+
+/// Fetch the abilities of the pokemon from the PokeAPI, in slot order, without their effects.
+/// 
+/// The effects are fetched for each ability, see `fetch_ability_effect_from_api`.
+pub(crate) async fn fetch_pokemon_abilities_from_api(pokemon_name_to_search: String, config: &Config) -> Result<Vec<PokemonAbility>, Box<dyn std::error::Error + Send + Sync>> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+
+    let mut abilities = pokemon.abilities;
+    abilities.sort_by_key(|pokemon_ability| pokemon_ability.slot);
+    Ok(abilities
+        .into_iter()
+        .map(|pokemon_ability| PokemonAbility { name: pokemon_ability.ability.name, is_hidden: pokemon_ability.is_hidden, effect: None, short_effect: None })
+        .collect())
+}
+
+/// Fetch the english effect texts of the ability from the PokeAPI.
+pub(crate) async fn fetch_ability_effect_from_api(ability_name: String, config: &Config) -> Result<AbilityEffect, rustemon::error::Error> {
+    let rustemon_client = get_rustemon_client(config);
+    let ability = rustemon::pokemon::ability::get_by_name(&ability_name, &rustemon_client).await?;
+
+    let effect_entry = ability.effect_entries.into_iter().find(|entry| entry.language.name == "en");
+    Ok(AbilityEffect {
+        effect: effect_entry.as_ref().map(|entry| collapse_whitespace(&entry.effect)),
+        short_effect: effect_entry.as_ref().map(|entry| collapse_whitespace(&entry.short_effect)),
+    })
+}
+
+/// Fetch the descriptions of the pokemon in all the languages from the PokeAPI.
+/// 
+/// In the dry run mode the fixtures only have the english description.
+pub(crate) async fn fetch_pokemon_descriptions_from_api(pokemon_name_to_search: String, config: &Config) -> Result<Descriptions, Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(pokemon) = is_dry_run().then(|| get_fixture_pokemon(&pokemon_name_to_search)).flatten() {
+        return Ok(pokemon.description.map(|description| ("en".to_string(), description)).into_iter().collect());
+    }
+
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+    let species = pokemon.species.follow(&rustemon_client).await?;
+
+    Ok(get_descriptions_by_language(species.flavor_text_entries))
+}
+
+/// Fetch all the flavor texts of the pokemon from the PokeAPI, in the order of the species.
+pub(crate) async fn fetch_pokemon_flavor_texts_from_api(pokemon_name_to_search: String, config: &Config) -> Result<Vec<FlavorTextEntry>, Box<dyn std::error::Error + Send + Sync>> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+    let species = pokemon.species.follow(&rustemon_client).await?;
+
+    let flavor_texts = species.flavor_text_entries
+        .into_iter()
+        .map(|entry| FlavorTextEntry {
+            description: collapse_whitespace(&entry.flavor_text),
+            language: entry.language.name,
+            version: entry.version.map(|version| version.name),
+        })
+        .collect();
+    Ok(flavor_texts)
+}
+
+/// Fetch the URLs of the pokemon cries from the PokeAPI.
+pub(crate) async fn fetch_pokemon_cries_from_api(pokemon_name_to_search: String, config: &Config) -> Result<CriesResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+
+    Ok(CriesResponse {
+        name: pokemon.name,
+        latest: pokemon.cries.latest,
+        legacy: pokemon.cries.legacy,
+    })
+}
+
+/// Fetch the evolution chain of the pokemon species from the PokeAPI, as the list of its stages.
+/// 
+/// The chain is walked breadth first, so the stages are ordered by `stage` and each stage comes after the one it evolves from.
+/// When a stage can evolve in several ways, e.g. in different games, only the first one is returned.
+pub(crate) async fn fetch_pokemon_evolution_chain_from_api(pokemon_name_to_search: String, config: &Config) -> Result<Vec<EvolutionStage>, Box<dyn std::error::Error + Send + Sync>> {
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon = get_pokemon_from_api(&pokemon_name_to_search, &rustemon_client).await?;
+    let species = pokemon.species.follow(&rustemon_client).await?;
+
+    // Some species, like the newest ones, can be missing the chain: they don't evolve as far as PokeAPI knows.
+    let Some(evolution_chain) = species.evolution_chain else {
+        return Ok(vec![EvolutionStage { name: species.name, stage: 1, evolves_from: None, trigger: None, item: None, min_level: None }]);
+    };
+    let evolution_chain = evolution_chain.follow(&rustemon_client).await?;
+
+    let mut stages = Vec::new();
+    let mut links = VecDeque::from([(&evolution_chain.chain, 1, None)]);
+    while let Some((link, stage, evolves_from)) = links.pop_front() {
+        let evolution_detail = link.evolution_details.first();
+        stages.push(EvolutionStage {
+            name: link.species.name.clone(),
+            stage,
+            evolves_from,
+            trigger: evolution_detail.map(|detail| detail.trigger.name.clone()),
+            item: evolution_detail.and_then(|detail| detail.item.as_ref()).map(|item| item.name.clone()),
+            min_level: evolution_detail.and_then(|detail| detail.min_level),
+        });
+        links.extend(link.evolves_to.iter().map(|evolution| (evolution, stage + 1, Some(link.species.name.clone()))));
+    }
+
+    Ok(stages)
+}
+
+/// Fetch the names of all the pokemon species from the PokeAPI.
+pub(crate) async fn fetch_species_names_from_api(config: &Config) -> Result<Vec<String>, rustemon::error::Error> {
+    if is_dry_run() {
+        return Ok(get_fixture_pokemon_list().into_iter().map(|pokemon| pokemon.name).collect());
+    }
+
+    let rustemon_client = get_rustemon_client(config);
+    let species = rustemon::pokemon::pokemon_species::get_all_entries(&rustemon_client).await?;
+
+    Ok(species.into_iter().map(|species| species.name).collect())
+}
+
+/// Fetch the names of the pokemon species living in the habitat from the PokeAPI.
+pub(crate) async fn fetch_habitat_species_from_api(habitat: String, config: &Config) -> Result<Vec<String>, rustemon::error::Error> {
+    if is_dry_run() {
+        return Ok(get_fixture_pokemon_list().into_iter().filter(|pokemon| pokemon.habitat.as_deref() == Some(habitat.as_str())).map(|pokemon| pokemon.name).collect());
+    }
+
+    let rustemon_client = get_rustemon_client(config);
+    let habitat = rustemon::pokemon::pokemon_habitat::get_by_name(&habitat, &rustemon_client).await?;
+
+    Ok(habitat.pokemon_species.into_iter().map(|species| species.name).collect())
+}
+
+/// Fetch the names of the pokemon of the type from the PokeAPI, including the alternate forms.
+pub(crate) async fn fetch_type_pokemon_from_api(pokemon_type: String, config: &Config) -> Result<Vec<String>, rustemon::error::Error> {
+    if is_dry_run() {
+        return Ok(get_fixture_pokemon_list().into_iter().filter(|pokemon| pokemon.types.contains(&pokemon_type)).map(|pokemon| pokemon.name).collect());
+    }
+
+    let rustemon_client = get_rustemon_client(config);
+    let pokemon_type = rustemon::pokemon::type_::get_by_name(&pokemon_type, &rustemon_client).await?;
+
+    Ok(pokemon_type.pokemon.into_iter().map(|type_pokemon| type_pokemon.pokemon.name).collect())
+}
+
+/// Get the pokemon of the dry run fixtures.
+pub(crate) fn get_fixture_pokemon_list() -> Vec<PokemonResponse> {
+    serde_json::from_str(include_str!("../../fixtures/pokemon.json")).expect("Invalid pokemon fixtures")
+}
+
+/// Get a pokemon of the dry run fixtures by name.
+pub(crate) fn get_fixture_pokemon(pokemon_name: &str) -> Option<PokemonResponse> {
+    get_fixture_pokemon_list().into_iter().find(|pokemon| pokemon.name == pokemon_name)
+}
+
+/// Create the rustemon client for the PokeAPI.
+/// 
+/// The PokeAPI base URL can be changed with the `POKEAPI_BASE_URL` env variable (e.g. `http://localhost:8080/api/v2/`),
+/// or `pokeapi_base_url` of the config file, the tests use it to call a mock server instead of the real PokeAPI.
+pub(crate) fn get_rustemon_client(config: &Config) -> rustemon::client::RustemonClient {
+    let base_url = get_pokeapi_base_url(config).and_then(|url| reqwest::Url::parse(&url).ok());
+
+    match base_url {
+        Some(base_url) => rustemon::client::RustemonClientBuilder::default()
+            .with_environment(rustemon::client::Environment::Custom(base_url))
+            .try_build()
+            .unwrap(), // Suppose to be safe to unwrap, the client only fails to build with an invalid cache configuration
+        None => rustemon::client::RustemonClient::default(),
+    }
+}
+
+/// Get the PokeAPI base URL of the `POKEAPI_BASE_URL` env variable, or of the config file, `None` for the public PokeAPI.
+pub(crate) fn get_pokeapi_base_url(config: &Config) -> Option<String> {
+    std::env::var("POKEAPI_BASE_URL").ok().or_else(|| config.pokeapi_base_url.clone())
+}
+
+/// Check that PokeAPI is reachable, listing one pokemon within the timeout.
+/// 
+/// The check is a plain HTTP call, without the rustemon cache, so it really reaches PokeAPI.
+/// In dry run mode PokeAPI is not called at all, so it is always reachable.
+pub(crate) async fn check_pokeapi_reachable(timeout: Duration, config: &Config) -> bool {
+    if is_dry_run() {
+        return true;
+    }
+
+    let base_url = get_pokeapi_base_url(config).unwrap_or("https://pokeapi.co/api/v2/".to_string());
+    let res = get_http_client().get(format!("{}pokemon?limit=1", base_url)).timeout(timeout).send().await;
+    match res {
+        Ok(res) if res.status().is_success() => true,
+        Ok(res) => {
+            warn!("PokeAPI is not ready, it replied with {}", res.status());
+            false
+        }
+        Err(error) => {
+            warn!("PokeAPI is not reachable: {}", error);
+            false
+        }
+    }
+}
+
+/// Get the description in the first of the languages the pokemon has a description in.
+/// 
+/// The languages are walked in order, then the default language, and the english description is the last fallback,
+/// `None` if the pokemon has no description in any of them.
+pub(crate) fn get_description_for_language(descriptions: &Descriptions, languages: &[String], default_language: Option<&str>) -> Option<String> {
+    get_description_language(descriptions, languages, default_language).and_then(|language| descriptions.get(language).cloned())
+}
+
+/// Get the language of the description chosen by `get_description_for_language`, e.g. `en` when the pokemon
+/// has none of the requested languages.
+pub(crate) fn get_description_language<'a>(descriptions: &Descriptions, languages: &'a [String], default_language: Option<&'a str>) -> Option<&'a str> {
+    languages
+        .iter()
+        .map(String::as_str)
+        .chain(default_language)
+        .chain(std::iter::once("en"))
+        .find(|language| descriptions.contains_key(*language))
+}
+
+/// Get the default language of the descriptions from the `DEFAULT_LANGUAGE` env variable, e.g. `ja`.
+/// 
+/// The default language is used when the request has no `lang` query parameter, english is still the fallback
+/// for the pokemon without a description in it. An unknown language is ignored with a warning.
+pub(crate) fn get_default_language() -> Option<String> {
+    let language = std::env::var("DEFAULT_LANGUAGE").ok()?;
+    let default_language = DESCRIPTION_LANGUAGES.iter().find(|known_language| known_language.eq_ignore_ascii_case(&language));
+    if default_language.is_none() {
+        warn!("Unknown DEFAULT_LANGUAGE {}, using en", language);
+    }
+    default_language.map(|default_language| default_language.to_string())
+}
+
+/// Get the placeholder description of the pokemon without an english description.
+/// 
+/// The template is read from the `DEFAULT_DESCRIPTION_TEMPLATE` env variable, e.g. `No description available for {name}.`,
+/// with `{name}` replaced by the pokemon name. Without the env variable the description stays `None`.
+pub(crate) fn get_default_description(pokemon_name: &str) -> Option<String> {
+    std::env::var("DEFAULT_DESCRIPTION_TEMPLATE").ok().map(|template| template.replace("{name}", pokemon_name))
+}
+
+/// Get the first description of each language from the flavor text entries.
+pub(crate) fn get_descriptions_by_language(language_array: Vec<FlavorText>) -> Descriptions {
+    let mut descriptions = HashMap::new();
+    for entry in language_array {
+        descriptions
+            .entry(entry.language.name)
+            .or_insert_with(|| collapse_whitespace(&entry.flavor_text));
+    }
+    descriptions
+}
+
+/// The error of the pokemon found on PokeAPI whose species can't be fetched, e.g. during a partial outage.
+#[derive(Debug)]
+pub(crate) struct SpeciesUnavailable(pub(crate) rustemon::error::Error);
+
+impl std::fmt::Display for SpeciesUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to fetch the species: {}", self.0)
+    }
+}
+
+impl std::error::Error for SpeciesUnavailable {}
+
+/// The error of the pokemon PokeAPI replied with a 404 to, or not in the fixtures in the dry run mode.
+#[derive(Debug)]
+pub(crate) struct PokemonNotFound(pub(crate) String);
+
+impl std::fmt::Display for PokemonNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the pokemon {} doesn't exist", self.0)
+    }
+}
+
+impl std::error::Error for PokemonNotFound {}
+
+/// The error of the pokemon sub-resources in the dry run mode, PokeAPI is not called and the fixtures don't have them.
+#[derive(Debug)]
+pub(crate) struct DryRunUnavailable(pub(crate) String);
+
+impl std::fmt::Display for DryRunUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the fixtures of the dry run mode have only the data of the pokemon {}", self.0)
+    }
+}
+
+impl std::error::Error for DryRunUnavailable {}
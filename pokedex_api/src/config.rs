@@ -0,0 +1,148 @@
+//! The configuration of the server, the command line arguments and the format of the log lines.
+
+use std::net::IpAddr;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+/// The server configuration used by `main` to wire the routes.
+/// 
+/// The values come from the code defaults, overridden by the optional `config.toml` file
+/// (the path can be changed with the `CONFIG_FILE` env variable), overridden by the env variables.
+/// - listen_address: `LISTEN_ADDRESS`, default 0.0.0.0 for docker
+/// - port: `PORT`, default 3030
+/// - base_path: `BASE_PATH`, the prefix of all the routes, e.g. `/api/v1`, default no prefix
+/// - cors_allowed_origins: `CORS_ALLOWED_ORIGINS` as a comma separated list, default any origin
+/// 
+/// The other settings are read where they are used, their env variables still override the file,
+/// e.g. `get_rustemon_client` reads `POKEAPI_BASE_URL` and falls back to `pokeapi_base_url`.
+/// The routes pass the config down to the caches and the clients, see `build_routes`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen_address: IpAddr,
+    pub port: u16,
+    pub base_path: String,
+    pub cors_allowed_origins: Vec<String>,
+    pub pokeapi_base_url: Option<String>,
+    pub funtranslations_base_url: Option<String>,
+    pub request_timeout_seconds: Option<u32>,
+    pub pokemon_cache_soft_ttl_seconds: Option<u32>,
+    pub pokemon_cache_hard_ttl_seconds: Option<u32>,
+    pub translation_cache_ttl_seconds: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            listen_address: IpAddr::from([0, 0, 0, 0]),
+            port: 3030,
+            base_path: String::new(),
+            cors_allowed_origins: Vec::new(),
+            pokeapi_base_url: None,
+            funtranslations_base_url: None,
+            request_timeout_seconds: None,
+            pokemon_cache_soft_ttl_seconds: None,
+            pokemon_cache_hard_ttl_seconds: None,
+            translation_cache_ttl_seconds: None,
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration from the file and the env variables, the file is optional.
+    /// 
+    /// A file with unknown keys or wrong types is an error, so a typo in the file doesn't go unnoticed.
+    pub fn load(config_path: &str) -> Result<Config, String> {
+        let mut config: Config = match std::fs::read_to_string(config_path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|error| format!("Invalid config file {}: {}", config_path, error))?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(error) => return Err(format!("Failed to read the config file {}: {}", config_path, error)),
+        };
+
+        if let Ok(listen_address) = std::env::var("LISTEN_ADDRESS") {
+            config.listen_address = listen_address.parse().map_err(|_| format!("Invalid LISTEN_ADDRESS env variable: {}", listen_address))?;
+        }
+        if let Ok(port) = std::env::var("PORT") {
+            config.port = port.parse().map_err(|_| format!("Invalid PORT env variable: {}", port))?;
+        }
+        if let Ok(base_path) = std::env::var("BASE_PATH") {
+            config.base_path = base_path;
+        }
+        if let Ok(cors_allowed_origins) = std::env::var("CORS_ALLOWED_ORIGINS") {
+            config.cors_allowed_origins = cors_allowed_origins
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+        }
+
+        // The leading and trailing slashes of the base path are optional.
+        config.base_path = config.base_path.trim_matches('/').to_string();
+
+        Ok(config)
+    }
+}
+
+/// What the binary does, selected with the command line arguments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunMode {
+    /// Start the server, without arguments.
+    Serve,
+    /// Check the PokeAPI and Fun Translations are reachable and exit, with `--check`.
+    Check,
+}
+
+/// Get the run mode from the command line arguments, without the binary name.
+pub fn parse_run_mode(args: impl IntoIterator<Item = String>) -> Result<RunMode, String> {
+    let mut run_mode = RunMode::Serve;
+    for arg in args {
+        match arg.as_str() {
+            "--check" => run_mode = RunMode::Check,
+            _ => return Err(format!("Unknown argument {}, usage: pokedex_api [--check]", arg)),
+        }
+    }
+
+    Ok(run_mode)
+}
+
+/// The format of the log lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum LogFormat {
+    /// Human readable lines, with the fields of the request and call spans, like `request{request_id=id}`.
+    Text,
+    /// Multi-line human readable entries, for the local development.
+    Pretty,
+    /// JSON lines for the log aggregators, with the timestamp, level, target, message and fields,
+    /// and the request id and the other span fields in `spans`.
+    Json,
+}
+
+/// Get the format of the log lines from the `LOG_FORMAT` env variable, `json`, `pretty` or `text`, the default is `text`.
+pub(crate) fn get_log_format() -> LogFormat {
+    match std::env::var("LOG_FORMAT").map(|value| value.to_lowercase()).as_deref() {
+        Ok("json") => LogFormat::Json,
+        Ok("pretty") => LogFormat::Pretty,
+        _ => LogFormat::Text,
+    }
+}
+
+/// Get the path of the config file from the `CONFIG_FILE` env variable, the default is `config.toml`.
+pub fn get_config_path() -> String {
+    std::env::var("CONFIG_FILE").unwrap_or("config.toml".to_string())
+}
+
+/// Set up the `tracing` subscriber writing the log lines, with the format of `LOG_FORMAT`.
+/// 
+/// The log lines of a request have the span of the request, with its id, see `handle_with_request_context`.
+/// The log level is `info` by default, it can be changed with the `RUST_LOG` env variable,
+/// e.g. `RUST_LOG=pokedex_api=debug` for the cache hits and misses.
+/// The `log` records of the dependencies, like warp, are written as well.
+pub fn init_logger() {
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(env_filter);
+    match get_log_format() {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Pretty => subscriber.pretty().init(),
+        LogFormat::Json => subscriber.json().flatten_event(true).with_current_span(false).with_span_list(true).init(),
+    }
+}
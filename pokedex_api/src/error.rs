@@ -0,0 +1,127 @@
+//! The errors of the handlers, rejected with `warp::reject::custom` and turned into the JSON error replies by `handle_rejection`.
+
+use std::time::Duration;
+use tracing::error;
+use crate::clients::funtranslations::TranslationError;
+use crate::clients::pokeapi::{DryRunUnavailable, PokemonNotFound};
+use crate::models::PokemonFetchError;
+
+/// Why a request failed, each error has its status code and the message of the reply, see `PokedexError::status`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PokedexError {
+    /// The pokemon, or the resource asked for, doesn't exist: a 404.
+    NotFound(&'static str),
+    /// The pokemon doesn't exist, with the names close to the one asked for: a 404, see `suggest_pokemon_names`.
+    NotFoundWithSuggestions(Vec<String>),
+    /// An external API replied with an error: a 502.
+    UpstreamFailed(&'static str),
+    /// An external API is not called at all, e.g. while the circuit breaker is open: a 503.
+    UpstreamUnavailable(&'static str),
+    /// An external API rate limit is reached: a 429.
+    RateLimited(&'static str),
+    /// The local translation rate limit is reached, the API is not called: a 429 with the time to wait, see `TranslationQuota::reserve_call`.
+    Throttled(Duration),
+    /// The handler didn't reply in time, see `handle_with_request_context`: a 504.
+    Timeout,
+    /// An external API replied with something we can't read, e.g. an HTML error page: a 502.
+    ParseError(&'static str),
+}
+
+impl PokedexError {
+    /// The status code of the reply.
+    pub(crate) fn status(&self) -> warp::http::StatusCode {
+        match self {
+            PokedexError::NotFound(_) | PokedexError::NotFoundWithSuggestions(_) => warp::http::StatusCode::NOT_FOUND,
+            PokedexError::UpstreamFailed(_) | PokedexError::ParseError(_) => warp::http::StatusCode::BAD_GATEWAY,
+            PokedexError::UpstreamUnavailable(_) => warp::http::StatusCode::SERVICE_UNAVAILABLE,
+            PokedexError::RateLimited(_) | PokedexError::Throttled(_) => warp::http::StatusCode::TOO_MANY_REQUESTS,
+            PokedexError::Timeout => warp::http::StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+
+    /// The message of the reply, the `error` field.
+    pub(crate) fn message(&self) -> &'static str {
+        match self {
+            PokedexError::NotFound(message)
+            | PokedexError::UpstreamFailed(message)
+            | PokedexError::UpstreamUnavailable(message)
+            | PokedexError::RateLimited(message)
+            | PokedexError::ParseError(message) => message,
+            PokedexError::NotFoundWithSuggestions(_) => "Pokemon not found",
+            PokedexError::Throttled(_) => "Translation rate limit reached",
+            PokedexError::Timeout => "Request timed out",
+        }
+    }
+
+    /// The seconds to wait before retrying, for the `Retry-After` header, rounded up.
+    pub(crate) fn retry_after(&self) -> Option<u64> {
+        match self {
+            PokedexError::Throttled(wait) => Some(wait.as_secs() + u64::from(wait.subsec_nanos() > 0)),
+            _ => None,
+        }
+    }
+
+    /// The names suggested in the reply, the `suggestions` field.
+    pub(crate) fn suggestions(&self) -> Option<&[String]> {
+        match self {
+            PokedexError::NotFoundWithSuggestions(suggestions) => Some(suggestions),
+            _ => None,
+        }
+    }
+}
+
+impl warp::reject::Reject for PokedexError {}
+
+impl From<PokemonFetchError> for PokedexError {
+    fn from(error: PokemonFetchError) -> PokedexError {
+        match error {
+            PokemonFetchError::NotFound => PokedexError::NotFound("Pokemon not found"),
+            PokemonFetchError::Upstream => PokedexError::UpstreamFailed("PokeAPI failed to return the pokemon"),
+        }
+    }
+}
+
+/// The errors of the PokeAPI fetches of the pokemon sub-resources, e.g. the moves: a 404 for the pokemon not found,
+/// see `PokemonNotFound`, a 503 in the dry run mode, see `DryRunUnavailable`, and a 502 for the others, e.g. PokeAPI replying 500.
+impl From<Box<dyn std::error::Error + Send + Sync>> for PokedexError {
+    fn from(error: Box<dyn std::error::Error + Send + Sync>) -> PokedexError {
+        if error.is::<PokemonNotFound>() {
+            return PokemonFetchError::NotFound.into();
+        }
+        if error.is::<DryRunUnavailable>() {
+            return PokedexError::UpstreamUnavailable("PokeAPI is not called in the dry run mode");
+        }
+        error!("PokeAPI failed to return the pokemon: {}", error);
+        PokemonFetchError::Upstream.into()
+    }
+}
+
+/// The malformed replies are an upstream failure, a 502. The error statuses are a 429 for the rate limit and a 502 for the others,
+/// the requests without a reply, e.g. the connection refused or timed out, are a 503.
+/// The error objects in the 200 replies are a 429 for the rate limit code and a 502 for the others.
+impl From<TranslationError> for PokedexError {
+    fn from(error: TranslationError) -> PokedexError {
+        match error {
+            TranslationError::Api { code: 429, .. } => PokedexError::RateLimited("Translation failed"),
+            TranslationError::Api { code, message } => {
+                error!("Fun Translations replied with the error {}: {}", code, message);
+                PokedexError::UpstreamFailed("Translation failed")
+            }
+            TranslationError::Malformed(reason) => {
+                error!("Fun Translations replied with a malformed translation: {}", reason);
+                PokedexError::ParseError("Invalid translation response")
+            }
+            TranslationError::Request(error) => match error.status() {
+                Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => PokedexError::RateLimited("Translation failed"),
+                Some(status) => {
+                    error!("Fun Translations replied with the status {}", status);
+                    PokedexError::UpstreamFailed("Translation failed")
+                }
+                None => {
+                    error!("Fun Translations can't be reached: {}", error);
+                    PokedexError::UpstreamUnavailable("Translation unavailable")
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! The warp filters shared by the routes: the request context, the authentication, the query validation and the limits.
+//!
+//! The rejections of the filters are turned into the JSON error replies by `handle_rejection`.
+
+use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use serde::Serialize;
+use serde_json::json;
+use warp::{Filter, Reply};
+use tracing::{Instrument, error, info_span, warn};
+use futures::FutureExt;
+use crate::config::Config;
+use crate::error::PokedexError;
+use crate::models::ApiError;
+use crate::utils::{get_env_u32, json_reply, with_cache_control};
+
+tokio::task_local! {
+    /// If the JSON replies of the request being handled are pretty-printed, see `json_reply`.
+    pub(crate) static PRETTY_JSON: bool;
+}
+
+/// Match the segments of the base path, see `Config`.
+pub(crate) fn with_base_path(base_path: &str) -> warp::filters::BoxedFilter<()> {
+    base_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(warp::any().boxed(), |filter, segment| filter.and(warp::path(segment.to_string())).boxed())
+}
+
+/// The per-request settings shared by all the routes.
+pub(crate) struct RequestContext {
+    pub(crate) request_id: String,
+    pub(crate) pretty_json: bool,
+    pub(crate) request_timeout: Duration,
+}
+
+/// Extract the request context of the request.
+/// 
+/// The request id comes from the `X-Request-Id` header, or it is a new UUID if the header is missing.
+/// The JSON replies are pretty-printed with the `pretty=true` query parameter, accepted by all the routes.
+/// The timeout of the handler comes from the config, see `get_request_timeout`.
+pub(crate) fn with_request_context(config: Arc<Config>) -> impl Filter<Extract = (RequestContext,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("x-request-id")
+        .and(warp::query::<HashMap<String, String>>())
+        .map(move |request_id: Option<String>, query: HashMap<String, String>| RequestContext {
+            request_id: request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            pretty_json: query.get("pretty").map(String::as_str) == Some("true"),
+            request_timeout: get_request_timeout(&config),
+        })
+}
+
+/// Get the time the handlers have to reply from the `REQUEST_TIMEOUT_SECONDS` env variable,
+/// or `request_timeout_seconds` of the config file, the default is 15 seconds.
+pub(crate) fn get_request_timeout(config: &Config) -> Duration {
+    Duration::from_secs(get_env_u32("REQUEST_TIMEOUT_SECONDS", config.request_timeout_seconds.unwrap_or(15)) as u64)
+}
+
+/// Pass the config of the server to the handlers, they pass it down to the caches and the clients.
+pub(crate) fn with_config(config: Arc<Config>) -> impl Filter<Extract = (Arc<Config>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || config.clone())
+}
+
+/// Run the handler with the request context, in a `request` span with the request id, so the id is attached to all the log lines of the request
+/// and the JSON replies are formatted as requested, and echo the request id in the `X-Request-Id` response header.
+/// 
+/// Some code paths still unwrap the PokeAPI data, so a panic of the handler is caught, logged with the request id
+/// and replied with a JSON 500 instead of dropping the connection.
+/// 
+/// A slow chain of calls to the external APIs can't hold the request forever: the handler has the request timeout
+/// of the context (15 seconds by default) to reply, otherwise it is dropped and the request gets a JSON 504.
+/// 
+/// The `PokedexError` rejections of the handler are replied here with `handle_rejection`,
+/// so the error replies also have the request id and are pretty-printed as requested.
+pub(crate) async fn handle_with_request_context<R: warp::Reply>(context: RequestContext, handler: impl std::future::Future<Output = Result<R, warp::Rejection>>) -> Result<warp::reply::WithHeader<warp::reply::Response>, warp::Rejection> {
+    let request_timeout = context.request_timeout;
+    let handler = async {
+        match tokio::time::timeout(request_timeout, std::panic::AssertUnwindSafe(handler).catch_unwind()).await {
+            Ok(Ok(Ok(reply))) => Ok(reply.into_response()),
+            Ok(Ok(Err(rejection))) => handle_rejection(rejection).await,
+            Err(_) => {
+                warn!("The handler didn't reply within {} seconds", request_timeout.as_secs());
+                handle_rejection(PokedexError::Timeout.into()).await
+            }
+            Ok(Err(panic)) => {
+                error!("The handler panicked: {}", get_panic_message(&panic));
+                let reply = ApiError::new("Internal server error");
+                Ok(json_reply(&reply, warp::http::StatusCode::INTERNAL_SERVER_ERROR).into_response())
+            }
+        }
+    };
+
+    let span = info_span!("request", request_id = %context.request_id);
+    let reply = PRETTY_JSON.scope(context.pretty_json, handler).instrument(span).await?;
+    Ok(warp::reply::with_header(reply, "x-request-id", context.request_id))
+}
+
+/// Get the message of a caught panic, the panics with a formatted message carry a `String`, the others a `&str`.
+pub(crate) fn get_panic_message(panic: &Box<dyn std::any::Any + Send>) -> &str {
+    if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Extract the JSON body of a POST request, rejecting the bodies bigger than the limit.
+/// 
+/// The limit can be changed with the `MAX_BODY_BYTES` env variable, the default is 16 KiB.
+/// The oversized bodies are rejected before reading them and `handle_rejection` replies with a JSON 413.
+pub(crate) fn json_body<T: serde::de::DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(get_env_u32("MAX_BODY_BYTES", 16 * 1024) as u64).and(warp::body::json())
+}
+
+/// The rejection for the admin requests without a valid admin token and the data requests without a valid API key.
+#[derive(Debug)]
+pub(crate) struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// Check the admin token in the `Authorization: Bearer <token>` header.
+/// 
+/// The admin endpoints are disabled unless the `ADMIN_TOKEN` env variable is set, they are not found without it.
+/// The requests without the matching token are rejected with `Unauthorized`.
+pub(crate) fn with_admin_token() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(|authorization: Option<String>| async move {
+            let admin_token = match std::env::var("ADMIN_TOKEN") {
+                Ok(admin_token) if !admin_token.is_empty() => admin_token,
+                _ => return Err(warp::reject::not_found()),
+            };
+
+            if authorization == Some(format!("Bearer {}", admin_token)) {
+                Ok(())
+            } else {
+                Err(warp::reject::custom(Unauthorized))
+            }
+        })
+        .untuple_one()
+}
+
+/// Match the GET and the HEAD requests and extract the method, for the routes answering both.
+/// 
+/// The HEAD requests run the same handler as the GET ones, so they get the same status code
+/// and a cached pokemon is not fetched again, then the body is dropped with `without_body_for_head`.
+pub(crate) fn get_or_head() -> impl Filter<Extract = (warp::http::Method,), Error = warp::Rejection> + Clone {
+    warp::get().or(warp::head()).unify().and(warp::method())
+}
+
+/// Drop the body of the reply to a HEAD request, the status code and the headers are kept.
+pub(crate) fn without_body_for_head(method: &warp::http::Method, reply: impl Reply) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    if method == warp::http::Method::HEAD {
+        *response.body_mut() = warp::hyper::Body::empty();
+    }
+    response
+}
+
+/// Check the API key in the `X-API-Key` header.
+/// 
+/// The data endpoints are open unless the `API_KEY` env variable is set.
+/// With it the requests without the matching key are rejected with `Unauthorized`.
+pub(crate) fn with_api_key() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("x-api-key")
+        .and_then(|api_key: Option<String>| async move {
+            match std::env::var("API_KEY") {
+                Ok(expected_api_key) if !expected_api_key.is_empty() && api_key.as_ref() != Some(&expected_api_key) => {
+                    Err(warp::reject::custom(Unauthorized))
+                }
+                _ => Ok(()),
+            }
+        })
+        .untuple_one()
+}
+
+/// The rejection for the requests with query parameters the route doesn't know.
+#[derive(Debug)]
+pub(crate) struct UnexpectedQueryParameters(pub(crate) Vec<String>);
+
+impl warp::reject::Reject for UnexpectedQueryParameters {}
+
+/// An invalid query parameter, see `QueryValidator`.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct ParamError {
+    pub(crate) param: &'static str,
+    pub(crate) message: String,
+}
+
+/// Validate the query parameters of a request, collecting all the invalid ones instead of stopping at the first one.
+/// 
+/// The query structs keep the parameters as strings, so warp never rejects them, and the handlers parse them
+/// with the validator, then reply to the invalid requests with all the errors at once, see `QueryValidator::finish`.
+pub(crate) struct QueryValidator {
+    pub(crate) errors: Vec<ParamError>,
+}
+
+impl QueryValidator {
+    pub(crate) fn new() -> Self {
+        QueryValidator { errors: Vec::new() }
+    }
+
+    /// Parse a boolean parameter, `true` or `false`, the missing parameters are `false`.
+    pub(crate) fn bool(&mut self, param: &'static str, value: Option<&str>) -> bool {
+        self.parse(param, value, |value| match value {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            value => Err(format!("Expected true or false, got {}", value)),
+        })
+        .unwrap_or(false)
+    }
+
+    /// Parse a parameter with the parser, `None` if the parameter is missing or invalid.
+    pub(crate) fn parse<T>(&mut self, param: &'static str, value: Option<&str>, parser: impl FnOnce(&str) -> Result<T, String>) -> Option<T> {
+        match parser(value?) {
+            Ok(value) => Some(value),
+            Err(message) => {
+                self.errors.push(ParamError { param, message });
+                None
+            }
+        }
+    }
+
+    /// Get the 400 reply listing all the invalid parameters, if any.
+    pub(crate) fn finish(self) -> Result<(), warp::reply::WithHeader<warp::reply::WithStatus<String>>> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+
+        let reply = json!({
+            "error": "Invalid query parameters",
+            "errors": self.errors
+        });
+        Err(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST))
+    }
+}
+
+/// Reject the query parameters missing from the allow-list of the route with `UnexpectedQueryParameters`.
+/// 
+/// By default warp ignores the unknown query parameters, so a typo like `?no_cahce=true` would be silently ignored.
+/// The `pretty` query parameter is accepted by all the routes, see `with_request_context`.
+pub(crate) fn with_allowed_query(allowed: &'static [&'static str]) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::query::<HashMap<String, String>>()
+        .and_then(move |query: HashMap<String, String>| async move {
+            let mut unexpected: Vec<String> = query.into_keys().filter(|key| key != "pretty" && !allowed.contains(&key.as_str())).collect();
+            if unexpected.is_empty() {
+                return Ok(());
+            }
+            unexpected.sort();
+            Err(warp::reject::custom(UnexpectedQueryParameters(unexpected)))
+        })
+        .untuple_one()
+}
+
+/// The rejection for the clients over their request limit.
+#[derive(Debug)]
+pub(crate) struct ClientRateLimited;
+
+impl warp::reject::Reject for ClientRateLimited {}
+
+/// Count the requests of each client IP in a sliding window of one minute.
+/// 
+/// Each translated request can use the scarce Fun Translations quota, so a single client is limited
+/// to `TRANSLATED_REQUESTS_PER_MINUTE` requests per minute (10 by default) on the translated endpoint.
+pub(crate) struct ClientRateLimiter {
+    pub(crate) requests: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+impl ClientRateLimiter {
+    pub(crate) fn new() -> Self {
+        ClientRateLimiter {
+            requests: HashMap::new(),
+        }
+    }
+
+    /// Record a request of the client, return false if the client is over the limit.
+    pub(crate) fn check(&mut self, client_ip: IpAddr) -> bool {
+        let window = Duration::from_secs(60);
+
+        // Forget the clients without requests in the window, so the map doesn't grow forever.
+        self.requests.retain(|_, request_times| request_times.back().is_some_and(|request_time| request_time.elapsed() < window));
+
+        let request_times = self.requests.entry(client_ip).or_default();
+        while request_times.front().is_some_and(|request_time| request_time.elapsed() >= window) {
+            request_times.pop_front();
+        }
+
+        if request_times.len() >= get_env_u32("TRANSLATED_REQUESTS_PER_MINUTE", 10) as usize {
+            return false;
+        }
+        request_times.push_back(Instant::now());
+        true
+    }
+}
+
+/// Reject the requests of the clients over the limit with `ClientRateLimited`, see `ClientRateLimiter`.
+/// 
+/// The requests without a remote address are not limited.
+pub(crate) fn with_client_rate_limit(rate_limiter: Arc<Mutex<ClientRateLimiter>>) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::addr::remote()
+        .and_then(move |remote_addr: Option<std::net::SocketAddr>| {
+            let rate_limiter = rate_limiter.clone();
+            async move {
+                match remote_addr {
+                    Some(remote_addr) if !rate_limiter.lock().unwrap().check(remote_addr.ip()) => Err(warp::reject::custom(ClientRateLimited)),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// The rejection for the request paths over the length limit, see `with_max_path_length`.
+#[derive(Debug)]
+pub(crate) struct PathTooLong;
+
+impl warp::reject::Reject for PathTooLong {}
+
+/// Reject the request paths longer than `MAX_PATH_LENGTH` bytes (256 by default) with `PathTooLong`.
+/// 
+/// The pokemon names are path segments, so the huge names are rejected before they are decoded, normalized
+/// and validated by the handlers, see `validate_pokemon_name` for the length of the names themselves.
+pub(crate) fn with_max_path_length() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::path::full()
+        .and_then(|path: warp::path::FullPath| async move {
+            if path.as_str().len() > get_env_u32("MAX_PATH_LENGTH", 256) as usize {
+                return Err(warp::reject::custom(PathTooLong));
+            }
+            Ok(())
+        })
+        .untuple_one()
+}
+
+/// The rejection for the requests over the in-flight limit, see `with_in_flight_limit`.
+#[derive(Debug)]
+pub(crate) struct InFlightLimitExceeded;
+
+impl warp::reject::Reject for InFlightLimitExceeded {}
+
+/// Get the semaphore limiting the requests handled at the same time, at most `MAX_IN_FLIGHT_REQUESTS` (512 by default).
+/// 
+/// Unlike the upstream semaphore of the preload, this one caps all the requests, so under overload
+/// the server sheds the extra requests instead of piling them up until it runs out of memory or file descriptors.
+pub(crate) fn get_in_flight_semaphore() -> Arc<Semaphore> {
+    Arc::new(Semaphore::new(get_env_u32("MAX_IN_FLIGHT_REQUESTS", 512).max(1) as usize))
+}
+
+/// Take a permit of the in-flight semaphore for the request, or reject it with `InFlightLimitExceeded` if none is left.
+/// 
+/// The permit is extracted so that the routes after this filter hold it until their reply is ready.
+pub(crate) fn with_in_flight_limit(semaphore: Arc<Semaphore>) -> impl Filter<Extract = (OwnedSemaphorePermit,), Error = warp::Rejection> + Clone {
+    warp::any().and_then(move || {
+        let semaphore = semaphore.clone();
+        async move { semaphore.try_acquire_owned().map_err(|_| warp::reject::custom(InFlightLimitExceeded)) }
+    })
+}
+
+/// Convert the rejections to JSON error replies.
+/// 
+/// The built-in rejections of warp are JSON too, e.g. the unknown routes are a 404,
+/// and the rejections not handled here are logged and replied with a JSON 500.
+pub(crate) async fn handle_rejection(rejection: warp::Rejection) -> Result<warp::reply::Response, warp::Rejection> {
+    // The errors of the handlers are never cached, a missing pokemon can be added by PokeAPI.
+    if let Some(error) = rejection.find::<PokedexError>() {
+        let reply = match error.suggestions() {
+            Some(suggestions) => json_reply(&json!({ "error": error.message(), "suggestions": suggestions }), error.status()),
+            None => json_reply(&ApiError::new(error.message()), error.status()),
+        };
+        let reply = with_cache_control(reply, None).into_response();
+        if let Some(retry_after) = error.retry_after() {
+            return Ok(warp::reply::with_header(reply, "retry-after", retry_after.to_string()).into_response());
+        }
+        return Ok(reply);
+    }
+
+    if rejection.find::<Unauthorized>().is_some() {
+        let reply = ApiError::new("Unauthorized");
+        return Ok(json_reply(&reply, warp::http::StatusCode::UNAUTHORIZED).into_response());
+    }
+
+    if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        let reply = ApiError::new("Request body too large");
+        return Ok(json_reply(&reply, warp::http::StatusCode::PAYLOAD_TOO_LARGE).into_response());
+    }
+
+    if rejection.find::<PathTooLong>().is_some() {
+        let reply = ApiError::new("Request path too long");
+        return Ok(json_reply(&reply, warp::http::StatusCode::URI_TOO_LONG).into_response());
+    }
+
+    if rejection.find::<InFlightLimitExceeded>().is_some() {
+        let reply = ApiError::new("Server overloaded");
+        let reply = warp::reply::with_header(json_reply(&reply, warp::http::StatusCode::SERVICE_UNAVAILABLE), "retry-after", "1");
+        return Ok(reply.into_response());
+    }
+
+    if rejection.find::<ClientRateLimited>().is_some() {
+        let reply = ApiError::new("Too many requests");
+        return Ok(json_reply(&reply, warp::http::StatusCode::TOO_MANY_REQUESTS).into_response());
+    }
+
+    if let Some(UnexpectedQueryParameters(parameters)) = rejection.find::<UnexpectedQueryParameters>() {
+        let reply = json!({
+            "error": "Unexpected query parameters",
+            "parameters": parameters
+        });
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST).into_response());
+    }
+
+    if rejection.find::<warp::filters::body::BodyDeserializeError>().is_some() {
+        let reply = ApiError::new("Invalid request body");
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST).into_response());
+    }
+
+    if rejection.find::<warp::reject::InvalidQuery>().is_some() {
+        let reply = ApiError::new("Invalid query string");
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST).into_response());
+    }
+
+    if rejection.find::<warp::reject::MissingHeader>().is_some() || rejection.find::<warp::reject::InvalidHeader>().is_some() {
+        let reply = ApiError::new("Invalid request headers");
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST).into_response());
+    }
+
+    if rejection.find::<warp::reject::LengthRequired>().is_some() {
+        let reply = ApiError::new("Content-Length required");
+        return Ok(json_reply(&reply, warp::http::StatusCode::LENGTH_REQUIRED).into_response());
+    }
+
+    if rejection.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        let reply = ApiError::new("Unsupported media type, use application/json");
+        return Ok(json_reply(&reply, warp::http::StatusCode::UNSUPPORTED_MEDIA_TYPE).into_response());
+    }
+
+    if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        let reply = ApiError::new("Method not allowed");
+        return Ok(json_reply(&reply, warp::http::StatusCode::METHOD_NOT_ALLOWED).into_response());
+    }
+
+    if rejection.is_not_found() {
+        let reply = ApiError::new("Not found");
+        return Ok(json_reply(&reply, warp::http::StatusCode::NOT_FOUND).into_response());
+    }
+
+    error!("Unhandled rejection: {:?}", rejection);
+    let reply = ApiError::new("Internal server error");
+    Ok(json_reply(&reply, warp::http::StatusCode::INTERNAL_SERVER_ERROR).into_response())
+}
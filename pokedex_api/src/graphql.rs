@@ -0,0 +1,125 @@
+//! The GraphQL endpoint: the same pokemon and translated pokemon of the REST endpoints,
+//! but the clients select only the fields they need instead of the fixed JSON shape.
+//!
+//! The queries share the caches, the translation quota and the circuit breaker of the REST endpoints, see `GraphqlState`.
+
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::time::Instant;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, ErrorExtensions, Object, Schema, SimpleObject};
+use crate::cache::{Cache, get_or_fetch_pokemon};
+use crate::clients::funtranslations::{CircuitBreaker, TranslationQuota};
+use crate::config::Config;
+use crate::error::PokedexError;
+use crate::models::{PokemonResponse, Translation};
+use crate::routes::translate_pokemon;
+use crate::utils::{json_reply, normalize_pokemon_name, validate_pokemon_name};
+
+/// The schema of the graphql endpoint, queries only.
+pub(crate) type PokedexSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// The caches, the translation state and the config the queries share with the REST endpoints.
+#[derive(Clone)]
+pub(crate) struct GraphqlState {
+    pub(crate) cache_pokemon: Cache<String, PokemonResponse>,
+    pub(crate) cache_aliases: Arc<Mutex<HashMap<String, String>>>,
+    pub(crate) cache_not_found: Arc<Mutex<HashMap<String, Instant>>>,
+    pub(crate) cache_translation: Cache<String, Translation>,
+    pub(crate) translation_quota: Arc<Mutex<TranslationQuota>>,
+    pub(crate) translation_breaker: Arc<Mutex<CircuitBreaker>>,
+    pub(crate) config: Arc<Config>,
+}
+
+/// A pokemon, the fields of the pokemon/pokemon_name and translated/pokemon_name endpoints.
+#[derive(SimpleObject)]
+#[graphql(name = "Pokemon")]
+pub(crate) struct GraphqlPokemon {
+    name: String,
+    /// Null if the pokemon has no description.
+    description: Option<String>,
+    habitat: Option<String>,
+    is_legendary: bool,
+    types: Vec<String>,
+    /// The translator of the description, only for the translated pokemon.
+    translation: Option<String>,
+    /// Whether the description is translated, only for the translated pokemon.
+    translated: Option<bool>,
+}
+
+impl From<PokemonResponse> for GraphqlPokemon {
+    fn from(pokemon: PokemonResponse) -> GraphqlPokemon {
+        GraphqlPokemon {
+            name: pokemon.name,
+            description: pokemon.description,
+            habitat: pokemon.habitat,
+            is_legendary: pokemon.is_legendary,
+            types: pokemon.types,
+            translation: pokemon.translation.map(|translator| translator.name().to_string()),
+            translated: pokemon.translated,
+        }
+    }
+}
+
+/// The errors of the queries have the status code of the REST endpoints in the `status` extension.
+impl ErrorExtensions for PokedexError {
+    fn extend(&self) -> async_graphql::Error {
+        let status = self.status().as_u16();
+        async_graphql::Error::new(self.message()).extend_with(|_, extensions| extensions.set("status", status))
+    }
+}
+
+/// Normalize and validate the name argument, the invalid names are a 422 error like in the REST endpoints.
+fn validate_name_argument(name: &str) -> async_graphql::Result<String> {
+    let name = normalize_pokemon_name(name);
+    validate_pokemon_name(&name).map_err(|code| {
+        async_graphql::Error::new("Invalid pokemon name").extend_with(|_, extensions| {
+            extensions.set("code", code);
+            extensions.set("status", warp::http::StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+        })
+    })?;
+    Ok(name)
+}
+
+/// The queries of the schema, they are nullable so a failed query doesn't null the others of the request.
+pub(crate) struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The pokemon data, the same of the pokemon/pokemon_name endpoint.
+    async fn pokemon(&self, context: &Context<'_>, name: String) -> async_graphql::Result<Option<GraphqlPokemon>> {
+        let state = context.data_unchecked::<GraphqlState>();
+        let name = validate_name_argument(&name)?;
+
+        let pokemon = get_or_fetch_pokemon(name, false, state.cache_pokemon.clone(), state.cache_aliases.clone(), state.cache_not_found.clone(), state.config.clone()).await
+            .map_err(|error| PokedexError::from(error).extend())?;
+        Ok(Some(pokemon.into()))
+    }
+
+    /// The pokemon with the translated description, the same of the translated/pokemon_name endpoint.
+    async fn translated_pokemon(&self, context: &Context<'_>, name: String) -> async_graphql::Result<Option<GraphqlPokemon>> {
+        let state = context.data_unchecked::<GraphqlState>();
+        let name = validate_name_argument(&name)?;
+
+        let state = state.clone();
+        let pokemon = translate_pokemon(name, false, true, None, state.cache_pokemon, state.cache_aliases, state.cache_not_found, state.cache_translation, state.translation_quota, state.translation_breaker, state.config).await
+            .map_err(|error| error.extend())?;
+        Ok(Some(pokemon.into()))
+    }
+}
+
+/// Build the schema of the graphql endpoint, the queries use the caches of the state.
+pub(crate) fn build_schema(state: GraphqlState) -> PokedexSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .finish()
+}
+
+/// Execute a query of the graphql endpoint.
+/// 
+/// The body is the usual GraphQL request: `{"query": String, "variables": Object, "operationName": String}`.
+/// The reply is always a 200, the errors are in the `errors` array with the status code of the REST endpoints
+/// in their `status` extension, the fields of the failed queries are null.
+pub(crate) async fn post_graphql(request: async_graphql::Request, schema: PokedexSchema) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = schema.execute(request).await;
+    Ok(json_reply(&response, warp::http::StatusCode::OK))
+}
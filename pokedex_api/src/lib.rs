@@ -0,0 +1,26 @@
+//! The Pokedex API: the pokemon data from PokeAPI, with their descriptions translated by Fun Translations.
+//!
+//! The binary only reads the command line and the configuration and starts the server with `serve`,
+//! the routes can also be embedded in another warp server with `build_routes`.
+
+mod cache;
+mod clients;
+mod config;
+mod error;
+mod filters;
+mod graphql;
+mod models;
+mod openapi;
+mod routes;
+mod stats;
+pub mod telemetry;
+mod utils;
+
+#[cfg(test)]
+mod mock_servers;
+#[cfg(test)]
+mod tests;
+
+pub use clients::check_upstreams;
+pub use config::{get_config_path, init_logger, parse_run_mode, Config, RunMode};
+pub use routes::{build_routes, serve};
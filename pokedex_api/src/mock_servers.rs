@@ -0,0 +1,392 @@
+//! Mock PokeAPI and Fun Translations servers for the tests.
+//!
+//! The mock servers are configured through the `POKEAPI_BASE_URL` and `FUNTRANSLATIONS_BASE_URL` env variables.
+//! The env variables are shared by the whole test process, so `MockServers` holds a lock until it is dropped
+//! and the tests using the mock servers run one at a time.
+
+use serde_json::{json, Map, Value};
+use tokio::sync::{Mutex, MutexGuard};
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+static ENV_LOCK: Mutex<()> = Mutex::const_new(());
+
+pub struct MockServers {
+    pub pokeapi: MockServer,
+    pub funtranslations: MockServer,
+    env_variables: std::sync::Mutex<Vec<String>>,
+    _env_guard: MutexGuard<'static, ()>,
+}
+
+impl MockServers {
+    /// Set an env variable for the test, it is removed when the mock servers are dropped.
+    pub fn set_env(&self, name: &str, value: &str) {
+        std::env::set_var(name, value);
+        self.env_variables.lock().unwrap().push(name.to_string());
+    }
+}
+
+impl Drop for MockServers {
+    fn drop(&mut self) {
+        for name in self.env_variables.lock().unwrap().iter() {
+            std::env::remove_var(name);
+        }
+    }
+}
+
+/// Start the mock servers and point the env variables to them.
+pub async fn start_mock_servers() -> MockServers {
+    let env_guard = ENV_LOCK.lock().await;
+
+    let pokeapi = MockServer::start().await;
+    let funtranslations = MockServer::start().await;
+
+    std::env::set_var("POKEAPI_BASE_URL", format!("{}/api/v2/", pokeapi.uri()));
+    std::env::set_var("FUNTRANSLATIONS_BASE_URL", funtranslations.uri());
+
+    MockServers {
+        pokeapi,
+        funtranslations,
+        env_variables: std::sync::Mutex::new(Vec::new()),
+        _env_guard: env_guard,
+    }
+}
+
+/// Mount the pokemon and pokemon-species resources of a pokemon on the mock PokeAPI.
+pub async fn mock_pokemon(server: &MockServer, name: &str, description: &str, habitat: &str, is_legendary: bool) {
+    mock_resource(server, "pokemon", name, pokemon_json(&server.uri(), name)).await;
+    mock_resource(server, "pokemon-species", name, pokemon_species_json(&server.uri(), name, description, habitat, is_legendary)).await;
+}
+
+/// Mount a PokeAPI resource on the mock PokeAPI.
+pub async fn mock_resource(server: &MockServer, resource: &str, name: &str, body: Value) {
+    Mock::given(method("GET"))
+        .and(path_regex(format!("^/api/v2/{}/{}/?$", resource, name)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .mount(server)
+        .await;
+}
+
+/// Mount a PokeAPI resource replying with an error status on the mock PokeAPI, e.g. a 500 during an outage.
+pub async fn mock_resource_error(server: &MockServer, resource: &str, name: &str, status: u16) {
+    Mock::given(method("GET"))
+        .and(path_regex(format!("^/api/v2/{}/{}/?$", resource, name)))
+        .respond_with(ResponseTemplate::new(status))
+        .mount(server)
+        .await;
+}
+
+/// Mount the paginated list of a PokeAPI resource on the mock PokeAPI, with all the names in one page.
+pub async fn mock_resource_list(server: &MockServer, resource: &str, names: &[&str]) {
+    let results: Vec<Value> = names.iter().map(|name| named_resource(&server.uri(), resource, name)).collect();
+    Mock::given(method("GET"))
+        .and(path_regex(format!("^/api/v2/{}/?$", resource)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "count": names.len(),
+            "next": null,
+            "previous": null,
+            "results": results
+        })))
+        .mount(server)
+        .await;
+}
+
+/// Mount a successful translation on the mock Fun Translations API.
+pub async fn mock_translation(server: &MockServer, translator: &str, translated: &str) {
+    Mock::given(method("POST"))
+        .and(path(format!("/translate/{}", translator)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": { "total": 1 },
+            "contents": {
+                "translated": translated,
+                "text": "",
+                "translation": translator
+            }
+        })))
+        .mount(server)
+        .await;
+}
+
+/// Mount a rate limited translation on the mock Fun Translations API.
+pub async fn mock_translation_rate_limited(server: &MockServer, translator: &str) {
+    Mock::given(method("POST"))
+        .and(path(format!("/translate/{}", translator)))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "error": {
+                "code": 429,
+                "message": "Too Many Requests: Rate limit of 10 requests per hour exceeded. Please wait for 59 minutes and 59 seconds."
+            }
+        })))
+        .mount(server)
+        .await;
+}
+
+/// Build a named API resource like the ones returned by PokeAPI.
+pub fn named_resource(base_url: &str, resource: &str, name: &str) -> Value {
+    json!({
+        "name": name,
+        "url": format!("{}/api/v2/{}/{}/", base_url, resource, name)
+    })
+}
+
+/// Build the pokemon resource with all the fields PokeAPI returns.
+pub fn pokemon_json(base_url: &str, name: &str) -> Value {
+    json!({
+        "abilities": [],
+        "base_experience": 100,
+        "cries": {
+            "latest": format!("{}/cries/{}/latest.ogg", base_url, name),
+            "legacy": format!("{}/cries/{}/legacy.ogg", base_url, name)
+        },
+        "forms": [named_resource(base_url, "pokemon-form", name)],
+        "game_indices": [],
+        "height": 4,
+        "held_items": [],
+        "id": 1,
+        "is_default": true,
+        "location_area_encounters": format!("{}/api/v2/pokemon/{}/encounters", base_url, name),
+        "moves": [],
+        "name": name,
+        "order": 1,
+        "past_abilities": [],
+        "past_types": [],
+        "species": named_resource(base_url, "pokemon-species", name),
+        "sprites": sprites_json(),
+        "stats": [],
+        "types": [],
+        "weight": 60
+    })
+}
+
+/// Build the pokemon-species resource with all the fields PokeAPI returns.
+pub fn pokemon_species_json(base_url: &str, name: &str, description: &str, habitat: &str, is_legendary: bool) -> Value {
+    json!({
+        "base_happiness": 50,
+        "capture_rate": 45,
+        "color": named_resource(base_url, "pokemon-color", "yellow"),
+        "egg_groups": [],
+        "evolution_chain": { "url": format!("{}/api/v2/evolution-chain/1/", base_url) },
+        "evolves_from_species": null,
+        "flavor_text_entries": [
+            {
+                "flavor_text": description,
+                "language": named_resource(base_url, "language", "en"),
+                "version": named_resource(base_url, "version", "red")
+            }
+        ],
+        "form_descriptions": [],
+        "forms_switchable": false,
+        "gender_rate": 4,
+        "genera": [],
+        "generation": named_resource(base_url, "generation", "generation-i"),
+        "growth_rate": named_resource(base_url, "growth-rate", "medium"),
+        "habitat": named_resource(base_url, "pokemon-habitat", habitat),
+        "has_gender_differences": false,
+        "hatch_counter": 10,
+        "id": 1,
+        "is_baby": false,
+        "is_legendary": is_legendary,
+        "is_mythical": false,
+        "name": name,
+        "names": [],
+        "order": 1,
+        "pal_park_encounters": [],
+        "pokedex_numbers": [],
+        "shape": named_resource(base_url, "pokemon-shape", "quadruped"),
+        "varieties": []
+    })
+}
+
+/// Build a move of the pokemon resource, with a version group detail for each learn method, level and version group.
+pub fn pokemon_move_json(base_url: &str, name: &str, learn_methods: &[(&str, i64, &str)]) -> Value {
+    let version_group_details: Vec<Value> = learn_methods
+        .iter()
+        .map(|(learn_method, level, version_group)| json!({
+            "level_learned_at": level,
+            "move_learn_method": named_resource(base_url, "move-learn-method", learn_method),
+            "version_group": named_resource(base_url, "version-group", version_group)
+        }))
+        .collect();
+
+    json!({
+        "move": named_resource(base_url, "move", name),
+        "version_group_details": version_group_details
+    })
+}
+
+/// Build the move resource with the fields PokeAPI returns, `None` for the power and the accuracy of the status moves.
+pub fn move_json(base_url: &str, name: &str, move_type: &str, power: Option<i64>, accuracy: Option<i64>) -> Value {
+    json!({
+        "id": 1,
+        "name": name,
+        "accuracy": accuracy,
+        "effect_chance": null,
+        "pp": 15,
+        "priority": 0,
+        "power": power,
+        "contest_combos": null,
+        "contest_type": null,
+        "contest_effect": null,
+        "damage_class": named_resource(base_url, "move-damage-class", if power.is_some() { "special" } else { "status" }),
+        "effect_entries": [],
+        "effect_changes": [],
+        "learned_by_pokemon": [],
+        "flavor_text_entries": [],
+        "generation": named_resource(base_url, "generation", "generation-i"),
+        "machines": [],
+        "meta": null,
+        "names": [],
+        "past_values": [],
+        "stat_changes": [],
+        "super_contest_effect": null,
+        "target": named_resource(base_url, "move-target", "selected-pokemon"),
+        "type": named_resource(base_url, "type", move_type)
+    })
+}
+
+/// Build a link of the evolution-chain resource, the evolution is the trigger, the item and the minimum level
+/// of the evolution from the previous link, `None` for the first link.
+pub fn evolution_link_json(base_url: &str, name: &str, evolution: Option<(&str, Option<&str>, Option<i64>)>, evolves_to: Vec<Value>) -> Value {
+    let evolution_details: Vec<Value> = evolution
+        .into_iter()
+        .map(|(trigger, item, min_level)| json!({
+            "gender": null,
+            "held_item": null,
+            "item": item.map(|item| named_resource(base_url, "item", item)),
+            "known_move": null,
+            "known_move_type": null,
+            "location": null,
+            "min_affection": null,
+            "min_beauty": null,
+            "min_happiness": null,
+            "min_level": min_level,
+            "needs_overworld_rain": false,
+            "party_species": null,
+            "party_type": null,
+            "relative_physical_stats": null,
+            "time_of_day": "",
+            "trade_species": null,
+            "trigger": named_resource(base_url, "evolution-trigger", trigger),
+            "turn_upside_down": false
+        }))
+        .collect();
+
+    json!({
+        "evolution_details": evolution_details,
+        "evolves_to": evolves_to,
+        "is_baby": false,
+        "species": named_resource(base_url, "pokemon-species", name)
+    })
+}
+
+/// Build the evolution-chain resource from its first link.
+pub fn evolution_chain_json(chain: Value) -> Value {
+    json!({
+        "baby_trigger_item": null,
+        "chain": chain,
+        "id": 1
+    })
+}
+
+/// Build the ability resource with all the fields PokeAPI returns, the effects are in english and in german.
+pub fn ability_json(base_url: &str, name: &str, effect: &str, short_effect: &str) -> Value {
+    let effect_entry = |effect: &str, short_effect: &str, language: &str| json!({
+        "effect": effect,
+        "short_effect": short_effect,
+        "language": named_resource(base_url, "language", language)
+    });
+    json!({
+        "id": 1,
+        "name": name,
+        "is_main_series": true,
+        "generation": named_resource(base_url, "generation", "generation-iii"),
+        "names": [],
+        "effect_entries": [
+            effect_entry("Wirkung", "Kurze Wirkung", "de"),
+            effect_entry(effect, short_effect, "en")
+        ],
+        "effect_changes": [],
+        "flavor_text_entries": [],
+        "pokemon": []
+    })
+}
+
+/// Build the pokemon-habitat resource with the species living in it.
+pub fn pokemon_habitat_json(base_url: &str, name: &str, species: &[&str]) -> Value {
+    json!({
+        "id": 1,
+        "name": name,
+        "names": [],
+        "pokemon_species": species.iter().map(|species| named_resource(base_url, "pokemon-species", species)).collect::<Vec<Value>>()
+    })
+}
+
+/// Build the type resource with the pokemon of the type.
+pub fn type_json(base_url: &str, name: &str, pokemon: &[&str]) -> Value {
+    json!({
+        "id": 1,
+        "name": name,
+        "pokemon": pokemon.iter().map(|pokemon| json!({ "slot": 1, "pokemon": named_resource(base_url, "pokemon", pokemon) })).collect::<Vec<Value>>()
+    })
+}
+
+/// Build a sprites object with every sprite missing.
+fn sprites_json() -> Value {
+    let flat = [
+        "back_default", "back_female", "back_shiny", "back_shiny_female",
+        "front_default", "front_female", "front_shiny", "front_shiny_female",
+    ];
+    let mut sprites = null_fields(&flat);
+    sprites["other"] = json!({
+        "dream_world": null_fields(&["front_default", "front_female"]),
+        "home": null_fields(&["front_default", "front_female", "front_shiny", "front_shiny_female"]),
+        "official-artwork": null_fields(&["front_default", "front_shiny"]),
+        "showdown": null_fields(&flat)
+    });
+    let gray = ["back_default", "back_gray", "back_transparent", "front_default", "front_gray", "front_transparent"];
+    let gold = ["back_default", "back_shiny", "front_default", "front_shiny", "front_transparent"];
+    let shiny = ["back_default", "back_shiny", "front_default", "front_shiny"];
+    let female = ["front_default", "front_female", "front_shiny", "front_shiny_female"];
+    let mut black_white = null_fields(&flat);
+    black_white["animated"] = null_fields(&flat);
+    sprites["versions"] = json!({
+        "generation-i": { "red-blue": null_fields(&gray), "yellow": null_fields(&gray) },
+        "generation-ii": {
+            "crystal": null_fields(&[
+                "back_default", "back_shiny", "back_shiny_transparent", "back_transparent",
+                "front_default", "front_shiny", "front_shiny_transparent", "front_transparent",
+            ]),
+            "gold": null_fields(&gold),
+            "silver": null_fields(&gold)
+        },
+        "generation-iii": {
+            "emerald": null_fields(&["front_default", "front_shiny"]),
+            "firered-leafgreen": null_fields(&shiny),
+            "ruby-sapphire": null_fields(&shiny)
+        },
+        "generation-iv": {
+            "diamond-pearl": null_fields(&flat),
+            "heartgold-soulsilver": null_fields(&flat),
+            "platinum": null_fields(&flat)
+        },
+        "generation-v": { "black-white": black_white },
+        "generation-vi": {
+            "omegaruby-alphasapphire": null_fields(&female),
+            "x-y": null_fields(&female)
+        },
+        "generation-vii": {
+            "icons": null_fields(&["front_default", "front_female"]),
+            "ultra-sun-ultra-moon": null_fields(&female)
+        },
+        "generation-viii": { "icons": null_fields(&["front_default", "front_female"]) }
+    });
+    sprites
+}
+
+fn null_fields(fields: &[&str]) -> Value {
+    let mut object = Map::new();
+    for field in fields {
+        object.insert(field.to_string(), Value::Null);
+    }
+    Value::Object(object)
+}
@@ -0,0 +1,204 @@
+//! The data returned by the endpoints and cached by the server.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::clients::funtranslations::TranslatorKind;
+
+/// The cries data returned by the pokemon/pokemon_name/cry endpoint.
+/// 
+/// The missing cries are serialized as `null`, so the clients always get both fields.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CriesResponse {
+    pub(crate) name: String,
+    pub(crate) latest: Option<String>,
+    pub(crate) legacy: Option<String>,
+}
+
+/// A flavor text of the pokemon/pokemon_name/descriptions endpoint, with its language and game version.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FlavorTextEntry {
+    pub(crate) description: String,
+    pub(crate) language: String,
+    pub(crate) version: Option<String>,
+}
+
+/// A page of the pokemon endpoint, shaped like the PokeAPI lists.
+/// 
+/// The `next` and `previous` URLs are `null` on the last and the first page.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PokemonListResponse {
+    pub(crate) count: usize,
+    pub(crate) next: Option<String>,
+    pub(crate) previous: Option<String>,
+    pub(crate) results: Vec<PokemonListEntry>,
+}
+
+/// A pokemon of the pokemon list, with the URL of its pokemon/pokemon_name endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PokemonListEntry {
+    pub(crate) name: String,
+    pub(crate) url: String,
+}
+
+/// The species of a habitat returned by the habitat/habitat endpoint, with the URLs of their pokemon/pokemon_name endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct HabitatResponse {
+    pub(crate) name: String,
+    pub(crate) count: usize,
+    pub(crate) pokemon: Vec<PokemonListEntry>,
+}
+
+/// The descriptions of a pokemon keyed by language.
+pub(crate) type Descriptions = HashMap<String, String>;
+
+/// The pokemon data returned by the pokemon and translated endpoints.
+/// 
+/// The `translation` and `translated` fields are set only by the translated endpoint, the `translation_reason` field
+/// only by the translated endpoint with `explain=true`, the `cached_at` field only by the pokemon endpoint with `include_meta=true`,
+/// the `description_language` field only by the pokemon endpoint with `lang`, the language the description is really in,
+/// and the `translation_applied` field only by the translated endpoint, `false` when the translation failed
+/// and the description is the original one.
+/// The `types` are ordered by slot, so the first one is the primary type.
+/// The pokemon without an english description have a `null` description and `description_available: false`,
+/// or the placeholder of `get_default_description` as the description.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct PokemonResponse {
+    pub(crate) name: String,
+    pub(crate) description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description_available: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) description_language: Option<String>,
+    pub(crate) habitat: Option<String>,
+    pub(crate) is_legendary: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) types: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) translation: Option<TranslatorKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) translated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) translation_applied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) cached_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) translation_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sprites: Option<PokemonSprites>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stats: Option<PokemonStats>,
+}
+
+/// The sprite URLs of the pokemon, added by the pokemon endpoint with `include=sprites`.
+/// 
+/// The missing sprites are serialized as `null`, e.g. the newest pokemon have no game sprites yet.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct PokemonSprites {
+    pub(crate) front_default: Option<String>,
+    pub(crate) front_shiny: Option<String>,
+    pub(crate) official_artwork: Option<String>,
+    pub(crate) official_artwork_shiny: Option<String>,
+}
+
+/// The base stats of the pokemon, added by the pokemon endpoint with `include=stats`, with their `total`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct PokemonStats {
+    pub(crate) hp: i64,
+    pub(crate) attack: i64,
+    pub(crate) defense: i64,
+    pub(crate) special_attack: i64,
+    pub(crate) special_defense: i64,
+    pub(crate) speed: i64,
+    pub(crate) total: i64,
+}
+
+/// A translated description and the translator that produced it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Translation {
+    pub(crate) text: String,
+    pub(crate) translator: TranslatorKind,
+}
+
+/// The body of the error replies, `{"error": "..."}`.
+/// 
+/// The errors with more details, like the invalid query parameters, add their own fields with `json!`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ApiError {
+    pub(crate) error: String,
+}
+
+impl ApiError {
+    pub(crate) fn new(error: impl Into<String>) -> ApiError {
+        ApiError { error: error.into() }
+    }
+}
+
+/// A move the pokemon can learn, returned by the pokemon/pokemon_name/moves endpoint.
+/// 
+/// The `level` is set only for the level-up learn method, and serialized as `null` for the others.
+/// The type, the power and the accuracy come from the move resource, serialized as `null` when the move has none
+/// or it can't be fetched.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PokemonMove {
+    pub(crate) name: String,
+    pub(crate) learn_method: String,
+    pub(crate) level: Option<i64>,
+    pub(crate) version_group: String,
+    #[serde(rename = "type")]
+    pub(crate) move_type: Option<String>,
+    pub(crate) power: Option<i64>,
+    pub(crate) accuracy: Option<i64>,
+}
+
+/// The data of a move, cached for each move and shared by the pokemon learning it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct MoveDetails {
+    #[serde(rename = "type")]
+    pub(crate) move_type: String,
+    pub(crate) power: Option<i64>,
+    pub(crate) accuracy: Option<i64>,
+}
+
+/// An ability of the pokemon, returned by the pokemon/pokemon_name/abilities endpoint.
+/// 
+/// The effects are the english texts of the ability, serialized as `null` when PokeAPI has none or they can't be fetched.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PokemonAbility {
+    pub(crate) name: String,
+    pub(crate) is_hidden: bool,
+    pub(crate) effect: Option<String>,
+    pub(crate) short_effect: Option<String>,
+}
+
+/// The english effect texts of an ability, cached for each ability and shared by the pokemon having it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AbilityEffect {
+    pub(crate) effect: Option<String>,
+    pub(crate) short_effect: Option<String>,
+}
+
+/// A stage of the evolution chain returned by the pokemon/pokemon_name/evolution-chain endpoint.
+/// 
+/// The conditions are the ones of the evolution from the previous stage, all `null` for the first stage.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct EvolutionStage {
+    pub(crate) name: String,
+    /// 1 for the first stage, 2 for its evolutions and so on.
+    pub(crate) stage: u32,
+    pub(crate) evolves_from: Option<String>,
+    /// How the pokemon evolves, e.g. `level-up`, `use-item` or `trade`.
+    pub(crate) trigger: Option<String>,
+    /// The item used or held to evolve, e.g. `thunder-stone`.
+    pub(crate) item: Option<String>,
+    pub(crate) min_level: Option<i64>,
+}
+
+/// Why a pokemon couldn't be fetched, see `get_or_fetch_pokemon`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum PokemonFetchError {
+    /// PokeAPI doesn't know the pokemon.
+    NotFound,
+    /// PokeAPI failed to return the pokemon or its data, see `SpeciesUnavailable`.
+    Upstream,
+}
@@ -0,0 +1,251 @@
+//! The OpenAPI 3.0 document of the pokemon and translation endpoints, served at /openapi.json,
+//! and the Swagger UI page exploring it at /docs.
+//!
+//! The schemas are generated with schemars from the models, like the /schema endpoint, the paths are described here.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+use crate::clients::funtranslations::get_translator_registry;
+use crate::models::{ApiError, PokemonResponse};
+use crate::routes::{PokemonBatchRequest, TranslateRequest, TranslatedBatchRequest, DEFAULT_POKEMON_LIST_LIMIT, MAX_POKEMON_BATCH_SIZE, MAX_POKEMON_LIST_LIMIT, MAX_TRANSLATED_BATCH_SIZE, POKEMON_HABITATS, POKEMON_TYPES};
+use crate::utils::json_reply;
+
+/// The Swagger UI page, the assets come from the unpkg CDN so the binary doesn't embed them.
+/// The document is loaded relative to the page, so the page works under the base path too.
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Pokedex API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        window.ui = SwaggerUIBundle({ url: "openapi.json", dom_id: "#swagger-ui" });
+    </script>
+</body>
+</html>
+"##;
+
+/// A JSON request body with the schema of the type.
+fn json_body_of<T: JsonSchema>(generator: &mut SchemaGenerator) -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": generator.subschema_for::<T>() } }
+    })
+}
+
+/// A JSON response with the schema.
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } }
+    })
+}
+
+/// A query parameter, the boolean flags are `true` or `false`.
+fn query_parameter(name: &str, description: &str, schema: Value) -> Value {
+    json!({ "name": name, "in": "query", "required": false, "description": description, "schema": schema })
+}
+
+/// Build the OpenAPI document, the paths are under the base path of the configuration.
+pub(crate) fn build_openapi_document(base_path: &str) -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let pokemon = serde_json::to_value(generator.subschema_for::<PokemonResponse>()).unwrap(); // Suppose to be safe to unwrap, the schemas are plain JSON
+    let error = serde_json::to_value(generator.subschema_for::<ApiError>()).unwrap();
+    let batch_results = json!({
+        "type": "object",
+        "properties": {
+            "results": {
+                "type": "object",
+                "description": "The results keyed by the names of the request, a pokemon or an error with its status.",
+                "additionalProperties": {}
+            }
+        }
+    });
+
+    let pokemon_name = json!({ "name": "pokemon_name", "in": "path", "required": true, "description": "The pokemon name, e.g. pikachu, case insensitive, or its national dex number, e.g. 25.", "schema": { "type": "string" } });
+    let flag = json!({ "type": "boolean" });
+    let error_responses = json!({
+        "404": json_response("The pokemon doesn't exist, the `suggestions` are the pokemon names close to it.", error.clone()),
+        "422": json_response("The name can't be a pokemon name.", json!({ "type": "object" })),
+        "502": json_response("PokeAPI failed.", error.clone())
+    });
+
+    let mut pokemon_responses = error_responses.clone();
+    pokemon_responses["200"] = json_response("The pokemon.", pokemon.clone());
+    let mut random_responses = error_responses.clone();
+    random_responses["200"] = json_response("A random pokemon.", pokemon.clone());
+    let mut translated_responses = error_responses;
+    translated_responses["200"] = json_response("The pokemon with its description translated.", pokemon);
+    translated_responses["429"] = json_response("The translation rate limit is reached with `strict=true`, see the Retry-After header.", error.clone());
+    translated_responses["503"] = json_response("Fun Translations is failing with `strict=true`, it is not called.", error.clone());
+
+    let pokemon_list = json!({
+        "type": "object",
+        "properties": {
+            "count": { "type": "integer" },
+            "next": { "type": "string", "nullable": true },
+            "previous": { "type": "string", "nullable": true },
+            "results": {
+                "type": "array",
+                "items": { "type": "object", "properties": { "name": { "type": "string" }, "url": { "type": "string" } } }
+            }
+        }
+    });
+
+    let paths = json!({
+        "/pokemon": {
+            "get": {
+                "summary": "List the pokemon",
+                "parameters": [
+                    query_parameter("limit", "The page size.", json!({ "type": "integer", "minimum": 1, "maximum": MAX_POKEMON_LIST_LIMIT, "default": DEFAULT_POKEMON_LIST_LIMIT })),
+                    query_parameter("offset", "The position of the first pokemon.", json!({ "type": "integer", "minimum": 0, "default": 0 }))
+                ],
+                "responses": {
+                    "200": json_response("The page of the pokemon names, with the URLs of the pokemon.", pokemon_list.clone()),
+                    "400": json_response("The limit or the offset is invalid.", json!({ "type": "object" })),
+                    "503": json_response("The species list can't be loaded.", error.clone())
+                }
+            }
+        },
+        "/pokemon/{pokemon_name}": {
+            "get": {
+                "summary": "Get a pokemon",
+                "parameters": [
+                    pokemon_name,
+                    query_parameter("lang", "Comma separated languages of the description, e.g. it,de, the `description_language` field is the one used.", json!({ "type": "string" })),
+                    query_parameter("normalize_text", "Normalize the POKéMON artifacts of the description.", flag.clone()),
+                    query_parameter("no_cache", "Bypass the cache.", flag.clone()),
+                    query_parameter("include", "Comma separated optional fields: sprites, stats.", json!({ "type": "string" }))
+                ],
+                "responses": pokemon_responses
+            }
+        },
+        "/search": {
+            "get": {
+                "summary": "Search the pokemon by name",
+                "parameters": [
+                    { "name": "q", "in": "query", "required": true, "description": "The name to search, e.g. char.", "schema": { "type": "string" } },
+                    query_parameter("mode", "How the names match: the ones starting with the query, containing it or close to it.", json!({ "type": "string", "enum": ["prefix", "substring", "fuzzy"], "default": "substring" }))
+                ],
+                "responses": {
+                    "200": json_response("The count and the matching pokemon names, with the URLs of the pokemon.", json!({ "type": "object" })),
+                    "400": json_response("The query or the mode is invalid.", json!({ "type": "object" })),
+                    "503": json_response("The species list can't be loaded.", error.clone())
+                }
+            }
+        },
+        "/habitat/{habitat}": {
+            "get": {
+                "summary": "List the pokemon species living in a habitat",
+                "parameters": [{ "name": "habitat", "in": "path", "required": true, "schema": { "type": "string", "enum": POKEMON_HABITATS } }],
+                "responses": {
+                    "200": json_response("The habitat name, the count and the species, with the URLs of the pokemon.", json!({ "type": "object" })),
+                    "404": json_response("The habitat doesn't exist.", error.clone()),
+                    "502": json_response("PokeAPI failed.", error.clone())
+                }
+            }
+        },
+        "/type/{type}": {
+            "get": {
+                "summary": "List the pokemon of a type",
+                "parameters": [
+                    { "name": "type", "in": "path", "required": true, "schema": { "type": "string", "enum": POKEMON_TYPES } },
+                    query_parameter("limit", "The page size.", json!({ "type": "integer", "minimum": 1, "maximum": MAX_POKEMON_LIST_LIMIT, "default": DEFAULT_POKEMON_LIST_LIMIT })),
+                    query_parameter("offset", "The position of the first pokemon.", json!({ "type": "integer", "minimum": 0, "default": 0 }))
+                ],
+                "responses": {
+                    "200": json_response("The page of the pokemon names of the type, with the URLs of the pokemon.", pokemon_list),
+                    "400": json_response("The limit or the offset is invalid.", json!({ "type": "object" })),
+                    "404": json_response("The type doesn't exist.", error.clone()),
+                    "502": json_response("PokeAPI failed.", error.clone())
+                }
+            }
+        },
+        "/pokemon/random": {
+            "get": {
+                "summary": "Get a random pokemon",
+                "parameters": [query_parameter("seed", "The seed of a reproducible choice.", json!({ "type": "integer", "minimum": 0 }))],
+                "responses": random_responses
+            }
+        },
+        "/translated/{pokemon_name}": {
+            "get": {
+                "summary": "Get a pokemon with its description translated to Yoda or Shakespeare",
+                "parameters": [
+                    pokemon_name,
+                    query_parameter("explain", "Add the reason of the translator choice.", flag.clone()),
+                    query_parameter("style", "Translate in a registered style instead of the default rule.", json!({ "type": "string", "enum": get_translator_registry().names() })),
+                    query_parameter("strict", "Fail when the translation fails, instead of returning the original description with `translation_applied: false`.", flag.clone()),
+                    query_parameter("no_cache", "Bypass the caches.", flag)
+                ],
+                "responses": translated_responses
+            }
+        },
+        "/pokemon/batch": {
+            "post": {
+                "summary": format!("Get up to {} pokemon", MAX_POKEMON_BATCH_SIZE),
+                "requestBody": json_body_of::<PokemonBatchRequest>(&mut generator),
+                "responses": {
+                    "200": json_response("The pokemon keyed by name.", batch_results.clone()),
+                    "400": json_response("The batch is empty or too big.", error.clone())
+                }
+            }
+        },
+        "/translated/batch": {
+            "post": {
+                "summary": format!("Get up to {} pokemon with their descriptions translated", MAX_TRANSLATED_BATCH_SIZE),
+                "requestBody": json_body_of::<TranslatedBatchRequest>(&mut generator),
+                "responses": {
+                    "200": json_response("The translated pokemon keyed by name.", batch_results),
+                    "400": json_response("The batch is empty or too big.", error.clone())
+                }
+            }
+        },
+        "/translate": {
+            "post": {
+                "summary": "Translate any text to Yoda or Shakespeare",
+                "requestBody": json_body_of::<TranslateRequest>(&mut generator),
+                "responses": {
+                    "200": json_response("The text, the translated text and the style.", json!({ "type": "object" })),
+                    "400": json_response("The style is unknown or the text is empty.", error.clone()),
+                    "429": json_response("The translation rate limit is reached.", error)
+                }
+            }
+        }
+    });
+
+    let server_url = if base_path.is_empty() { "/".to_string() } else { format!("/{}", base_path) };
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Pokedex API",
+            "description": "The pokemon data from PokeAPI, with their descriptions translated by Fun Translations.",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "servers": [{ "url": server_url }],
+        "paths": paths,
+        "components": {
+            "schemas": generator.take_definitions(),
+            // The API key is checked only when the `API_KEY` env variable is set.
+            "securitySchemes": {
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-API-Key" }
+            }
+        },
+        "security": [{}, { "apiKey": [] }]
+    })
+}
+
+/// Get the data for the openapi.json endpoint, the OpenAPI document built once by `build_routes`.
+pub(crate) async fn get_openapi(document: std::sync::Arc<Value>) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(json_reply(document.as_ref(), warp::http::StatusCode::OK))
+}
+
+/// Get the Swagger UI page of the docs endpoint.
+pub(crate) async fn get_docs() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::html(SWAGGER_UI_HTML))
+}
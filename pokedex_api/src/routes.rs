@@ -0,0 +1,1932 @@
+//! The handlers of the endpoints and the warp filters tying them to their paths.
+//!
+//! `build_routes` puts all the endpoints together with their shared caches, so the server can be embedded in another binary.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use futures::StreamExt;
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore};
+use serde::Deserialize;
+use schemars::{schema_for, JsonSchema};
+use serde_json::{json, Value};
+use warp::{Filter, Reply};
+use tracing::{info, warn};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::cache::backend::{delete_prefix_from_cache_backend, set_in_cache_backend};
+use crate::cache::{Cache, PreloadJobs, PreloadResult, ResourceKind, SubResourceCache, cache_fetched_value, evict_expired_entries, get_or_fetch_descriptions, get_or_fetch_pokemon, get_or_fetch_sub_resource, get_pokemon_cached_at, get_preload_semaphore, get_species_names, get_shared_translation, get_translation_cache_ttl, get_warm_up_list, is_translation_cache_disabled, preload_pokemon, warm_up_pokemon_cache, warm_up_species_names};
+use crate::clients::funtranslations::{CircuitBreaker, TranslationProvider, TranslationQuota, TranslatorKind, TranslatorReason, TranslatorSelection, get_translation, get_translator_registry, get_translator_type_rules, select_translator};
+use crate::clients::pokeapi::{DryRunUnavailable, check_pokeapi_reachable, fetch_ability_effect_from_api, fetch_habitat_species_from_api, fetch_move_details_from_api, fetch_pokemon_abilities_from_api, fetch_pokemon_cries_from_api, fetch_pokemon_evolution_chain_from_api, fetch_pokemon_flavor_texts_from_api, fetch_pokemon_moves_from_api, fetch_pokemon_sprites_from_api, fetch_pokemon_stats_from_api, fetch_type_pokemon_from_api, get_default_language, get_description_language};
+use crate::config::Config;
+use crate::error::PokedexError;
+use crate::filters::{ClientRateLimiter, QueryValidator, get_in_flight_semaphore, get_or_head, handle_rejection, handle_with_request_context, json_body, with_admin_token, with_allowed_query, with_api_key, with_base_path, with_client_rate_limit, with_config, with_in_flight_limit, with_max_path_length, with_request_context, without_body_for_head};
+use crate::graphql::{GraphqlState, build_schema, post_graphql};
+use crate::models::{AbilityEffect, ApiError, CriesResponse, Descriptions, EvolutionStage, FlavorTextEntry, HabitatResponse, MoveDetails, PokemonAbility, PokemonFetchError, PokemonListEntry, PokemonListResponse, PokemonMove, PokemonResponse, PokemonSprites, PokemonStats, Translation};
+use crate::openapi::{build_openapi_document, get_docs, get_openapi};
+use crate::stats::{RequestStats, render_metrics, with_request_latency, with_request_stats};
+use crate::utils::{accepts_plain_text, get_env_u32, invalid_name_reply, json_reply, levenshtein_distance, normalize_pokemon_name, normalize_text, validate_pokemon_name, with_cache_control};
+
+/// Get the data for the pokemon/pokemon_name endpoint.
+/// 
+/// The endpoint will return the pokemon data as a JSON object.
+/// - name: String
+/// - description: String, null if the pokemon has no english description (with description_available: false)
+/// - habitat: String
+/// - is_legendary: bool
+/// - types: Array of String, omitted if unknown
+/// 
+/// The description is in the `DEFAULT_LANGUAGE` language when the pokemon has one, see `get_default_language`.
+/// With the `normalize_text=true` query parameter the "POKéMON" artifacts in the description are normalized.
+/// The endpoint will cache the pokemon data, the `no_cache=true` query parameter bypasses the cache for debugging.
+/// With the `Accept: text/plain` header only the description is returned as plain text, the errors are still JSON.
+/// The `lang` query parameter is a comma separated list of languages, e.g. `lang=it,de`, the description is
+/// in the first language the pokemon has a description in, or in the default language or english if it has none of them.
+/// The `description_language` field of the reply is the language the description is in, so the clients see the fallback.
+/// With the `include_meta=true` query parameter the reply has the `cached_at` RFC3339 timestamp of the pokemon data.
+/// The `include` query parameter is a comma separated list of the optional fields, see `POKEMON_INCLUDES`,
+/// e.g. `include=sprites,stats` adds the `sprites` URLs and the base `stats`. They are left out by default to keep the payload small.
+#[allow(clippy::too_many_arguments)] // Each cache is its own argument, like the other endpoints.
+pub(crate) async fn get_pokemon(pokemon_name_to_search: String, query: PokemonQuery, accept: Option<String>, cache: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, cache_descriptions: Cache<String, Descriptions>, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<warp::reply::Response, warp::Rejection> { 
+    let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name_to_search);
+    if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+        return Ok(with_cache_control(invalid_name_reply(code), None).into_response());
+    }
+
+    let mut validator = QueryValidator::new();
+    let normalize = validator.bool("normalize_text", query.normalize_text.as_deref());
+    let no_cache = validator.bool("no_cache", query.no_cache.as_deref());
+    let include_meta = validator.bool("include_meta", query.include_meta.as_deref());
+    let languages = validator.parse("lang", query.lang.as_deref(), |lang| {
+        parse_description_languages(lang).map_err(|language| format!("Unknown language {}, use one of: {}", language, DESCRIPTION_LANGUAGES.join(", ")))
+    });
+    let includes = validator.parse("include", query.include.as_deref(), |include| {
+        parse_pokemon_includes(include).map_err(|include| format!("Unknown include {}, use one of: {}", include, POKEMON_INCLUDES.join(", ")))
+    }).unwrap_or_default();
+    if let Err(reply) = validator.finish() {
+        return Ok(with_cache_control(reply, None).into_response());
+    }
+
+    let mut pokemon = get_or_fetch_pokemon(pokemon_name_to_search, no_cache, cache.clone(), cache_aliases, cache_not_found, config.clone()).await
+        .map_err(PokedexError::from)?;
+
+    // The cached pokemon has the description in the default language, the other languages are fetched and cached on demand.
+    if let Some(languages) = languages {
+        if let Some(descriptions) = get_or_fetch_descriptions(pokemon.name.clone(), no_cache, cache_descriptions, &config).await {
+            let default_language = get_default_language();
+            let language = get_description_language(&descriptions, &languages, default_language.as_deref());
+            pokemon.description = language.and_then(|language| descriptions.get(language).cloned());
+            pokemon.description_language = language.map(str::to_string);
+        }
+    }
+
+    // The optional fields are not in the cached pokemon either, they are fetched and cached on demand in the sub-resource cache.
+    if includes.contains(&"sprites") {
+        let sprites: Result<PokemonSprites, _> = get_or_fetch_sub_resource(&cache_sub_resources, &pokemon.name, ResourceKind::Sprites, &config, || {
+            fetch_pokemon_sprites_from_api(pokemon.name.clone(), &config)
+        }).await;
+        pokemon.sprites = Some(sprites.map_err(|error| {
+            warn!("Failed to fetch the sprites of {}: {}", pokemon.name, error);
+            if error.is::<DryRunUnavailable>() {
+                return PokedexError::from(error);
+            }
+            PokedexError::UpstreamFailed("PokeAPI failed to return the sprites")
+        })?);
+    }
+    if includes.contains(&"stats") {
+        let stats: Result<PokemonStats, _> = get_or_fetch_sub_resource(&cache_sub_resources, &pokemon.name, ResourceKind::Stats, &config, || {
+            fetch_pokemon_stats_from_api(pokemon.name.clone(), &config)
+        }).await;
+        pokemon.stats = Some(stats.map_err(|error| {
+            warn!("Failed to fetch the stats of {}: {}", pokemon.name, error);
+            if error.is::<DryRunUnavailable>() {
+                return PokedexError::from(error);
+            }
+            PokedexError::UpstreamFailed("PokeAPI failed to return the stats")
+        })?);
+    }
+
+    if normalize {
+        pokemon.description = pokemon.description.as_deref().map(normalize_text);
+    }
+
+    if include_meta {
+        let cached_at = get_pokemon_cached_at(&pokemon.name, cache);
+        pokemon.cached_at = Some(humantime::format_rfc3339_seconds(cached_at).to_string());
+    }
+
+    // The pokemon data rarely changes, so the browsers and the proxies can cache it for long.
+    let max_age_seconds = get_env_u32("POKEMON_MAX_AGE_SECONDS", 24 * 60 * 60);
+
+    if accepts_plain_text(accept.as_deref()) {
+        let reply = warp::reply::with_header(pokemon.description.unwrap_or_default(), "content-type", "text/plain; charset=utf-8");
+        return Ok(with_cache_control(reply, Some(max_age_seconds)).into_response());
+    }
+
+    Ok(with_cache_control(json_reply(&pokemon, warp::http::StatusCode::OK), Some(max_age_seconds)).into_response())
+}
+
+/// The query parameters of the pokemon/pokemon_name endpoint, validated by the handler, see `QueryValidator`.
+#[derive(Deserialize)]
+pub(crate) struct PokemonQuery {
+    pub(crate) normalize_text: Option<String>,
+    pub(crate) no_cache: Option<String>,
+    pub(crate) lang: Option<String>,
+    pub(crate) include_meta: Option<String>,
+    pub(crate) include: Option<String>,
+}
+
+/// The optional fields of the pokemon/pokemon_name endpoint accepted by the `include` query parameter.
+pub(crate) const POKEMON_INCLUDES: [&str; 2] = ["sprites", "stats"];
+
+/// Parse the comma separated list of the `include` query parameter, the first unknown field is returned as the error.
+pub(crate) fn parse_pokemon_includes(include: &str) -> Result<Vec<&'static str>, String> {
+    include
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| POKEMON_INCLUDES.iter().find(|known_field| **known_field == field).copied().ok_or(field.to_string()))
+        .collect()
+}
+
+/// The languages of the PokeAPI flavor texts accepted by the `lang` query parameter of the pokemon endpoint.
+pub(crate) const DESCRIPTION_LANGUAGES: [&str; 13] = ["cs", "de", "en", "es", "fr", "it", "ja", "ja-Hrkt", "ko", "pt-BR", "roomaji", "zh-Hans", "zh-Hant"];
+
+/// Parse the comma separated list of the `lang` query parameter, the first unknown language is returned as the error.
+/// 
+/// The languages are matched ignoring the case, so `pt-br` is `pt-BR`.
+pub(crate) fn parse_description_languages(lang: &str) -> Result<Vec<String>, String> {
+    lang.split(',')
+        .map(str::trim)
+        .filter(|language| !language.is_empty())
+        .map(|language| {
+            DESCRIPTION_LANGUAGES
+                .iter()
+                .find(|known_language| known_language.eq_ignore_ascii_case(language))
+                .map(|known_language| known_language.to_string())
+                .ok_or(language.to_string())
+        })
+        .collect()
+}
+
+/// Get the data for the pokemon endpoint, a page of the pokemon names with the URLs of their pokemon/pokemon_name endpoint.
+/// 
+/// The `limit` query parameter is the page size, 20 by default and at most `MAX_POKEMON_LIST_LIMIT`,
+/// the `offset` query parameter is the position of the first pokemon, 0 by default.
+/// The pages come from the species list shared with the pokemon/random endpoint, loaded once from the PokeAPI,
+/// so the pagination is served from memory. If the species list can't be loaded, the endpoint returns a 503.
+pub(crate) async fn get_pokemon_list(query: PokemonListQuery, path_prefix: String, species_names: Arc<OnceCell<Vec<String>>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut validator = QueryValidator::new();
+    let (limit, offset) = parse_pagination(&mut validator, &query);
+    if let Err(reply) = validator.finish() {
+        return Ok(with_cache_control(reply, None));
+    }
+
+    let Ok(names) = get_species_names(&species_names, &config).await else {
+        return Err(PokedexError::UpstreamUnavailable("Species list unavailable").into());
+    };
+    let page = get_pokemon_list_page(names, limit, offset, &path_prefix, "pokemon");
+
+    // The species list only changes with a new generation, so the pages are cached like the pokemon data.
+    let max_age_seconds = get_env_u32("POKEMON_MAX_AGE_SECONDS", 24 * 60 * 60);
+    Ok(with_cache_control(json_reply(&page, warp::http::StatusCode::OK), Some(max_age_seconds)))
+}
+
+/// The query parameters of the paginated endpoints, pokemon and type/type, validated by `parse_pagination`.
+#[derive(Deserialize)]
+pub(crate) struct PokemonListQuery {
+    pub(crate) limit: Option<String>,
+    pub(crate) offset: Option<String>,
+}
+
+/// Parse the `limit` and `offset` query parameters of the paginated endpoints, the missing ones have their defaults.
+pub(crate) fn parse_pagination(validator: &mut QueryValidator, query: &PokemonListQuery) -> (usize, usize) {
+    let limit = validator.parse("limit", query.limit.as_deref(), |limit| match limit.parse::<usize>() {
+        Ok(limit) if (1..=MAX_POKEMON_LIST_LIMIT).contains(&limit) => Ok(limit),
+        _ => Err(format!("Expected a number between 1 and {}, got {}", MAX_POKEMON_LIST_LIMIT, limit)),
+    });
+    let offset = validator.parse("offset", query.offset.as_deref(), |offset| {
+        offset.parse::<usize>().map_err(|_| format!("Expected an unsigned number, got {}", offset))
+    });
+    (limit.unwrap_or(DEFAULT_POKEMON_LIST_LIMIT), offset.unwrap_or(0))
+}
+
+/// The page size of the pokemon endpoint without the `limit` query parameter.
+pub(crate) const DEFAULT_POKEMON_LIST_LIMIT: usize = 20;
+
+/// The biggest page of the pokemon endpoint.
+pub(crate) const MAX_POKEMON_LIST_LIMIT: usize = 100;
+
+/// Get the page of the names starting at the offset, the URLs start with the path prefix, e.g. `/api/v1` for the base path.
+/// 
+/// The `next` and `previous` URLs are pages of the list path, e.g. `pokemon` or `type/fire`. The offsets past the end are an empty page.
+pub(crate) fn get_pokemon_list_page(names: &[String], limit: usize, offset: usize, path_prefix: &str, list_path: &str) -> PokemonListResponse {
+    let page_url = |offset: usize| format!("{}/{}?limit={}&offset={}", path_prefix, list_path, limit, offset);
+    let results = names
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|name| PokemonListEntry { name: name.clone(), url: format!("{}/pokemon/{}", path_prefix, name) })
+        .collect();
+
+    PokemonListResponse {
+        count: names.len(),
+        next: (offset + limit < names.len()).then(|| page_url(offset + limit)),
+        previous: (offset > 0).then(|| page_url(offset.saturating_sub(limit))),
+        results,
+    }
+}
+
+/// Get the data for the search endpoint, all the pokemon whose names match the `q` query parameter.
+/// 
+/// The `mode` query parameter is how the names match, see `SearchMode`, `substring` by default.
+/// The query is normalized like the pokemon names, so `Mr. Mime` searches `mr-mime`.
+/// The names come from the species list shared with the pokemon/random endpoint, loaded once from the PokeAPI,
+/// so the searches don't call the PokeAPI. If the species list can't be loaded, the endpoint returns a 503.
+pub(crate) async fn get_search(query: SearchQuery, path_prefix: String, species_names: Arc<OnceCell<Vec<String>>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut validator = QueryValidator::new();
+    // The missing `q` is parsed as empty, so it is an error too.
+    let search = validator.parse("q", Some(query.q.as_deref().unwrap_or_default()), |q| {
+        let q = normalize_pokemon_name(q.trim());
+        if q.is_empty() { Err("Expected a name to search".to_string()) } else { Ok(q) }
+    });
+    let mode = validator.parse("mode", query.mode.as_deref(), |mode| match mode {
+        "prefix" => Ok(SearchMode::Prefix),
+        "substring" => Ok(SearchMode::Substring),
+        "fuzzy" => Ok(SearchMode::Fuzzy),
+        mode => Err(format!("Expected prefix, substring or fuzzy, got {}", mode)),
+    });
+    if let Err(reply) = validator.finish() {
+        return Ok(with_cache_control(reply, None));
+    }
+    let search = search.unwrap_or_default();
+
+    let Ok(names) = get_species_names(&species_names, &config).await else {
+        return Err(PokedexError::UpstreamUnavailable("Species list unavailable").into());
+    };
+    let results: Vec<PokemonListEntry> = search_species_names(&search, mode.unwrap_or(SearchMode::Substring), names)
+        .into_iter()
+        .map(|name| PokemonListEntry { name: name.clone(), url: format!("{}/pokemon/{}", path_prefix, name) })
+        .collect();
+
+    let reply = json!({
+        "count": results.len(),
+        "results": results
+    });
+    Ok(with_cache_control(json_reply(&reply, warp::http::StatusCode::OK), None))
+}
+
+/// The query parameters of the search endpoint, validated by the handler, see `QueryValidator`.
+#[derive(Deserialize)]
+pub(crate) struct SearchQuery {
+    pub(crate) q: Option<String>,
+    pub(crate) mode: Option<String>,
+}
+
+/// How the names match the query of the search endpoint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SearchMode {
+    /// The names starting with the query.
+    Prefix,
+    /// The names containing the query, the ones starting with it first.
+    Substring,
+    /// The names close to the query, the closest first, see `get_fuzzy_matches`.
+    Fuzzy,
+}
+
+/// Search the species names matching the query, the names of the same rank are in the order of the species list.
+pub(crate) fn search_species_names<'a>(search: &str, mode: SearchMode, species_names: &'a [String]) -> Vec<&'a String> {
+    match mode {
+        SearchMode::Prefix => species_names.iter().filter(|name| name.starts_with(search)).collect(),
+        SearchMode::Substring => {
+            let (prefix_matches, other_matches): (Vec<&String>, Vec<&String>) = species_names
+                .iter()
+                .filter(|name| name.contains(search))
+                .partition(|name| name.starts_with(search));
+            prefix_matches.into_iter().chain(other_matches).collect()
+        }
+        SearchMode::Fuzzy => get_fuzzy_matches(search, species_names),
+    }
+}
+
+/// Get the data for the pokemon/random endpoint.
+/// 
+/// The endpoint will return the data of a random pokemon as a JSON object, same as the pokemon/pokemon_name endpoint.
+/// The pokemon is picked from the species list loaded from the PokeAPI, so the new generations are included
+/// without hardcoding the range of the pokedex numbers.
+/// If the species list can't be loaded, the endpoint returns a 503.
+/// With the `seed` query parameter, e.g. `seed=123`, the pokemon is picked with a seeded RNG,
+/// so the same seed returns the same pokemon as long as the species list doesn't change.
+pub(crate) async fn get_random_pokemon(query: RandomQuery, species_names: Arc<OnceCell<Vec<String>>>, cache: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut validator = QueryValidator::new();
+    let seed = validator.parse("seed", query.seed.as_deref(), |seed| {
+        seed.parse::<u64>().map_err(|_| format!("Expected an unsigned number, got {}", seed))
+    });
+    if let Err(reply) = validator.finish() {
+        return Ok(with_cache_control(reply, None));
+    }
+
+    let pokemon_name = match get_species_names(&species_names, &config).await {
+        Ok(names) => choose_random_species(names, seed).cloned(),
+        Err(_) => None,
+    };
+
+    let Some(pokemon_name) = pokemon_name else {
+        return Err(PokedexError::UpstreamUnavailable("Species list unavailable").into());
+    };
+
+    let pokemon = get_or_fetch_pokemon(pokemon_name, false, cache, cache_aliases, cache_not_found, config.clone()).await
+        .map_err(PokedexError::from)?;
+
+    // Every reply without a seed is different, so the random pokemon must not be cached by the browsers.
+    Ok(with_cache_control(json_reply(&pokemon, warp::http::StatusCode::OK), None))
+}
+
+/// The query parameters of the pokemon/random endpoint.
+#[derive(Deserialize)]
+pub(crate) struct RandomQuery {
+    pub(crate) seed: Option<String>,
+}
+
+/// Get the data for the translated/pokemon_name endpoint.
+/// 
+/// The endpoint will return the pokemon data with the description translated as a JSON object.
+/// - name: String
+/// - description: String, null if the pokemon has no english description (with description_available: false)
+/// - habitat: String
+/// - is_legendary: bool
+/// - types: Array of String, omitted if unknown
+/// 
+/// The endpoint will cache the pokemon data and the translation,
+/// the `no_cache=true` query parameter bypasses both caches for debugging.
+/// With the `explain=true` query parameter the translated pokemon have the `translation_reason` field,
+/// explaining why the translator was chosen, e.g. `cave habitat → yoda`, see `get_translation_reason`.
+/// The `style` query parameter asks for any registered translator instead of the default rule, e.g. `style=pirate`,
+/// see `TranslatorRegistry`. The translations of each style are cached apart.
+/// When Fun Translations fails, e.g. because of the rate limit, the pokemon is returned with its original description
+/// and `translation_applied: false`, not cached by the clients. With `strict=true` the failure is returned instead, e.g. a 429.
+// Each cache and shared state comes from its own filter, like the other endpoints.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn get_translated_pokemon(pokemon_name_to_search: String, query: TranslatedQuery, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, cache_translation: Cache<String, Translation>, translation_quota: Arc<Mutex<TranslationQuota>>, translation_breaker: Arc<Mutex<CircuitBreaker>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name_to_search);
+    if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+        return Ok(with_cache_control(invalid_name_reply(code), None));
+    }
+
+    let mut validator = QueryValidator::new();
+    let no_cache = validator.bool("no_cache", query.no_cache.as_deref());
+    let explain = validator.bool("explain", query.explain.as_deref());
+    let strict = validator.bool("strict", query.strict.as_deref());
+    let style = validator.parse("style", query.style.as_deref(), |style| {
+        TranslatorKind::from_name(&style.to_lowercase())
+            .ok_or_else(|| format!("Unknown translation style, use one of: {}", get_translator_registry().names().join(", ")))
+    });
+    if let Err(reply) = validator.finish() {
+        return Ok(with_cache_control(reply, None));
+    }
+
+    let mut pokemon = translate_pokemon(pokemon_name_to_search, no_cache, strict, style, cache_pokemon, cache_aliases, cache_not_found, cache_translation, translation_quota, translation_breaker, config).await?;
+    if explain {
+        pokemon.translation_reason = match style {
+            Some(style) => pokemon.translation.map(|_| TranslatorReason::Requested.explain(style)),
+            None => get_translation_reason(&pokemon),
+        };
+    }
+
+    // The translated data is cached for a shorter time, the translations can change when the translator changes.
+    // The untranslated fallback is not cached, the next request can get the translation.
+    let max_age_seconds = Some(get_env_u32("TRANSLATED_MAX_AGE_SECONDS", 60 * 60)).filter(|_| pokemon.translation_applied != Some(false));
+    Ok(with_cache_control(json_reply(&pokemon, warp::http::StatusCode::OK), max_age_seconds))
+}
+
+/// The query parameters of the translated/pokemon_name endpoint.
+#[derive(Deserialize)]
+pub(crate) struct TranslatedQuery {
+    pub(crate) no_cache: Option<String>,
+    pub(crate) explain: Option<String>,
+    pub(crate) style: Option<String>,
+    pub(crate) strict: Option<String>,
+}
+
+/// Explain why the pokemon was translated with its translator, `None` if the pokemon was not translated.
+/// 
+/// The translator is selected again with `select_translator`, when the translation comes from the other translator,
+/// e.g. because of the rate limit fallback, the reason says so.
+pub(crate) fn get_translation_reason(pokemon: &PokemonResponse) -> Option<String> {
+    let translator = pokemon.translation?;
+    let (selected_translator, reason) = select_translator(&TranslatorSelection::from_pokemon(pokemon), &get_translator_type_rules());
+    let reason = reason.explain(selected_translator);
+
+    if translator != selected_translator {
+        return Some(format!("{}, fell back to {}", reason, translator.name()));
+    }
+    Some(reason)
+}
+
+/// The longest list of names accepted by the pokemon/batch endpoint.
+pub(crate) const MAX_POKEMON_BATCH_SIZE: usize = 50;
+
+/// The body of the pokemon/batch endpoint.
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct PokemonBatchRequest {
+    pub(crate) names: Vec<String>,
+}
+
+/// Get the data for the pokemon/batch endpoint.
+/// 
+/// The endpoint takes a JSON body with the list of names, up to `MAX_POKEMON_BATCH_SIZE`,
+/// and returns the pokemon keyed by name as a JSON object, the same data of the pokemon/pokemon_name endpoint.
+/// - results: { pokemon name: pokemon data, or { error: String, status: Number } }
+/// 
+/// The cache misses are fetched from PokeAPI concurrently, at most `BATCH_CONCURRENCY` (5 by default) at a time,
+/// so a big batch doesn't flood PokeAPI. A name failing gets its own error without failing the batch.
+pub(crate) async fn post_pokemon_batch(body: PokemonBatchRequest, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    if body.names.is_empty() || body.names.len() > MAX_POKEMON_BATCH_SIZE {
+        let reply = ApiError::new(format!("The batch must have between 1 and {} names", MAX_POKEMON_BATCH_SIZE));
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST));
+    }
+
+    let semaphore = Semaphore::new(get_env_u32("BATCH_CONCURRENCY", 5).max(1) as usize);
+    let lookups = body.names.into_iter().map(|pokemon_name| {
+        let (semaphore, cache_pokemon, cache_aliases, cache_not_found, config) = (&semaphore, cache_pokemon.clone(), cache_aliases.clone(), cache_not_found.clone(), config.clone());
+        async move {
+            let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name);
+            if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+                let status = warp::http::StatusCode::UNPROCESSABLE_ENTITY;
+                return (pokemon_name, json!({ "error": "Invalid pokemon name", "code": code, "status": status.as_u16() }));
+            }
+
+            let _permit = semaphore.acquire().await.unwrap(); // Suppose to be safe to unwrap, the semaphore is never closed
+            let result = match get_or_fetch_pokemon(pokemon_name_to_search, false, cache_pokemon, cache_aliases, cache_not_found, config.clone()).await {
+                Ok(pokemon) => json!(pokemon),
+                Err(error) => {
+                    let error = PokedexError::from(error);
+                    json!({ "error": error.message(), "status": error.status().as_u16() })
+                }
+            };
+            (pokemon_name, result)
+        }
+    });
+
+    // The results are keyed by the names as sent.
+    let results: serde_json::Map<String, Value> = futures::future::join_all(lookups).await.into_iter().collect();
+
+    let reply = json!({
+        "results": results
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// The longest list of names accepted by the translated/batch endpoint.
+/// The Fun Translations API allows only 10 requests per hour, so a bigger batch would fail anyway.
+pub(crate) const MAX_TRANSLATED_BATCH_SIZE: usize = 10;
+
+/// The body of the translated/batch endpoint.
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct TranslatedBatchRequest {
+    pub(crate) names: Vec<String>,
+}
+
+/// Get the data for the translated/batch endpoint.
+/// 
+/// The endpoint takes a JSON body with the list of names, up to `MAX_TRANSLATED_BATCH_SIZE`,
+/// and returns the translated pokemon keyed by name as a JSON object.
+/// - results: { pokemon name: translated pokemon data, or { error: String, status: Number } }
+/// 
+/// The names are translated one at a time with the same caches, quota and circuit breaker of the translated endpoint,
+/// so a name failing, e.g. because of the translation rate limit, gets its own error, like with `strict=true`, without failing the batch.
+/// The names normalized to the same pokemon, e.g. `Pikachu` and `pikachu`, are translated once and share the result.
+#[allow(clippy::too_many_arguments)] // Each cache is its own argument, like the other endpoints.
+pub(crate) async fn post_translated_batch(body: TranslatedBatchRequest, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, cache_translation: Cache<String, Translation>, translation_quota: Arc<Mutex<TranslationQuota>>, translation_breaker: Arc<Mutex<CircuitBreaker>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    if body.names.is_empty() || body.names.len() > MAX_TRANSLATED_BATCH_SIZE {
+        let reply = ApiError::new(format!("The batch must have between 1 and {} names", MAX_TRANSLATED_BATCH_SIZE));
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST));
+    }
+
+    let mut results = serde_json::Map::new();
+    // The failed translations are not cached, so the duplicate names would spend the quota again without this.
+    let mut translated: HashMap<String, serde_json::Value> = HashMap::new();
+    for pokemon_name in body.names {
+        // The results are keyed by the names as sent.
+        let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name);
+        if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+            let status = warp::http::StatusCode::UNPROCESSABLE_ENTITY;
+            results.insert(pokemon_name, json!({ "error": "Invalid pokemon name", "code": code, "status": status.as_u16() }));
+            continue;
+        }
+        if let Some(result) = translated.get(&pokemon_name_to_search) {
+            results.insert(pokemon_name, result.clone());
+            continue;
+        }
+
+        let pokemon = translate_pokemon(pokemon_name_to_search.clone(), false, true, None, cache_pokemon.clone(), cache_aliases.clone(), cache_not_found.clone(), cache_translation.clone(), translation_quota.clone(), translation_breaker.clone(), config.clone()).await;
+        let result = match pokemon {
+            Ok(pokemon) => json!(pokemon),
+            Err(error) => json!({ "error": error.message(), "status": error.status().as_u16() }),
+        };
+        translated.insert(pokemon_name_to_search, result.clone());
+        results.insert(pokemon_name, result);
+    }
+
+    let reply = json!({
+        "results": results
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// The body of the translate endpoint.
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct TranslateRequest {
+    pub(crate) text: String,
+    pub(crate) style: String,
+}
+
+/// Get the data for the translate endpoint, translating any text instead of a pokemon description.
+/// 
+/// The endpoint takes a JSON body with the text and the translator style, any registered one, e.g. `yoda` or `pirate`,
+/// and returns the translated text as a JSON object.
+/// - text: String, the text to translate
+/// - translated: String
+/// - style: String
+/// 
+/// The translations share the cache, quota and circuit breaker of the translated endpoint,
+/// they are cached under the text and the style, see `get_text_translation_key`.
+pub(crate) async fn post_translate(body: TranslateRequest, cache_translation: Cache<String, Translation>, translation_quota: Arc<Mutex<TranslationQuota>>, translation_breaker: Arc<Mutex<CircuitBreaker>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    // The styles are case-insensitive, like the `style` query parameter of the translated endpoint.
+    let Some(translator) = TranslatorKind::from_name(&body.style.to_lowercase()) else {
+        let reply = json!({
+            "error": "Unknown translation style",
+            "styles": get_translator_registry().names()
+        });
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST));
+    };
+
+    if body.text.trim().is_empty() {
+        let reply = ApiError::new("The text must not be empty");
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST));
+    }
+
+    let cache_key = get_text_translation_key(&body.text, translator);
+    let translation_in_cache = if is_translation_cache_disabled() { None } else { get_shared_translation(cache_key.clone(), cache_translation.clone(), &config).await };
+    if let Some(translation) = translation_in_cache {
+        return Ok(json_reply(&text_translation_json(body.text, translation), warp::http::StatusCode::OK));
+    }
+
+    if !translation_breaker.lock().unwrap().allow_request() {
+        return Err(PokedexError::UpstreamUnavailable("Translation unavailable").into());
+    }
+    if let Err(error) = wait_for_translation_call(&translation_quota).await {
+        translation_breaker.lock().unwrap().release_probe();
+        return Err(error.into());
+    }
+
+    let fetch_started = Instant::now();
+    let translated_text = TranslationProvider::from_env().translate(translator, &body.text, &config).await;
+
+    translation_quota.lock().unwrap().record_call(translated_text.is_ok());
+    translation_breaker.lock().unwrap().record_result(translated_text.is_ok());
+
+    let translation = Translation { text: translated_text.map_err(PokedexError::from)?, translator };
+
+    // A concurrent request can have cached the same text meanwhile, the first translation cached wins.
+    let translation = if is_translation_cache_disabled() {
+        translation
+    } else {
+        set_in_cache_backend(&format!("translation:{}", cache_key), &translation, get_translation_cache_ttl(&config)).await;
+        cache_fetched_value(&cache_translation, cache_key, translation, fetch_started)
+    };
+
+    Ok(json_reply(&text_translation_json(body.text, translation), warp::http::StatusCode::OK))
+}
+
+/// Build the reply of the translate endpoint.
+pub(crate) fn text_translation_json(text: String, translation: Translation) -> Value {
+    json!({
+        "text": text,
+        "translated": translation.text,
+        "style": translation.translator
+    })
+}
+
+/// Get the translation cache key of a text translated by the translate endpoint.
+/// 
+/// The key has the text itself, so the instances sharing the cache backend agree on it and two texts never share a key,
+/// the texts are at most `MAX_BODY_BYTES` long. The `text:` prefix keeps them apart from the pokemon names.
+pub(crate) fn get_text_translation_key(text: &str, translator: TranslatorKind) -> String {
+    format!("text:{}:{}", translator.name(), text)
+}
+
+/// Get the data for the pokemon/pokemon_name/moves endpoint.
+/// 
+/// The endpoint will return the moves the pokemon can learn as a JSON object.
+/// - name: String
+/// - moves: Array of
+///   - name: String
+///   - learn_method: String
+///   - level: Number, only for the level-up learn method
+///   - version_group: String, the most recent version group the move is learnable in with the learn method
+///   - type: String, null if the move can't be fetched
+///   - power: Number, null for the status moves
+///   - accuracy: Number, null for the moves that never miss
+/// 
+/// The optional `method` query parameter filters the moves by learn method (level-up, machine, egg or tutor),
+/// the optional `version_group` one keeps the moves learnable in the version group, e.g. scarlet-violet.
+/// The endpoint will cache the moves of the pokemon in the sub-resource cache, and the data of each move,
+/// fetched `MAX_CONCURRENT_MOVE_FETCHES` at a time, so the moves learned by many pokemon are fetched once.
+pub(crate) async fn get_pokemon_moves(pokemon_name_to_search: String, query: MovesQuery, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name_to_search);
+    if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+        return Ok(invalid_name_reply(code));
+    }
+
+    let mut validator = QueryValidator::new();
+    let method = validator.parse("method", query.method.as_deref(), |method| {
+        if !LEARN_METHODS.contains(&method) {
+            return Err(format!("Unknown learn method, use one of: {}", LEARN_METHODS.join(", ")));
+        }
+        Ok(method.to_string())
+    });
+    let version_group = validator.parse("version_group", query.version_group.as_deref(), |version_group| {
+        let version_group = version_group.to_lowercase();
+        if version_group.is_empty() || !version_group.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err("Invalid version group, use a PokeAPI version group name, e.g. scarlet-violet".to_string());
+        }
+        Ok(version_group)
+    });
+    if let Err(reply) = validator.finish() {
+        return Ok(reply);
+    }
+
+    let cache_key = match &version_group {
+        Some(version_group) => format!("{}/{}", pokemon_name_to_search, version_group),
+        None => pokemon_name_to_search.clone(),
+    };
+    let moves: Result<Vec<PokemonMove>, _> = get_or_fetch_sub_resource(&cache_sub_resources, &cache_key, ResourceKind::Moves, &config, || {
+        fetch_pokemon_moves_from_api(pokemon_name_to_search.clone(), version_group.clone(), &config)
+    }).await;
+
+    let moves = moves.map_err(PokedexError::from)?;
+
+    let mut moves: Vec<PokemonMove> = moves
+        .into_iter()
+        .filter(|pokemon_move| method.as_ref().is_none_or(|method| pokemon_move.learn_method == *method))
+        .collect();
+
+    // A move can be learned with several methods, its data is fetched once.
+    let mut move_names: Vec<String> = moves.iter().map(|pokemon_move| pokemon_move.name.clone()).collect();
+    move_names.sort();
+    move_names.dedup();
+    let move_details: HashMap<String, MoveDetails> = futures::stream::iter(move_names)
+        .map(|move_name| {
+            let (cache_sub_resources, config) = (cache_sub_resources.clone(), config.clone());
+            async move {
+                let details: Result<MoveDetails, _> = get_or_fetch_sub_resource(&cache_sub_resources, &move_name, ResourceKind::MoveDetails, &config, || {
+                    fetch_move_details_from_api(move_name.clone(), &config)
+                }).await;
+                match details {
+                    Ok(details) => Some((move_name, details)),
+                    Err(error) => {
+                        warn!("Failed to fetch the move {}: {}", move_name, error);
+                        None
+                    }
+                }
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_MOVE_FETCHES)
+        .filter_map(|details| async move { details })
+        .collect()
+        .await;
+
+    for pokemon_move in moves.iter_mut() {
+        if let Some(details) = move_details.get(&pokemon_move.name) {
+            pokemon_move.move_type = Some(details.move_type.clone());
+            pokemon_move.power = details.power;
+            pokemon_move.accuracy = details.accuracy;
+        }
+    }
+
+    let reply = json!({
+        "name": pokemon_name_to_search,
+        "moves": moves
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// Get the data for the pokemon/pokemon_name/abilities endpoint.
+/// 
+/// The endpoint will return the abilities of the pokemon as a JSON object.
+/// - name: String
+/// - abilities: Array of
+///   - name: String
+///   - is_hidden: bool
+///   - effect: String, the english effect text, null if PokeAPI has none
+///   - short_effect: String, the english short effect text, null if PokeAPI has none
+/// 
+/// The endpoint will cache the abilities of the pokemon, and the effects of each ability, in the sub-resource cache,
+/// so the common abilities, like `static`, are fetched once for all the pokemon having them.
+/// An effect that can't be fetched is `null`, the other abilities are still returned.
+pub(crate) async fn get_pokemon_abilities(pokemon_name_to_search: String, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name_to_search);
+    if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+        return Ok(invalid_name_reply(code));
+    }
+
+    let abilities: Result<Vec<PokemonAbility>, _> = get_or_fetch_sub_resource(&cache_sub_resources, &pokemon_name_to_search, ResourceKind::Abilities, &config, || {
+        fetch_pokemon_abilities_from_api(pokemon_name_to_search.clone(), &config)
+    }).await;
+
+    let abilities = abilities.map_err(PokedexError::from)?;
+
+    let abilities = futures::future::join_all(abilities.into_iter().map(|mut ability| {
+        let (cache_sub_resources, config) = (cache_sub_resources.clone(), config.clone());
+        async move {
+            let effect: Result<AbilityEffect, _> = get_or_fetch_sub_resource(&cache_sub_resources, &ability.name, ResourceKind::AbilityEffect, &config, || {
+                fetch_ability_effect_from_api(ability.name.clone(), &config)
+            }).await;
+            match effect {
+                Ok(effect) => {
+                    ability.effect = effect.effect;
+                    ability.short_effect = effect.short_effect;
+                }
+                Err(error) => warn!("Failed to fetch the effect of the ability {}: {}", ability.name, error),
+            }
+            ability
+        }
+    })).await;
+
+    let reply = json!({
+        "name": pokemon_name_to_search,
+        "abilities": abilities
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// Get the data for the pokemon/pokemon_name/cry endpoint.
+/// 
+/// The endpoint will return the URLs of the pokemon cry audio as a JSON object.
+/// - name: String
+/// - latest: String, null if PokeAPI has no cry for the pokemon
+/// - legacy: String, null if PokeAPI has no cry for the pokemon, e.g. for the pokemon introduced after Gen 5
+/// 
+/// The endpoint will cache the cries of the pokemon in the sub-resource cache.
+pub(crate) async fn get_pokemon_cry(pokemon_name_to_search: String, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name_to_search);
+    if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+        return Ok(invalid_name_reply(code));
+    }
+
+    let cries: Result<CriesResponse, _> = get_or_fetch_sub_resource(&cache_sub_resources, &pokemon_name_to_search, ResourceKind::Cries, &config, || {
+        fetch_pokemon_cries_from_api(pokemon_name_to_search.clone(), &config)
+    }).await;
+
+    let cries = cries.map_err(PokedexError::from)?;
+
+    Ok(json_reply(&cries, warp::http::StatusCode::OK))
+}
+
+/// Get the data for the habitat/habitat endpoint.
+/// 
+/// The endpoint will return the pokemon species living in the habitat as a JSON object.
+/// - name: String
+/// - count: Number
+/// - pokemon: Array of
+///   - name: String
+///   - url: String, the pokemon/pokemon_name endpoint of the pokemon
+/// 
+/// The habitat is one of `POKEMON_HABITATS`, the other names get a 404 without calling the PokeAPI.
+/// The endpoint will cache the species of the habitat in the sub-resource cache.
+pub(crate) async fn get_habitat(habitat: String, path_prefix: String, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let habitat = normalize_pokemon_name(&habitat);
+    if !POKEMON_HABITATS.contains(&habitat.as_str()) {
+        return Err(PokedexError::NotFound("Habitat not found").into());
+    }
+
+    let species: Result<Vec<String>, _> = get_or_fetch_sub_resource(&cache_sub_resources, &habitat, ResourceKind::HabitatSpecies, &config, || {
+        fetch_habitat_species_from_api(habitat.clone(), &config)
+    }).await;
+    let species = species.map_err(|error| {
+        warn!("Failed to fetch the species of the habitat {}: {}", habitat, error);
+        PokedexError::UpstreamFailed("PokeAPI failed to return the habitat")
+    })?;
+
+    let reply = HabitatResponse {
+        count: species.len(),
+        pokemon: species.into_iter().map(|name| PokemonListEntry { url: format!("{}/pokemon/{}", path_prefix, name), name }).collect(),
+        name: habitat,
+    };
+    let max_age_seconds = get_env_u32("POKEMON_MAX_AGE_SECONDS", 24 * 60 * 60);
+    Ok(with_cache_control(json_reply(&reply, warp::http::StatusCode::OK), Some(max_age_seconds)))
+}
+
+/// The habitats of the PokeAPI pokemon-habitat resource, the only names accepted by the habitat/habitat endpoint.
+pub(crate) const POKEMON_HABITATS: [&str; 9] = ["cave", "forest", "grassland", "mountain", "rare", "rough-terrain", "sea", "urban", "waters-edge"];
+
+/// Get the data for the type/type endpoint, a page of the pokemon of the type, like the pokemon endpoint.
+/// 
+/// The type is one of `POKEMON_TYPES`, the other names get a 404 without calling the PokeAPI.
+/// The pokemon include the alternate forms of the type, e.g. `charizard-mega-y` for fire, in the PokeAPI order.
+/// The endpoint will cache the pokemon of the type in the sub-resource cache, so the pagination is served from memory.
+pub(crate) async fn get_type(pokemon_type: String, query: PokemonListQuery, path_prefix: String, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<warp::reply::Response, warp::Rejection> {
+    let pokemon_type = normalize_pokemon_name(&pokemon_type);
+    if !POKEMON_TYPES.contains(&pokemon_type.as_str()) {
+        return Err(PokedexError::NotFound("Type not found").into());
+    }
+
+    let mut validator = QueryValidator::new();
+    let (limit, offset) = parse_pagination(&mut validator, &query);
+    if let Err(reply) = validator.finish() {
+        return Ok(with_cache_control(reply, None).into_response());
+    }
+
+    let names: Result<Vec<String>, _> = get_or_fetch_sub_resource(&cache_sub_resources, &pokemon_type, ResourceKind::TypePokemon, &config, || {
+        fetch_type_pokemon_from_api(pokemon_type.clone(), &config)
+    }).await;
+    let names = names.map_err(|error| {
+        warn!("Failed to fetch the pokemon of the type {}: {}", pokemon_type, error);
+        PokedexError::UpstreamFailed("PokeAPI failed to return the type")
+    })?;
+
+    let page = get_pokemon_list_page(&names, limit, offset, &path_prefix, &format!("type/{}", pokemon_type));
+    let max_age_seconds = get_env_u32("POKEMON_MAX_AGE_SECONDS", 24 * 60 * 60);
+    Ok(with_cache_control(json_reply(&page, warp::http::StatusCode::OK), Some(max_age_seconds)).into_response())
+}
+
+/// The types of the PokeAPI type resource with pokemon, the only names accepted by the type/type endpoint.
+pub(crate) const POKEMON_TYPES: [&str; 18] = [
+    "bug", "dark", "dragon", "electric", "fairy", "fighting", "fire", "flying", "ghost",
+    "grass", "ground", "ice", "normal", "poison", "psychic", "rock", "steel", "water",
+];
+
+/// Get the data for the pokemon/pokemon_name/evolution-chain endpoint.
+/// 
+/// The endpoint will return the evolution chain of the pokemon species as a JSON object.
+/// - name: String
+/// - stages: Array of
+///   - name: String
+///   - stage: Number, 1 for the first stage
+///   - evolves_from: String, null for the first stage
+///   - trigger: String, how the pokemon evolves, e.g. level-up, use-item or trade
+///   - item: String, the item used to evolve, e.g. thunder-stone
+///   - min_level: Number, the minimum level for the level-up evolutions
+/// 
+/// The branching chains, like eevee's, have several stages with the same number.
+/// The endpoint will cache the evolution chain of the pokemon in the sub-resource cache.
+pub(crate) async fn get_pokemon_evolution_chain(pokemon_name_to_search: String, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name_to_search);
+    if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+        return Ok(invalid_name_reply(code));
+    }
+
+    let stages: Result<Vec<EvolutionStage>, _> = get_or_fetch_sub_resource(&cache_sub_resources, &pokemon_name_to_search, ResourceKind::EvolutionChain, &config, || {
+        fetch_pokemon_evolution_chain_from_api(pokemon_name_to_search.clone(), &config)
+    }).await;
+
+    let stages = stages.map_err(PokedexError::from)?;
+
+    let reply = json!({
+        "name": pokemon_name_to_search,
+        "stages": stages
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// Get the data for the pokemon/pokemon_name/descriptions endpoint.
+/// 
+/// The endpoint will return all the flavor texts of the pokemon, not only the first one, as a JSON object.
+/// - name: String
+/// - descriptions: Array of
+///   - description: String
+///   - language: String
+///   - version: String, null if PokeAPI doesn't say the game version
+/// 
+/// The flavor texts are in english by default, the `lang` query parameter is a comma separated list of languages,
+/// e.g. `lang=it,de`, or `all` for every language. The pokemon with a single flavor text get an array of one.
+/// The endpoint will cache the flavor texts of the pokemon in all the languages, in the sub-resource cache.
+pub(crate) async fn get_pokemon_descriptions(pokemon_name_to_search: String, query: DescriptionsQuery, cache_sub_resources: SubResourceCache, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let pokemon_name_to_search = normalize_pokemon_name(&pokemon_name_to_search);
+    if let Err(code) = validate_pokemon_name(&pokemon_name_to_search) {
+        return Ok(invalid_name_reply(code));
+    }
+
+    let mut validator = QueryValidator::new();
+    let languages = validator.parse("lang", Some(query.lang.as_deref().unwrap_or("en")), |lang| match lang {
+        "all" => Ok(None),
+        lang => parse_description_languages(lang)
+            .map(Some)
+            .map_err(|language| format!("Unknown language {}, use all or one of: {}", language, DESCRIPTION_LANGUAGES.join(", "))),
+    });
+    if let Err(reply) = validator.finish() {
+        return Ok(reply);
+    }
+    let languages = languages.flatten();
+
+    let flavor_texts: Result<Vec<FlavorTextEntry>, _> = get_or_fetch_sub_resource(&cache_sub_resources, &pokemon_name_to_search, ResourceKind::FlavorTexts, &config, || {
+        fetch_pokemon_flavor_texts_from_api(pokemon_name_to_search.clone(), &config)
+    }).await;
+
+    let flavor_texts = flavor_texts.map_err(PokedexError::from)?;
+
+    let descriptions: Vec<&FlavorTextEntry> = flavor_texts
+        .iter()
+        .filter(|flavor_text| languages.as_ref().is_none_or(|languages| languages.contains(&flavor_text.language)))
+        .collect();
+
+    let reply = json!({
+        "name": pokemon_name_to_search,
+        "descriptions": descriptions
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// The query parameters of the pokemon/pokemon_name/descriptions endpoint.
+#[derive(Deserialize)]
+pub(crate) struct DescriptionsQuery {
+    pub(crate) lang: Option<String>,
+}
+
+/// The learn methods accepted by the `method` query parameter of the moves endpoint.
+pub(crate) const LEARN_METHODS: [&str; 4] = ["level-up", "machine", "egg", "tutor"];
+
+/// The maximum number of moves of the moves endpoint fetched at the same time from PokeAPI.
+const MAX_CONCURRENT_MOVE_FETCHES: usize = 10;
+
+/// The query parameters of the pokemon/pokemon_name/moves endpoint.
+#[derive(Deserialize)]
+pub(crate) struct MovesQuery {
+    pub(crate) method: Option<String>,
+    pub(crate) version_group: Option<String>,
+}
+
+/// Get the data for the admin/cache endpoint.
+/// 
+/// The endpoint will return the keys and the number of entries of the caches as a JSON object.
+/// - pokemon: { count: Number, names: Array of String }
+/// - translations: { count: Number, names: Array of String }
+/// 
+/// The endpoint requires the admin token, see `with_admin_token`.
+pub(crate) async fn get_admin_cache(cache_pokemon: Cache<String, PokemonResponse>, cache_translation: Cache<String, Translation>) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut pokemon_names: Vec<String> = cache_pokemon.lock().unwrap().keys().cloned().collect();
+    let mut translation_names: Vec<String> = cache_translation.lock().unwrap().keys().cloned().collect();
+    pokemon_names.sort();
+    translation_names.sort();
+
+    let reply = json!({
+        "pokemon": {
+            "count": pokemon_names.len(),
+            "names": pokemon_names
+        },
+        "translations": {
+            "count": translation_names.len(),
+            "names": translation_names
+        }
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// Delete the data for the admin/cache endpoint.
+/// 
+/// The endpoint will empty the pokemon and translation caches, the in-memory ones and the cache backend,
+/// and return the number of entries removed as a JSON object.
+/// - removed: Number
+/// - pokemon: Number, removed from the in-memory cache
+/// - translations: Number, removed from the in-memory cache
+/// - backend: Number, removed from the cache backend, always 0 with the memory backend
+/// 
+/// The endpoint requires the admin token, see `with_admin_token`.
+pub(crate) async fn delete_admin_cache(cache_pokemon: Cache<String, PokemonResponse>, cache_translation: Cache<String, Translation>) -> Result<impl warp::Reply, warp::Rejection> {
+    let pokemon_removed = cache_pokemon.lock().unwrap().drain().count();
+    let translations_removed = cache_translation.lock().unwrap().drain().count();
+    // With a shared backend the next miss would get the entries back from it, so they are deleted there too.
+    let backend_removed = delete_prefix_from_cache_backend("pokemon:").await + delete_prefix_from_cache_backend("translation:").await;
+
+    info!("Cleared the caches, removed {} pokemon, {} translations and {} backend entries", pokemon_removed, translations_removed, backend_removed);
+
+    let reply = json!({
+        "removed": pokemon_removed + translations_removed + backend_removed,
+        "pokemon": pokemon_removed,
+        "translations": translations_removed,
+        "backend": backend_removed
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// The longest list of names accepted by the admin/preload endpoint.
+pub(crate) const MAX_PRELOAD_SIZE: usize = 100;
+
+/// The body of the admin/preload endpoint.
+#[derive(Deserialize)]
+pub(crate) struct PreloadRequest {
+    pub(crate) names: Vec<String>,
+}
+
+/// Post the data for the admin/preload endpoint.
+/// 
+/// The endpoint takes a JSON body with the list of names, up to `MAX_PRELOAD_SIZE`, and fetches them into the cache
+/// in the background, sharing the semaphore of the warm-up. It replies right away with a 202 and the job id as a JSON object.
+/// - job_id: String, for polling the results with the admin/preload/job_id endpoint
+/// - status: String, `running`
+/// 
+/// The endpoint requires the admin token, see `with_admin_token`.
+pub(crate) async fn post_admin_preload(body: PreloadRequest, semaphore: Arc<Semaphore>, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, preload_jobs: PreloadJobs, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    if body.names.is_empty() || body.names.len() > MAX_PRELOAD_SIZE {
+        let reply = ApiError::new(format!("The preload must have between 1 and {} names", MAX_PRELOAD_SIZE));
+        return Ok(json_reply(&reply, warp::http::StatusCode::BAD_REQUEST));
+    }
+
+    let job_id = uuid::Uuid::new_v4().to_string();
+    preload_jobs.lock().unwrap().insert(job_id.clone(), None);
+    info!("Started the preload job {} with {} pokemon", job_id, body.names.len());
+
+    let job = job_id.clone();
+    tokio::spawn(async move {
+        let results = preload_pokemon(body.names, semaphore, cache_pokemon, cache_aliases, cache_not_found, config).await;
+        let cached = results.values().filter(|result| **result == PreloadResult::Cached).count();
+        info!("The preload job {} is done, cached {} of {} pokemon", job, cached, results.len());
+        preload_jobs.lock().unwrap().insert(job, Some(results));
+    });
+
+    let reply = json!({
+        "job_id": job_id,
+        "status": "running"
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::ACCEPTED))
+}
+
+/// Get the data for the admin/preload/job_id endpoint.
+/// 
+/// The endpoint will return the status of the preload job as a JSON object.
+/// - job_id: String
+/// - status: String, `running` or `done`
+/// - results: { pokemon name: `cached`, `not_found` or `invalid_name` }, only when the job is done
+/// 
+/// The endpoint requires the admin token, see `with_admin_token`.
+pub(crate) async fn get_admin_preload(job_id: String, preload_jobs: PreloadJobs) -> Result<impl warp::Reply, warp::Rejection> {
+    let job = preload_jobs.lock().unwrap().get(&job_id).cloned();
+
+    let reply = match job {
+        None => return Err(PokedexError::NotFound("Preload job not found").into()),
+        Some(None) => json!({
+            "job_id": job_id,
+            "status": "running"
+        }),
+        Some(Some(results)) => json!({
+            "job_id": job_id,
+            "status": "done",
+            "results": results
+        }),
+    };
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// Get the data for the schema endpoint.
+/// 
+/// The endpoint will return the JSON Schema of the pokemon data returned by the pokemon and translated endpoints,
+/// for the clients generating their bindings. The schema is generated from `PokemonResponse`, so it can't get out of sync.
+pub(crate) async fn get_schema() -> Result<impl warp::Reply, warp::Rejection> {
+    let schema = schema_for!(PokemonResponse);
+    Ok(json_reply(&schema, warp::http::StatusCode::OK))
+}
+
+/// Get the data for the health/live endpoint, the server is up: always a 200 `{"status": "ok"}`.
+pub(crate) async fn get_health_live() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(with_cache_control(json_reply(&json!({ "status": "ok" }), warp::http::StatusCode::OK), None))
+}
+
+/// The last readiness check of PokeAPI, reused for `READINESS_CACHE_SECONDS`.
+pub(crate) struct ReadinessCheck {
+    pub(crate) checked_at: Option<Instant>,
+    pub(crate) ready: bool,
+}
+
+impl ReadinessCheck {
+    pub(crate) fn new() -> Self {
+        ReadinessCheck {
+            checked_at: None,
+            ready: false,
+        }
+    }
+}
+
+/// Get the data for the health/ready endpoint, if the server can serve the pokemon.
+/// 
+/// The endpoint checks PokeAPI is reachable within `READINESS_TIMEOUT_SECONDS` (2 by default).
+/// - status: "ready", with a 200, or "not_ready", with a 503
+/// - pokeapi: "pass" or "fail"
+/// 
+/// The orchestrators probe the endpoint every few seconds, so the result is reused for `READINESS_CACHE_SECONDS`
+/// (10 by default) instead of calling PokeAPI on each probe.
+pub(crate) async fn get_health_ready(readiness: Arc<Mutex<ReadinessCheck>>, config: Arc<Config>) -> Result<impl warp::Reply, warp::Rejection> {
+    let cache_duration = Duration::from_secs(get_env_u32("READINESS_CACHE_SECONDS", 10) as u64);
+    let cached_ready = {
+        let readiness = readiness.lock().unwrap();
+        readiness.checked_at.filter(|checked_at| checked_at.elapsed() < cache_duration).map(|_| readiness.ready)
+    };
+
+    let ready = match cached_ready {
+        Some(ready) => ready,
+        None => {
+            let ready = check_pokeapi_reachable(Duration::from_secs(get_env_u32("READINESS_TIMEOUT_SECONDS", 2) as u64), &config).await;
+            *readiness.lock().unwrap() = ReadinessCheck { checked_at: Some(Instant::now()), ready };
+            ready
+        }
+    };
+
+    let (reply, status) = if ready {
+        (json!({ "status": "ready", "pokeapi": "pass" }), warp::http::StatusCode::OK)
+    } else {
+        (json!({ "status": "not_ready", "pokeapi": "fail" }), warp::http::StatusCode::SERVICE_UNAVAILABLE)
+    };
+    Ok(with_cache_control(json_reply(&reply, status), None))
+}
+
+/// Get the data for the stats endpoint.
+/// 
+/// The endpoint will return the uptime and the request counts as a JSON object.
+/// - uptime_seconds: Number
+/// - total_requests: Number
+/// - endpoints: { endpoint name: Number }
+/// 
+/// The requests to the stats endpoint itself are not counted.
+pub(crate) async fn get_stats(start_time: Instant, request_stats: Arc<RequestStats>) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut endpoints = serde_json::Map::new();
+    for (endpoint, count) in request_stats.endpoints.iter() {
+        endpoints.insert(endpoint.to_string(), json!(count.load(Ordering::Relaxed)));
+    }
+
+    let reply = json!({
+        "uptime_seconds": start_time.elapsed().as_secs(),
+        "total_requests": request_stats.total.load(Ordering::Relaxed),
+        "endpoints": endpoints
+    });
+    Ok(json_reply(&reply, warp::http::StatusCode::OK))
+}
+
+/// Get the data for the metrics endpoint.
+/// 
+/// The endpoint will return the request counts and the latency histograms of the endpoints
+/// in the Prometheus text format, see `render_metrics`. The requests to the metrics endpoint itself are not counted.
+pub(crate) async fn get_metrics(request_stats: Arc<RequestStats>) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::with_header(render_metrics(&request_stats), "content-type", "text/plain; version=0.0.4"))
+}
+
+/// Get the pokemon with the description translated, the shared logic of the translated endpoints.
+/// 
+/// The translator is the `style` if any, without the rate limit fallback, or the one of `get_translation`.
+/// The translations of the fallback translator are not cached, the next request tries the preferred translator again.
+/// When the translation fails the pokemon keeps its original description with `translation_applied: false`,
+/// with `strict` the failure is returned instead.
+/// The errors are returned as `PokedexError`, with the status code and the error message of the reply.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn translate_pokemon(pokemon_name_to_search: String, no_cache: bool, strict: bool, style: Option<TranslatorKind>, cache_pokemon: Cache<String, PokemonResponse>, cache_aliases: Arc<Mutex<HashMap<String, String>>>, cache_not_found: Arc<Mutex<HashMap<String, Instant>>>, cache_translation: Cache<String, Translation>, translation_quota: Arc<Mutex<TranslationQuota>>, translation_breaker: Arc<Mutex<CircuitBreaker>>, config: Arc<Config>) -> Result<PokemonResponse, PokedexError> {
+    // Get the pokemon data from the cache or fetch from the API
+    let mut pokemon = get_or_fetch_pokemon(pokemon_name_to_search, no_cache, cache_pokemon, cache_aliases, cache_not_found, config.clone()).await?;
+
+    // The translations are cached under the pokemon name returned by PokeAPI, so the aliases share them,
+    // and the style, e.g. `pikachu:pirate`, for the requested styles.
+    let cache_key = match style {
+        Some(style) => format!("{}:{}", pokemon.name, style.name()),
+        None => pokemon.name.clone(),
+    };
+    let translation_cache_disabled = is_translation_cache_disabled();
+    let translation_in_cache: Option<Translation> = if no_cache || translation_cache_disabled { None } else { get_shared_translation(cache_key.clone(), cache_translation.clone(), &config).await };
+
+    // Get the translation from the cache or fetch from the API
+    if let Some(translation) = translation_in_cache {
+        set_translation(&mut pokemon, translation);
+
+        return Ok(pokemon);
+    }
+
+    // Without a description there is nothing to translate, the placeholder of the missing descriptions is not translated either,
+    // and the short descriptions come back nearly unchanged, so they are not worth the translation quota.
+    let description = pokemon.description.clone().filter(|_| pokemon.description_available != Some(false));
+    let Some(description) = description.filter(|description| !is_too_short_to_translate(description)) else {
+        pokemon.translated = Some(false);
+
+        return Ok(pokemon);
+    };
+
+    let translation: Result<(Translation, Instant), PokedexError> = async {
+        // While the circuit is open the Fun Translations API is failing, so it is not called at all.
+        if !translation_breaker.lock().unwrap().allow_request() {
+            return Err(PokedexError::UpstreamUnavailable("Translation unavailable"));
+        }
+        // The throttled request is not sent, so it doesn't hold the test request of the half-open circuit.
+        if let Err(error) = wait_for_translation_call(&translation_quota).await {
+            translation_breaker.lock().unwrap().release_probe();
+            return Err(error);
+        }
+
+        let fetch_started = Instant::now();
+        let translation = match style {
+            Some(style) => TranslationProvider::from_env().translate(style, &description, &config).await
+                .map(|text| Translation { text, translator: style }),
+            None => get_translation(&description, &TranslatorSelection::from_pokemon(&pokemon), &translation_quota, &config).await,
+        };
+
+        // Both the successful and the rate limited calls count against the Fun Translations quota.
+        translation_quota.lock().unwrap().record_call(translation.is_ok());
+        translation_breaker.lock().unwrap().record_result(translation.is_ok());
+
+        Ok((translation?, fetch_started))
+    }.await;
+
+    // Without `strict` a failed translation, e.g. rate limited or during an outage, keeps the original description.
+    let (translation, fetch_started) = match translation {
+        Ok(translation) => translation,
+        Err(error) if !strict => {
+            warn!("Failed to translate {}, replying with the original description: {}", pokemon.name, error.message());
+            pokemon.translated = Some(false);
+            pokemon.translation_applied = Some(false);
+
+            return Ok(pokemon);
+        }
+        Err(error) => return Err(error),
+    };
+
+    // The fallback translator only stands in while the preferred one is rate limited.
+    let is_fallback = style.is_none() && translation.translator != select_translator(&TranslatorSelection::from_pokemon(&pokemon), &get_translator_type_rules()).0;
+
+    // A concurrent request can have cached the same pokemon meanwhile, the first translation cached wins.
+    let translation = if translation_cache_disabled || is_fallback {
+        translation
+    } else {
+        set_in_cache_backend(&format!("translation:{}", cache_key), &translation, get_translation_cache_ttl(&config)).await;
+        cache_fetched_value(&cache_translation, cache_key, translation, fetch_started)
+    };
+
+    set_translation(&mut pokemon, translation);
+
+    Ok(pokemon)
+}
+
+/// Wait for a token of the translation rate limit before calling the Fun Translations API, see `TranslationQuota::reserve_call`.
+/// 
+/// The calls over the rate limit are rejected with a 429 and the time to wait, instead of burning the upstream quota.
+/// The fixtures provider doesn't call the API, so it is not rate limited.
+pub(crate) async fn wait_for_translation_call(translation_quota: &Arc<Mutex<TranslationQuota>>) -> Result<(), PokedexError> {
+    if TranslationProvider::from_env() != TranslationProvider::FunTranslations {
+        return Ok(());
+    }
+
+    let wait = translation_quota.lock().unwrap().reserve_call().map_err(PokedexError::Throttled)?;
+    if !wait.is_zero() {
+        info!("Queueing the translation call for {} ms", wait.as_millis());
+        tokio::time::sleep(wait).await;
+    }
+    Ok(())
+}
+
+/// Replace the pokemon description with the translated one and add the translator used.
+/// The `translated` field tells apart the descriptions left untranslated, see `is_too_short_to_translate`.
+pub(crate) fn set_translation(pokemon: &mut PokemonResponse, translation: Translation) {
+    pokemon.description = Some(translation.text);
+    pokemon.translation = Some(translation.translator);
+    pokemon.translated = Some(true);
+}
+
+/// Check if the description has fewer words than the `TRANSLATION_MIN_WORDS` env variable, the default is 5 words.
+pub(crate) fn is_too_short_to_translate(pokemon_description: &str) -> bool {
+    let word_count = pokemon_description.split_whitespace().count();
+    word_count < get_env_u32("TRANSLATION_MIN_WORDS", 5) as usize
+}
+
+/// Pick a random name from the species list, `None` if the list is empty.
+/// 
+/// With a seed the name is picked with a `StdRng` seeded with it, so the same seed always picks the same name.
+pub(crate) fn choose_random_species(species_names: &[String], seed: Option<u64>) -> Option<&String> {
+    match seed {
+        Some(seed) => species_names.choose(&mut rand::rngs::StdRng::seed_from_u64(seed)),
+        None => species_names.choose(&mut rand::thread_rng()),
+    }
+}
+
+/// The most names suggested in the 404 of a pokemon not found.
+pub(crate) const MAX_NAME_SUGGESTIONS: usize = 5;
+
+/// Get the species names close to the pokemon name, the closest first, for the "did you mean" suggestions.
+pub(crate) fn get_name_suggestions(pokemon_name: &str, species_names: &[String]) -> Vec<String> {
+    get_fuzzy_matches(pokemon_name, species_names).into_iter().take(MAX_NAME_SUGGESTIONS).cloned().collect()
+}
+
+/// Get the species names close to the name, the closest first.
+/// 
+/// The names are within a Levenshtein distance of a third of the name length, at least 1 and at most 3,
+/// so the short names don't match half of the pokedex. The ties are sorted by name.
+pub(crate) fn get_fuzzy_matches<'a>(name: &str, species_names: &'a [String]) -> Vec<&'a String> {
+    let max_distance = (name.chars().count() / 3).clamp(1, 3);
+    let mut matches: Vec<(usize, &String)> = species_names
+        .iter()
+        .map(|species_name| (levenshtein_distance(name, species_name), species_name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    matches.sort();
+    matches.into_iter().map(|(_, species_name)| species_name).collect()
+}
+
+/// Run the handler of a pokemon name and add the "did you mean" suggestions to its 404, see `suggest_pokemon_names`.
+/// 
+/// The suggestions must be added to the rejection of the handler itself, before `handle_with_request_context` replies to it.
+pub(crate) async fn with_name_suggestions<R>(handler: impl std::future::Future<Output = Result<R, warp::Rejection>>, pokemon_name: &str, species_names: &OnceCell<Vec<String>>, config: &Config) -> Result<R, warp::Rejection> {
+    match handler.await {
+        Ok(reply) => Ok(reply),
+        Err(rejection) => Err(suggest_pokemon_names(rejection, pokemon_name, species_names, config).await),
+    }
+}
+
+/// Add the "did you mean" suggestions to the 404 of a pokemon not found, the other rejections are returned as they are.
+/// 
+/// The suggestions come from the species list shared with the pokemon/random endpoint, loaded once from the PokeAPI.
+/// If the list can't be loaded the 404 has no suggestions, like before.
+pub(crate) async fn suggest_pokemon_names(rejection: warp::Rejection, pokemon_name: &str, species_names: &OnceCell<Vec<String>>, config: &Config) -> warp::Rejection {
+    if rejection.find::<PokedexError>() != Some(&PokedexError::from(PokemonFetchError::NotFound)) {
+        return rejection;
+    }
+
+    match get_species_names(species_names, config).await {
+        Ok(names) => PokedexError::NotFoundWithSuggestions(get_name_suggestions(&normalize_pokemon_name(pokemon_name), names)).into(),
+        Err(_) => rejection,
+    }
+}
+
+/// Build all the routes of the server with their caches, the routes are served by `serve`.
+/// 
+/// The caches live as long as the routes, and the species list and the pokemon of the warm-up list
+/// are loaded in the background, so this must be called inside the tokio runtime.
+/// The routes can be embedded in another warp server, e.g. under its own path.
+pub fn build_routes(config: &Config) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // The handlers, the caches and the clients get the config passed down, see `with_config`.
+    let shared_config = Arc::new(config.clone());
+    let start_time = Instant::now();
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+    let translated_rate_limiter: Arc<Mutex<ClientRateLimiter>> = Arc::new(Mutex::new(ClientRateLimiter::new()));
+    let sub_resource_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let descriptions_cache: Cache<String, Descriptions> = Arc::new(Mutex::new(HashMap::new()));
+    let species_names: Arc<OnceCell<Vec<String>>> = Arc::new(OnceCell::new());
+    let species_names_pokemon = Arc::clone(&species_names);
+    let species_names_translated = Arc::clone(&species_names);
+    let species_names_list = Arc::clone(&species_names);
+    let species_names_search = Arc::clone(&species_names);
+    let request_stats: Arc<RequestStats> = Arc::new(RequestStats::new());
+    let in_flight_semaphore = get_in_flight_semaphore();
+
+    // Warm up the species names shared by the features that need the full list, without blocking the startup.
+    tokio::spawn(warm_up_species_names(Arc::clone(&species_names), Arc::clone(&shared_config)));
+
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    // Evict the expired entries in the background, so the caches don't grow forever.
+    tokio::spawn(evict_expired_entries(Arc::clone(&pokemon_cache), Arc::clone(&alias_cache), Arc::clone(&not_found_cache), Arc::clone(&descriptions_cache), Arc::clone(&sub_resource_cache), Arc::clone(&translation_cache), Arc::clone(&shared_config)));
+
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+    let pokemon_cache_random = Arc::clone(&pokemon_cache);
+    let alias_cache_random = Arc::clone(&alias_cache);
+    let not_found_cache_random = Arc::clone(&not_found_cache);
+    let pokemon_cache_admin = Arc::clone(&pokemon_cache);
+    let graphql_schema = build_schema(GraphqlState {
+        cache_pokemon: Arc::clone(&pokemon_cache),
+        cache_aliases: Arc::clone(&alias_cache),
+        cache_not_found: Arc::clone(&not_found_cache),
+        cache_translation: Arc::clone(&translation_cache),
+        translation_quota: Arc::clone(&translation_quota),
+        translation_breaker: Arc::clone(&translation_breaker),
+        config: Arc::clone(&shared_config),
+    });
+    let pokemon_cache_pokemon_batch = Arc::clone(&pokemon_cache);
+    let alias_cache_pokemon_batch = Arc::clone(&alias_cache);
+    let not_found_cache_pokemon_batch = Arc::clone(&not_found_cache);
+    let pokemon_cache_batch = Arc::clone(&pokemon_cache);
+    let alias_cache_batch = Arc::clone(&alias_cache);
+    let not_found_cache_batch = Arc::clone(&not_found_cache);
+    let translation_cache_batch = Arc::clone(&translation_cache);
+    let translation_quota_batch = Arc::clone(&translation_quota);
+    let translation_breaker_batch = Arc::clone(&translation_breaker);
+    let translation_cache_translate = Arc::clone(&translation_cache);
+    let translation_quota_translate = Arc::clone(&translation_quota);
+    let translation_breaker_translate = Arc::clone(&translation_breaker);
+    let sub_resource_cache_moves = Arc::clone(&sub_resource_cache);
+    let sub_resource_cache_cry = Arc::clone(&sub_resource_cache);
+    let sub_resource_cache_abilities = Arc::clone(&sub_resource_cache);
+    let sub_resource_cache_pokemon = Arc::clone(&sub_resource_cache);
+    let sub_resource_cache_habitat = Arc::clone(&sub_resource_cache);
+    let sub_resource_cache_type = Arc::clone(&sub_resource_cache);
+    let sub_resource_cache_evolution_chain = Arc::clone(&sub_resource_cache);
+    let not_found_cache_clone = Arc::clone(&not_found_cache);
+    let alias_cache_clone = Arc::clone(&alias_cache);
+    let translation_cache_admin = Arc::clone(&translation_cache);
+    let pokemon_cache_admin_clear = Arc::clone(&pokemon_cache);
+    let translation_cache_admin_clear = Arc::clone(&translation_cache);
+
+    let pokemon_cache_preload = Arc::clone(&pokemon_cache);
+    let alias_cache_preload = Arc::clone(&alias_cache);
+    let not_found_cache_preload = Arc::clone(&not_found_cache);
+    let preload_jobs: PreloadJobs = Arc::new(Mutex::new(HashMap::new()));
+    let preload_jobs_status = Arc::clone(&preload_jobs);
+
+    // The warm-up and the admin preload share the semaphore, so together they don't flood PokeAPI.
+    let preload_semaphore = get_preload_semaphore();
+
+    // Warm up the pokemon of the warm-up list in the background, the requests are served meanwhile.
+    let warm_up_list = get_warm_up_list();
+    if !warm_up_list.is_empty() {
+        tokio::spawn(warm_up_pokemon_cache(warm_up_list, Arc::clone(&preload_semaphore), Arc::clone(&pokemon_cache), Arc::clone(&alias_cache), Arc::clone(&not_found_cache), Arc::clone(&shared_config)));
+    }
+
+    let pokemon = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(get_or_head())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon"))
+        .and(with_allowed_query(&["normalize_text", "no_cache", "lang", "include_meta", "include"]))
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || descriptions_cache.clone()))
+        .and(warp::any().map(move || sub_resource_cache_pokemon.clone()))
+        .and(warp::any().map(move || species_names_pokemon.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_name: String, method, query, accept, cache, cache_aliases, cache_not_found, cache_descriptions, cache_sub_resources, species_names: Arc<OnceCell<Vec<String>>>, config: Arc<Config>, context| async move {
+            let handler = get_pokemon(pokemon_name.clone(), query, accept, cache, cache_aliases, cache_not_found, cache_descriptions, cache_sub_resources, config.clone());
+            let reply = handle_with_request_context(context, with_name_suggestions(handler, &pokemon_name, &species_names, &config)).await?;
+            Ok::<_, warp::Rejection>(without_body_for_head(&method, reply))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon")));
+
+    let path_prefix = if config.base_path.is_empty() { String::new() } else { format!("/{}", config.base_path) };
+    let path_prefix_search = path_prefix.clone();
+    let path_prefix_habitat = path_prefix.clone();
+    let path_prefix_type = path_prefix.clone();
+    let pokemon_list = warp::path("pokemon")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_list"))
+        .and(with_allowed_query(&["limit", "offset"]))
+        .and(warp::query::<PokemonListQuery>())
+        .and(warp::any().map(move || path_prefix.clone()))
+        .and(warp::any().map(move || species_names_list.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|query, path_prefix, species_names, config, context| {
+            handle_with_request_context(context, get_pokemon_list(query, path_prefix, species_names, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_list")));
+
+    let search = warp::path("search")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "search"))
+        .and(with_allowed_query(&["q", "mode"]))
+        .and(warp::query::<SearchQuery>())
+        .and(warp::any().map(move || path_prefix_search.clone()))
+        .and(warp::any().map(move || species_names_search.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|query, path_prefix, species_names, config, context| {
+            handle_with_request_context(context, get_search(query, path_prefix, species_names, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "search")));
+
+    let random_pokemon = warp::path("pokemon")
+        .and(warp::path("random"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_random"))
+        .and(with_allowed_query(&["seed"]))
+        .and(warp::query::<RandomQuery>())
+        .and(warp::any().map(move || species_names.clone()))
+        .and(warp::any().map(move || pokemon_cache_random.clone()))
+        .and(warp::any().map(move || alias_cache_random.clone()))
+        .and(warp::any().map(move || not_found_cache_random.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|query, species_names, cache, cache_aliases, cache_not_found, config, context| {
+            handle_with_request_context(context, get_random_pokemon(query, species_names, cache, cache_aliases, cache_not_found, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_random")));
+
+    let translated_pokemon = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "translated"))
+        .and(with_client_rate_limit(translated_rate_limiter.clone()))
+        .and(with_allowed_query(&["no_cache", "explain", "style", "strict"]))
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(move || alias_cache_clone.clone()))
+        .and(warp::any().map(move || not_found_cache_clone.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(warp::any().map(move || species_names_translated.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_name: String, query, cache_pokemon, cache_aliases, cache_not_found, cache_translation, translation_quota, translation_breaker, species_names: Arc<OnceCell<Vec<String>>>, config: Arc<Config>, context| async move {
+            let handler = get_translated_pokemon(pokemon_name.clone(), query, cache_pokemon, cache_aliases, cache_not_found, cache_translation, translation_quota, translation_breaker, config.clone());
+            handle_with_request_context(context, with_name_suggestions(handler, &pokemon_name, &species_names, &config)).await
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "translated")));
+
+    let pokemon_batch = warp::path("pokemon")
+        .and(warp::path("batch"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_batch"))
+        .and(json_body::<PokemonBatchRequest>())
+        .and(warp::any().map(move || pokemon_cache_pokemon_batch.clone()))
+        .and(warp::any().map(move || alias_cache_pokemon_batch.clone()))
+        .and(warp::any().map(move || not_found_cache_pokemon_batch.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|body, cache_pokemon, cache_aliases, cache_not_found, config, context| {
+            handle_with_request_context(context, post_pokemon_batch(body, cache_pokemon, cache_aliases, cache_not_found, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_batch")));
+
+    let translated_batch = warp::path("translated")
+        .and(warp::path("batch"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "translated_batch"))
+        .and(with_client_rate_limit(translated_rate_limiter.clone()))
+        .and(json_body::<TranslatedBatchRequest>())
+        .and(warp::any().map(move || pokemon_cache_batch.clone()))
+        .and(warp::any().map(move || alias_cache_batch.clone()))
+        .and(warp::any().map(move || not_found_cache_batch.clone()))
+        .and(warp::any().map(move || translation_cache_batch.clone()))
+        .and(warp::any().map(move || translation_quota_batch.clone()))
+        .and(warp::any().map(move || translation_breaker_batch.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|body, cache_pokemon, cache_aliases, cache_not_found, cache_translation, translation_quota, translation_breaker, config, context| {
+            handle_with_request_context(context, post_translated_batch(body, cache_pokemon, cache_aliases, cache_not_found, cache_translation, translation_quota, translation_breaker, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "translated_batch")));
+
+    let translate = warp::path("translate")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "translate"))
+        .and(with_client_rate_limit(translated_rate_limiter.clone()))
+        .and(json_body::<TranslateRequest>())
+        .and(warp::any().map(move || translation_cache_translate.clone()))
+        .and(warp::any().map(move || translation_quota_translate.clone()))
+        .and(warp::any().map(move || translation_breaker_translate.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|body, cache_translation, translation_quota, translation_breaker, config, context| {
+            handle_with_request_context(context, post_translate(body, cache_translation, translation_quota, translation_breaker, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "translate")));
+
+    // The translated pokemon queries count against the same client rate limit of the translated endpoints.
+    let graphql = warp::path("graphql")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "graphql"))
+        .and(with_client_rate_limit(translated_rate_limiter))
+        .and(json_body::<async_graphql::Request>())
+        .and(warp::any().map(move || graphql_schema.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|request, schema, context| handle_with_request_context(context, post_graphql(request, schema)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "graphql")));
+
+    let pokemon_moves = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_moves"))
+        .and(with_allowed_query(&["method", "version_group"]))
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || sub_resource_cache_moves.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_name, query, cache_sub_resources, config, context| handle_with_request_context(context, get_pokemon_moves(pokemon_name, query, cache_sub_resources, config)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_moves")));
+
+    let pokemon_abilities = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("abilities"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_abilities"))
+        .and(with_allowed_query(&[]))
+        .and(warp::any().map(move || sub_resource_cache_abilities.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_name, cache_sub_resources, config, context| handle_with_request_context(context, get_pokemon_abilities(pokemon_name, cache_sub_resources, config)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_abilities")));
+
+    let pokemon_cry = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("cry"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_cry"))
+        .and(with_allowed_query(&[]))
+        .and(warp::any().map(move || sub_resource_cache_cry.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_name, cache_sub_resources, config, context| handle_with_request_context(context, get_pokemon_cry(pokemon_name, cache_sub_resources, config)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_cry")));
+
+    let habitat = warp::path("habitat")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "habitat"))
+        .and(with_allowed_query(&[]))
+        .and(warp::any().map(move || path_prefix_habitat.clone()))
+        .and(warp::any().map(move || sub_resource_cache_habitat.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|habitat, path_prefix, cache_sub_resources, config, context| handle_with_request_context(context, get_habitat(habitat, path_prefix, cache_sub_resources, config)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "habitat")));
+
+    let pokemon_type = warp::path("type")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "type"))
+        .and(with_allowed_query(&["limit", "offset"]))
+        .and(warp::query::<PokemonListQuery>())
+        .and(warp::any().map(move || path_prefix_type.clone()))
+        .and(warp::any().map(move || sub_resource_cache_type.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_type, query, path_prefix, cache_sub_resources, config, context| {
+            handle_with_request_context(context, get_type(pokemon_type, query, path_prefix, cache_sub_resources, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "type")));
+
+    let pokemon_evolution_chain = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("evolution-chain"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_evolution_chain"))
+        .and(with_allowed_query(&[]))
+        .and(warp::any().map(move || sub_resource_cache_evolution_chain.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_name, cache_sub_resources, config, context| handle_with_request_context(context, get_pokemon_evolution_chain(pokemon_name, cache_sub_resources, config)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_evolution_chain")));
+
+    let pokemon_descriptions = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("descriptions"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_key())
+        .and(with_request_stats(request_stats.clone(), "pokemon_descriptions"))
+        .and(with_allowed_query(&["lang"]))
+        .and(warp::query::<DescriptionsQuery>())
+        .and(warp::any().map(move || sub_resource_cache.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|pokemon_name, query, cache_sub_resources, config, context| handle_with_request_context(context, get_pokemon_descriptions(pokemon_name, query, cache_sub_resources, config)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon_descriptions")));
+
+    let admin_cache = warp::path("admin")
+        .and(warp::path("cache"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_request_stats(request_stats.clone(), "admin_cache"))
+        .and(with_admin_token())
+        .and(warp::any().map(move || pokemon_cache_admin.clone()))
+        .and(warp::any().map(move || translation_cache_admin.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|cache_pokemon, cache_translation, context| handle_with_request_context(context, get_admin_cache(cache_pokemon, cache_translation)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "admin_cache")));
+
+    let admin_cache_clear = warp::path("admin")
+        .and(warp::path("cache"))
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(with_request_stats(request_stats.clone(), "admin_cache_clear"))
+        .and(with_admin_token())
+        .and(warp::any().map(move || pokemon_cache_admin_clear.clone()))
+        .and(warp::any().map(move || translation_cache_admin_clear.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|cache_pokemon, cache_translation, context| handle_with_request_context(context, delete_admin_cache(cache_pokemon, cache_translation)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "admin_cache_clear")));
+
+    let admin_preload = warp::path("admin")
+        .and(warp::path("preload"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_request_stats(request_stats.clone(), "admin_preload"))
+        .and(with_admin_token())
+        .and(json_body::<PreloadRequest>())
+        .and(warp::any().map(move || preload_semaphore.clone()))
+        .and(warp::any().map(move || pokemon_cache_preload.clone()))
+        .and(warp::any().map(move || alias_cache_preload.clone()))
+        .and(warp::any().map(move || not_found_cache_preload.clone()))
+        .and(warp::any().map(move || preload_jobs.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|body, semaphore, cache_pokemon, cache_aliases, cache_not_found, preload_jobs, config, context| {
+            handle_with_request_context(context, post_admin_preload(body, semaphore, cache_pokemon, cache_aliases, cache_not_found, preload_jobs, config))
+        })
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "admin_preload")));
+
+    let admin_preload_status = warp::path("admin")
+        .and(warp::path("preload"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_request_stats(request_stats.clone(), "admin_preload_status"))
+        .and(with_admin_token())
+        .and(warp::any().map(move || preload_jobs_status.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|job_id, preload_jobs, context| handle_with_request_context(context, get_admin_preload(job_id, preload_jobs)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "admin_preload_status")));
+
+    let schema = warp::path("schema")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_request_stats(request_stats.clone(), "schema"))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|context| handle_with_request_context(context, get_schema()))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "schema")));
+
+    let openapi_document = Arc::new(build_openapi_document(&config.base_path));
+    let openapi = warp::path("openapi.json")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_request_stats(request_stats.clone(), "openapi"))
+        .and(warp::any().map(move || openapi_document.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|document, context| handle_with_request_context(context, get_openapi(document)))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "openapi")));
+
+    let docs = warp::path("docs")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_request_stats(request_stats.clone(), "docs"))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|context| handle_with_request_context(context, get_docs()))
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "docs")));
+
+    let request_stats_metrics = Arc::clone(&request_stats);
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::any().map(move || request_stats_metrics.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|request_stats, context| handle_with_request_context(context, get_metrics(request_stats)));
+
+    let stats = warp::path("stats")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::any().map(move || start_time))
+        .and(warp::any().map(move || request_stats.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|start_time, request_stats, context| handle_with_request_context(context, get_stats(start_time, request_stats)));
+
+    let health_live = warp::path("health")
+        .and(warp::path("live"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|context| handle_with_request_context(context, get_health_live()));
+
+    let readiness: Arc<Mutex<ReadinessCheck>> = Arc::new(Mutex::new(ReadinessCheck::new()));
+    let health_ready = warp::path("health")
+        .and(warp::path("ready"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::any().map(move || readiness.clone()))
+        .and(with_config(shared_config.clone()))
+        .and(with_request_context(shared_config.clone()))
+        .and_then(|readiness, config, context| handle_with_request_context(context, get_health_ready(readiness, config)));
+
+    let cors = warp::cors()
+        .allow_methods(vec!["GET", "HEAD", "POST", "DELETE"])
+        .allow_headers(vec!["x-request-id", "content-type", "x-api-key"])
+        .expose_headers(vec!["x-request-id"]);
+    let cors = if config.cors_allowed_origins.is_empty() {
+        cors.allow_any_origin()
+    } else {
+        cors.allow_origins(config.cors_allowed_origins.iter().map(String::as_str))
+    };
+
+    if !config.base_path.is_empty() {
+        info!("Serving the routes under /{}", config.base_path);
+    }
+
+    // The permit of the in-flight limit is held until the route replies.
+    let limited_routes = with_in_flight_limit(in_flight_semaphore)
+        .and(
+            pokemon_list
+                .or(search)
+                .or(random_pokemon)
+                .or(pokemon)
+                .or(translated_pokemon)
+                .or(pokemon_batch)
+                .or(translated_batch)
+                .or(translate)
+                .or(graphql)
+                .or(pokemon_moves)
+                .or(pokemon_abilities)
+                .or(pokemon_cry)
+                .or(pokemon_evolution_chain)
+                .or(pokemon_descriptions)
+                .or(habitat)
+                .or(pokemon_type)
+                .or(admin_cache)
+                .or(admin_cache_clear)
+                .or(admin_preload)
+                .or(admin_preload_status)
+                .or(schema)
+                .or(openapi)
+                .or(docs)
+                .or(stats)
+                .or(metrics)
+        )
+        .map(|_permit: OwnedSemaphorePermit, reply| reply);
+
+    // The health endpoints are not behind the in-flight limit, an overloaded server is still alive.
+    with_max_path_length()
+        .and(with_base_path(&config.base_path))
+        .and(health_live.or(health_ready).unify().or(limited_routes))
+        .recover(handle_rejection)
+        .with(cors)
+}
+
+/// Serve the routes on the port of the configuration, until the process gets SIGTERM or SIGINT (Ctrl+C).
+pub async fn serve(config: Config) {
+    serve_until(config, wait_for_shutdown_signal()).await;
+}
+
+/// Serve the routes until the shutdown future completes, then shut down gracefully.
+/// 
+/// On shutdown the server stops accepting new connections and waits for the in-flight requests,
+/// so a Kubernetes or Docker stop doesn't cut off the requests waiting for PokeAPI or Fun Translations.
+/// The requests still running after `SHUTDOWN_TIMEOUT_SECONDS` (20 by default) are dropped.
+/// The caches don't need to be flushed: the cache backend is written on each fetch, see `cache::backend`.
+pub(crate) async fn serve_until(config: Config, shutdown: impl std::future::Future<Output = ()> + Send + 'static) {
+    let (drain_sender, drain_receiver) = tokio::sync::oneshot::channel::<()>();
+    let (address, server) = warp::serve(build_routes(&config)).bind_with_graceful_shutdown((config.listen_address, config.port), async {
+        drain_receiver.await.ok();
+    });
+    info!("Listening on {}", address);
+
+    let mut server = tokio::spawn(server);
+    tokio::select! {
+        _ = &mut server => return,
+        _ = shutdown => {}
+    }
+
+    info!("Shutting down, waiting for the in-flight requests");
+    drain_sender.send(()).ok();
+    let shutdown_timeout = Duration::from_secs(get_env_u32("SHUTDOWN_TIMEOUT_SECONDS", 20) as u64);
+    match tokio::time::timeout(shutdown_timeout, server).await {
+        Ok(_) => info!("Shut down"),
+        Err(_) => warn!("The in-flight requests didn't complete within {} seconds, shutting down anyway", shutdown_timeout.as_secs()),
+    }
+}
+
+/// Wait for SIGTERM, sent by Kubernetes and Docker to stop the container, or SIGINT (Ctrl+C).
+pub(crate) async fn wait_for_shutdown_signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c().await.expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).expect("Failed to listen for SIGTERM").recv().await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
@@ -0,0 +1,137 @@
+//! The request counts and latencies of the endpoints, returned by the stats and metrics endpoints.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use warp::{Filter, Reply};
+
+/// Render the request counts and the latency histograms in the Prometheus text format.
+pub(crate) fn render_metrics(request_stats: &RequestStats) -> String {
+    let mut endpoints: Vec<&&str> = request_stats.endpoints.keys().collect();
+    endpoints.sort();
+
+    let mut metrics = String::new();
+    metrics.push_str("# HELP pokedex_requests_total The number of requests to the endpoint.\n");
+    metrics.push_str("# TYPE pokedex_requests_total counter\n");
+    for endpoint in &endpoints {
+        let count = request_stats.endpoints[**endpoint].load(Ordering::Relaxed);
+        metrics.push_str(&format!("pokedex_requests_total{{endpoint=\"{}\"}} {}\n", endpoint, count));
+    }
+
+    metrics.push_str("# HELP pokedex_request_duration_seconds The latency of the replies of the endpoint.\n");
+    metrics.push_str("# TYPE pokedex_request_duration_seconds histogram\n");
+    for endpoint in &endpoints {
+        let histogram = &request_stats.latencies[**endpoint];
+        let mut cumulative_count = 0;
+        for (upper_bound, bucket) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+            cumulative_count += bucket.load(Ordering::Relaxed);
+            metrics.push_str(&format!("pokedex_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}\n", endpoint, upper_bound, cumulative_count));
+        }
+        let count = histogram.count.load(Ordering::Relaxed);
+        let sum = histogram.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        metrics.push_str(&format!("pokedex_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}\n", endpoint, count));
+        metrics.push_str(&format!("pokedex_request_duration_seconds_sum{{endpoint=\"{}\"}} {}\n", endpoint, sum));
+        metrics.push_str(&format!("pokedex_request_duration_seconds_count{{endpoint=\"{}\"}} {}\n", endpoint, count));
+    }
+
+    metrics
+}
+
+/// The endpoints counted by the stats endpoint.
+pub(crate) const STATS_ENDPOINTS: [&str; 23] = ["pokemon", "pokemon_list", "pokemon_random", "search", "pokemon_batch", "translated", "translated_batch", "translate", "graphql", "pokemon_moves", "pokemon_abilities", "pokemon_cry", "pokemon_evolution_chain", "pokemon_descriptions", "habitat", "type", "admin_cache", "admin_cache_clear", "admin_preload", "admin_preload_status", "schema", "openapi", "docs"];
+
+/// Count the requests served, in total and for each endpoint.
+/// 
+/// The counters are atomic, so the requests don't wait for each other to count.
+pub(crate) struct RequestStats {
+    pub(crate) total: AtomicU64,
+    pub(crate) endpoints: HashMap<&'static str, AtomicU64>,
+    pub(crate) latencies: HashMap<&'static str, LatencyHistogram>,
+}
+
+impl RequestStats {
+    pub(crate) fn new() -> Self {
+        RequestStats {
+            total: AtomicU64::new(0),
+            endpoints: STATS_ENDPOINTS.iter().map(|endpoint| (*endpoint, AtomicU64::new(0))).collect(),
+            latencies: STATS_ENDPOINTS.iter().map(|endpoint| (*endpoint, LatencyHistogram::new())).collect(),
+        }
+    }
+
+    /// Record the latency of a request to the endpoint.
+    pub(crate) fn record_latency(&self, endpoint: &str, latency: Duration) {
+        if let Some(histogram) = self.latencies.get(endpoint) {
+            histogram.observe(latency);
+        }
+    }
+
+    /// Count a request to the endpoint.
+    pub(crate) fn record(&self, endpoint: &str) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        if let Some(count) = self.endpoints.get(endpoint) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The upper bounds in seconds of the request latency histogram buckets, the Prometheus default buckets.
+pub(crate) const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// A histogram of the request latencies of an endpoint, see `LATENCY_BUCKETS`.
+/// 
+/// Each bucket counts the requests up to its upper bound and above the previous one,
+/// the cumulative counts of Prometheus are computed in `render_metrics`.
+pub(crate) struct LatencyHistogram {
+    pub(crate) buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    pub(crate) count: AtomicU64,
+    pub(crate) sum_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub(crate) fn new() -> Self {
+        LatencyHistogram {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn observe(&self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|upper_bound| seconds <= *upper_bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Count the requests reaching the filter for the endpoint, see `RequestStats`.
+pub(crate) fn with_request_stats(request_stats: Arc<RequestStats>, endpoint: &'static str) -> impl Filter<Extract = (), Error = std::convert::Infallible> + Clone {
+    warp::any()
+        .map(move || request_stats.record(endpoint))
+        .untuple_one()
+}
+
+/// Record the latency of the replies of the endpoint route, see `LatencyHistogram`, to be used with `warp::wrap_fn`.
+/// 
+/// The latency is measured from the route matching the request to the reply, so it includes the upstream calls.
+/// The rejected requests, like the ones for the other routes or without the admin token, are not measured.
+pub(crate) fn with_request_latency<F, T>(request_stats: Arc<RequestStats>, endpoint: &'static str) -> impl Fn(F) -> warp::filters::BoxedFilter<(warp::reply::Response,)>
+where
+    F: Filter<Extract = (T,), Error = warp::Rejection> + Clone + Send + Sync + 'static,
+    T: Reply,
+{
+    move |route| {
+        let request_stats = Arc::clone(&request_stats);
+        warp::any()
+            .map(Instant::now)
+            .and(route)
+            .map(move |start: Instant, reply: T| {
+                request_stats.record_latency(endpoint, start.elapsed());
+                reply.into_response()
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,131 @@
+//! The spans around the pokemon lookups and the PokeAPI and Fun Translations calls.
+//!
+//! Each `Span` is a `tracing` span, so the log lines have the upstream call timings and the cache hits and misses.
+//! The OpenTelemetry spans are recorded only when the server is built with the `otel` cargo feature,
+//! and exported only when the `OTEL_EXPORTER_OTLP_ENDPOINT` env variable is set.
+//! Without the feature the default builds don't pull the OpenTelemetry crates.
+
+use std::time::Instant;
+use tracing::{debug, info};
+
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{Span as _, Status, Tracer};
+#[cfg(feature = "otel")]
+use opentelemetry::KeyValue;
+
+/// The name of the tracer and of the service in the exported spans.
+#[cfg(feature = "otel")]
+const SERVICE_NAME: &str = "pokedex_api";
+
+/// Keeps the tracer provider alive, the spans not exported yet are flushed when it is dropped.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(error) = provider.shutdown() {
+                tracing::warn!("Failed to flush the OpenTelemetry spans: {}", error);
+            }
+        }
+    }
+}
+
+/// Set up the OTLP export of the spans when the `OTEL_EXPORTER_OTLP_ENDPOINT` env variable is set.
+///
+/// The exporter reads the endpoint, and the other standard `OTEL_EXPORTER_OTLP_*` env variables, by itself.
+/// If the exporter can't be built the server starts anyway, without exporting the spans.
+pub fn init() -> TelemetryGuard {
+    #[cfg(feature = "otel")]
+    {
+        if std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_err() {
+            return TelemetryGuard { provider: None };
+        }
+
+        let exporter = match opentelemetry_otlp::SpanExporter::builder().with_http().build() {
+            Ok(exporter) => exporter,
+            Err(error) => {
+                tracing::warn!("Failed to build the OTLP exporter, the spans are not exported: {}", error);
+                return TelemetryGuard { provider: None };
+            }
+        };
+
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(opentelemetry_sdk::Resource::builder().with_service_name(SERVICE_NAME).build())
+            .build();
+        opentelemetry::global::set_tracer_provider(provider.clone());
+        info!("Exporting the OpenTelemetry spans with OTLP");
+
+        TelemetryGuard { provider: Some(provider) }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    TelemetryGuard {}
+}
+
+/// A span around an upstream call or a cache lookup, it ends when it is dropped.
+/// 
+/// The `tracing` span is a child of the span of the request, see `handle_with_request_context`,
+/// so its log lines have the request id too.
+pub struct Span {
+    tracing: tracing::Span,
+    started: Instant,
+    #[cfg(feature = "otel")]
+    span: opentelemetry::global::BoxedSpan,
+}
+
+impl Span {
+    /// Start a span with the name of the traced function, the attributes are added with the `record` methods.
+    pub fn start(name: &'static str) -> Span {
+        // The tracing fields must be declared upfront, they are the attributes of the `record` methods.
+        let tracing = tracing::info_span!("call", function = name, pokemon.name = tracing::field::Empty, translator = tracing::field::Empty, cache.hit = tracing::field::Empty, http.response.status_code = tracing::field::Empty);
+
+        Span {
+            tracing,
+            started: Instant::now(),
+            #[cfg(feature = "otel")]
+            span: opentelemetry::global::tracer(SERVICE_NAME).start(name),
+        }
+    }
+
+    /// Record an attribute of the span, like `pokemon.name`.
+    pub fn record(&mut self, key: &'static str, value: &str) {
+        self.tracing.record(key, value);
+        #[cfg(feature = "otel")]
+        self.span.set_attribute(KeyValue::new(key, value.to_string()));
+    }
+
+    /// Record if the value was found in the cache, as the `cache.hit` attribute, and log the hit or the miss.
+    pub fn record_cache_hit(&mut self, hit: bool) {
+        self.tracing.record("cache.hit", hit);
+        self.tracing.in_scope(|| debug!("{}", if hit { "Cache hit" } else { "Cache miss" }));
+        #[cfg(feature = "otel")]
+        self.span.set_attribute(KeyValue::new("cache.hit", hit));
+    }
+
+    /// Record the status code of the upstream reply, as the `http.response.status_code` attribute.
+    pub fn record_upstream_status(&mut self, status: u16) {
+        self.tracing.record("http.response.status_code", status);
+        #[cfg(feature = "otel")]
+        self.span.set_attribute(KeyValue::new("http.response.status_code", status as i64));
+    }
+
+    /// Record the outcome of the upstream call as the span status, and log how long the call took.
+    pub fn record_result<T, E: std::fmt::Display>(&mut self, result: &Result<T, E>) {
+        let elapsed_ms = self.started.elapsed().as_millis() as u64;
+        self.tracing.in_scope(|| match result {
+            Ok(_) => info!(elapsed_ms, "Upstream call succeeded"),
+            Err(error) => info!(elapsed_ms, %error, "Upstream call failed"),
+        });
+
+        #[cfg(feature = "otel")]
+        match result {
+            Ok(_) => self.span.set_status(Status::Ok),
+            Err(error) => self.span.set_status(Status::error(error.to_string())),
+        }
+    }
+}
@@ -0,0 +1,4814 @@
+//! The tests of the routes, the filters, the caches and the clients.
+//!
+//! The tests calling PokeAPI or Fun Translations use the mock servers of `mock_servers`, the ones calling the real APIs are ignored.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore};
+use rustemon::model::resource::FlavorText;
+use serde_json::{json, Value};
+use warp::Filter;
+
+use crate::cache::backend::*;
+use crate::cache::*;
+use crate::clients::funtranslations::*;
+use crate::clients::pokeapi::*;
+use crate::config::*;
+use crate::error::*;
+use crate::filters::*;
+use crate::graphql::*;
+use crate::mock_servers;
+use crate::models::*;
+use crate::openapi::*;
+use crate::routes::*;
+use crate::stats::*;
+use crate::utils::*;
+
+#[tokio::test]
+#[ignore = "calls the real PokeAPI"]
+async fn test_fetch_pokemon_from_api_with_common_pokemon() {
+    let pokemon = fetch_pokemon_from_api("pikachu".to_string(), &Config::default()).await.unwrap();
+    assert_eq!(pokemon.name, "pikachu");
+    assert_eq!(pokemon.habitat.as_deref(), Some("forest"));
+    assert!(!pokemon.is_legendary);
+    assert_eq!(pokemon.description.as_deref(), Some("When several of these POKéMON gather, their electricity could build and cause lightning storms."));
+}
+
+#[tokio::test]
+#[ignore = "calls the real PokeAPI"]
+async fn test_fetch_pokemon_from_api_with_legendary_pokemon() {
+    let pokemon = fetch_pokemon_from_api("mewtwo".to_string(), &Config::default()).await.unwrap();
+    assert_eq!(pokemon.name, "mewtwo");
+    assert_eq!(pokemon.habitat.as_deref(), Some("rare"));
+    assert!(pokemon.is_legendary);
+    assert_eq!(pokemon.description.as_deref(), Some("It was created by a scientist after years of horrific gene splicing and DNA engineering experiments."));
+}
+
+#[tokio::test]
+#[ignore = "calls the real PokeAPI"]
+async fn test_fetch_pokemon_from_api_with_cave_pokemon() {
+    let pokemon = fetch_pokemon_from_api("zubat".to_string(), &Config::default()).await.unwrap();
+    assert_eq!(pokemon.name, "zubat");
+    assert_eq!(pokemon.habitat.as_deref(), Some("cave"));
+    assert!(!pokemon.is_legendary);
+    assert_eq!(pokemon.description.as_deref(), Some("Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets."));
+}
+
+#[tokio::test]
+#[ignore = "calls the real Fun Translations API"]
+async fn test_fetch_yoda_translation_from_api_with_mewtwo_description() {
+    let translation = fetch_yoda_translation_from_api(
+        "It was created by a scientist after years of horrific gene splicing and DNA engineering experiments.", &Config::default()).await.unwrap();
+
+    // The translation lowercase the DNA to dna.
+    assert_eq!(translation, "Created by a scientist after years of horrific gene splicing and dna engineering experiments, it was.");
+}
+
+#[tokio::test]
+#[ignore = "calls the real Fun Translations API"]
+async fn test_fetch_yoda_translation_from_api_with_zubat_description() {
+    let translation = fetch_yoda_translation_from_api(
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.", &Config::default()).await.unwrap();
+
+    assert_eq!(translation, "Forms colonies in perpetually dark places.Ultrasonic waves to identify and approach targets, uses.");
+}
+
+#[tokio::test]
+#[ignore = "calls the real Fun Translations API"]
+async fn test_fetch_shakespeare_translation_from_api_with_pikachu_description() {
+    let translation = fetch_shakespeare_translation_from_api(
+        "When several of these POKéMON gather, their electricity could build and cause lightning storms.", &Config::default()).await.unwrap();
+
+    assert_eq!(translation, "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.");
+}
+
+#[tokio::test]
+async fn test_get_description_with_flavor_text_entries() {
+    use rustemon::model::resource::NamedApiResource;
+    use rustemon::model::utility::Language;
+
+    let mut en_language: NamedApiResource<Language> = rustemon::model::resource::NamedApiResource::default(); 
+    en_language.name = "en".to_string();
+
+    let mut zh_language: NamedApiResource<Language> = rustemon::model::resource::NamedApiResource::default();
+    zh_language.name = "zh-Hant".to_string();
+
+    let flavor_text_entries = vec![
+        FlavorText {
+            flavor_text: "因為沒有眼珠所以看不見東西。會從口中發出超音波 來探測周圍的狀況".to_string(),
+            language: zh_language,
+            version: None
+        },
+        FlavorText {
+            flavor_text: "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.".to_string(),
+            language: en_language,
+            version: None
+        }
+    ];
+
+    let descriptions = get_descriptions_by_language(flavor_text_entries);
+    let english_description = get_description_for_language(&descriptions, &[], None);
+    assert_eq!(english_description.as_deref(), Some("Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets."));
+}
+
+#[tokio::test]
+async fn test_get_translation_with_cave_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "yoda",
+        "Forms colonies in perpetually dark places.Ultrasonic waves to identify and approach targets, uses.").await;
+
+    let translation = get_translation(
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.",
+        &TranslatorSelection { habitat: Some("cave"), is_legendary: false, types: &[] },
+        &Mutex::new(TranslationQuota::new()), &Config::default()
+    ).await.unwrap();
+
+    assert_eq!(translation.text, "Forms colonies in perpetually dark places.Ultrasonic waves to identify and approach targets, uses.");
+    assert_eq!(translation.translator, TranslatorKind::Yoda);
+}
+
+#[tokio::test]
+async fn test_get_translation_with_legendary_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "yoda",
+        "Created by a scientist after years of horrific gene splicing and dna engineering experiments, it was.").await;
+
+    let translation = get_translation(
+        "It was created by a scientist after years of horrific gene splicing and DNA engineering experiments.",
+        &TranslatorSelection { habitat: Some("rare"), is_legendary: true, types: &[] },
+        &Mutex::new(TranslationQuota::new()), &Config::default()
+    ).await.unwrap();
+
+    assert_eq!(translation.text, "Created by a scientist after years of horrific gene splicing and dna engineering experiments, it was.");
+    assert_eq!(translation.translator, TranslatorKind::Yoda);
+}
+
+#[tokio::test]
+async fn test_get_translation_with_common_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare",
+        "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let translation = get_translation(
+        "When several of these POKéMON gather, their electricity could build and cause lightning storms.",
+        &TranslatorSelection { habitat: Some("forest"), is_legendary: false, types: &[] },
+        &Mutex::new(TranslationQuota::new()), &Config::default()
+    ).await.unwrap();
+
+    assert_eq!(translation.text, "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.");
+    assert_eq!(translation.translator, TranslatorKind::Shakespeare);
+}
+
+#[test]
+fn test_get_pokemon_from_cache() {
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+
+    let pokemon = PokemonResponse {
+        name: "pikachu".to_string(),
+        description: Some("When several of these POKéMON gather, their electricity could build and cause lightning storms.".to_string()),
+        habitat: Some("forest".to_string()),
+        is_legendary: false,
+        ..Default::default()
+    };
+    pokemon_cache.lock().unwrap().insert("pikachu".to_string(), CachedValue::new(pokemon.clone()));
+
+    let (pokemon_from_cache, freshness) = get_pokemon_from_cache("pikachu".to_string(), pokemon_cache.clone(), &Config::default()).unwrap();
+    assert_eq!(pokemon_from_cache, pokemon);
+    assert_eq!(freshness, CacheFreshness::Fresh);
+}
+
+#[tokio::test]
+async fn test_get_translation_from_cache() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+
+    let translation = get_translation(
+        "When several of these POKéMON gather, their electricity could build and cause lightning storms.",
+        &TranslatorSelection { habitat: Some("forest"), is_legendary: false, types: &[] },
+        &Mutex::new(TranslationQuota::new()), &Config::default()
+    ).await.unwrap();
+    translation_cache.lock().unwrap().insert("pikachu".to_string(), CachedValue::new(translation.clone()));
+
+    let translation_from_cache = get_translation_from_cache("pikachu".to_string(), translation_cache.clone(), &Config::default());
+    assert_eq!(translation_from_cache.unwrap(), translation);
+}
+
+#[tokio::test]
+async fn test_get_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), 
+        "{\"name\":\"pikachu\",\"description\":\"When several of these POKéMON gather, their electricity could build and cause lightning storms.\",\"habitat\":\"forest\",\"is_legendary\":false}"
+    );
+}
+
+#[tokio::test]
+async fn test_get_pokemon_not_found() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/NoPokemon").reply(&f).await;
+
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), "{\"error\":\"Pokemon not found\"}");
+}
+
+#[test]
+fn test_levenshtein_distance() {
+    assert_eq!(levenshtein_distance("pikachu", "pikachu"), 0);
+    assert_eq!(levenshtein_distance("picachu", "pikachu"), 1);
+    assert_eq!(levenshtein_distance("pichu", "pikachu"), 2);
+    assert_eq!(levenshtein_distance("", "mew"), 3);
+    assert_eq!(levenshtein_distance("flabébé", "flabebe"), 2);
+}
+
+#[test]
+fn test_get_name_suggestions() {
+    let species_names: Vec<String> = ["bulbasaur", "mew", "mewtwo", "pichu", "pikachu", "raichu"].iter().map(|name| name.to_string()).collect();
+
+    assert_eq!(get_name_suggestions("picachu", &species_names), vec!["pikachu", "pichu"]);
+    assert_eq!(get_name_suggestions("bulbasuar", &species_names), vec!["bulbasaur"]);
+    // The short names only match the names a single edit away.
+    assert_eq!(get_name_suggestions("mwe", &species_names), Vec::<String>::new());
+    assert_eq!(get_name_suggestions("charizard", &species_names), Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn test_get_pokemon_not_found_with_suggestions() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    // Only the species list is mounted, so the pokemon misspelled is not found.
+    mock_servers::mock_resource_list(&mock_servers.pokeapi, "pokemon-species", &["pikachu", "raichu"]).await;
+
+    let routes = build_routes(&Config::default());
+
+    let res = warp::test::request().path("/pokemon/Picachu").reply(&routes).await;
+
+    assert_eq!(res.status(), 404);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["error"], "Pokemon not found");
+    assert_eq!(body["suggestions"][0], "pikachu");
+
+    // The translated endpoint has the suggestions too.
+    let res = warp::test::request().path("/translated/picachu").reply(&routes).await;
+
+    assert_eq!(res.status(), 404);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["suggestions"][0], "pikachu");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_species_unavailable() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    // Only the pokemon is mounted, so following its species fails.
+    let uri = mock_servers.pokeapi.uri();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", mock_servers::pokemon_json(&uri, "pikachu")).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache_clone = Arc::clone(&not_found_cache);
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache_clone.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+
+    assert_eq!(res.status(), 502);
+    assert_eq!(res.body(), "{\"error\":\"PokeAPI failed to return the pokemon\"}");
+    // The pokemon exists, so it is not remembered as not found.
+    assert!(not_found_cache.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+
+    let res = warp::test::request().path("/translated/pikachu").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), 
+        "{\"name\":\"pikachu\",\"description\":\"At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.\",\"habitat\":\"forest\",\"is_legendary\":false,\"translation\":\"shakespeare\",\"translated\":true}"
+    );
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_not_found() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/translated/NoPokemon").reply(&f).await;
+
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), "{\"error\":\"Pokemon not found\"}");
+}
+
+#[tokio::test]
+async fn test_get_or_fetch_pokemon_skips_api_for_name_in_not_found_cache() {
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    // pikachu exists in the PokeAPI, so a not found proves the API was not called.
+    not_found_cache.lock().unwrap().insert("pikachu".to_string(), Instant::now());
+
+    let pokemon = get_or_fetch_pokemon("pikachu".to_string(), false, pokemon_cache.clone(), alias_cache.clone(), not_found_cache.clone(), Arc::new(Config::default())).await;
+    assert_eq!(pokemon, Err(PokemonFetchError::NotFound));
+    assert!(pokemon_cache.lock().unwrap().is_empty());
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test]
+async fn test_get_or_fetch_pokemon_records_spans() {
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "togepi", "The shell seems to be filled with joy.", "forest", false).await;
+
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+    opentelemetry::global::set_tracer_provider(provider.clone());
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    // The first lookup fetches the pokemon, the second one finds it in the cache.
+    for _ in 0..2 {
+        let pokemon = get_or_fetch_pokemon("togepi".to_string(), false, pokemon_cache.clone(), alias_cache.clone(), not_found_cache.clone(), Arc::new(Config::default())).await;
+        assert!(pokemon.is_ok());
+    }
+    provider.force_flush().unwrap();
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let attribute = |span: &SpanData, key: &str| span.attributes.iter().find(|attribute| attribute.key.as_str() == key).map(|attribute| attribute.value.to_string());
+    let togepi_spans = |name: &str| -> Vec<SpanData> {
+        spans.iter().filter(|span| span.name == name && attribute(span, "pokemon.name").as_deref() == Some("togepi")).cloned().collect()
+    };
+
+    let lookups = togepi_spans("get_or_fetch_pokemon");
+    assert_eq!(lookups.len(), 2);
+    assert_eq!(attribute(&lookups[0], "cache.hit").as_deref(), Some("false"));
+    assert_eq!(attribute(&lookups[1], "cache.hit").as_deref(), Some("true"));
+
+    let fetches = togepi_spans("fetch_pokemon_from_api");
+    assert_eq!(fetches.len(), 1);
+    assert_eq!(fetches[0].status, opentelemetry::trace::Status::Ok);
+}
+
+#[tokio::test]
+async fn test_get_or_fetch_pokemon_remembers_not_found_name() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let pokemon = get_or_fetch_pokemon("NoPokemon".to_string(), false, pokemon_cache.clone(), alias_cache.clone(), not_found_cache.clone(), Arc::new(Config::default())).await;
+    assert_eq!(pokemon, Err(PokemonFetchError::NotFound));
+    assert!(is_in_not_found_cache("nopokemon".to_string(), not_found_cache.clone()));
+}
+
+#[tokio::test]
+async fn test_get_or_fetch_pokemon_doesnt_remember_upstream_failure() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    // The first request fails, like a transient PokeAPI failure, the next one gets the pokemon.
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .and(wiremock::matchers::path_regex("^/api/v2/pokemon/pikachu/?$"))
+        .respond_with(wiremock::ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock_servers.pokeapi)
+        .await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let pokemon = get_or_fetch_pokemon("pikachu".to_string(), false, pokemon_cache.clone(), alias_cache.clone(), not_found_cache.clone(), Arc::new(Config::default())).await;
+    assert_eq!(pokemon, Err(PokemonFetchError::Upstream));
+    assert!(!is_in_not_found_cache("pikachu".to_string(), not_found_cache.clone()));
+
+    let pokemon = get_or_fetch_pokemon("pikachu".to_string(), false, pokemon_cache.clone(), alias_cache.clone(), not_found_cache.clone(), Arc::new(Config::default())).await;
+    assert_eq!(pokemon.unwrap().name, "pikachu");
+}
+
+#[tokio::test]
+async fn test_is_in_not_found_cache_removes_expired_entry() {
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let expired = Instant::now() - get_not_found_cache_ttl() - Duration::from_secs(1);
+    not_found_cache.lock().unwrap().insert("NoPokemon".to_string(), expired);
+
+    assert!(!is_in_not_found_cache("NoPokemon".to_string(), not_found_cache.clone()));
+    assert!(not_found_cache.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_translation_quota_record_call() {
+    let mut translation_quota = TranslationQuota::new();
+
+    translation_quota.record_call(true);
+    translation_quota.record_call(false);
+
+    assert_eq!(translation_quota.hour_calls, 2);
+    assert_eq!(translation_quota.day_calls, 2);
+    assert_eq!(translation_quota.remaining_hour_calls(), 8);
+    assert_eq!(translation_quota.remaining_day_calls(), 58);
+}
+
+#[test]
+fn test_translation_quota_resets_hour_window() {
+    let mut translation_quota = TranslationQuota::new();
+    translation_quota.hour_calls = 10;
+    translation_quota.day_calls = 10;
+    translation_quota.hour_started_at = Instant::now() - Duration::from_secs(60 * 60);
+
+    translation_quota.record_call(true);
+
+    assert_eq!(translation_quota.hour_calls, 1);
+    assert_eq!(translation_quota.day_calls, 11);
+}
+
+#[test]
+fn test_translation_quota_reserve_call() {
+    let mut translation_quota = TranslationQuota::new();
+
+    // The bucket starts full with the hourly quota of 10 tokens.
+    for _ in 0..10 {
+        assert_eq!(translation_quota.reserve_call(), Ok(Duration::ZERO));
+    }
+
+    // The next token is refilled in 6 minutes, longer than the queue timeout.
+    let retry_after = translation_quota.reserve_call().unwrap_err();
+    assert!(retry_after > Duration::from_secs(359) && retry_after <= Duration::from_secs(360));
+
+    // A token refilled within the queue timeout is reserved, the call waits for it.
+    translation_quota.tokens = 1.0 - 2.0 * 10.0 / (60.0 * 60.0);
+    let wait = translation_quota.reserve_call().unwrap();
+    assert!(wait > Duration::from_secs(1) && wait <= Duration::from_secs(2));
+    assert!(translation_quota.tokens < 0.0);
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_over_the_rate_limit() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let mut translation_quota = TranslationQuota::new();
+    translation_quota.tokens = 0.0;
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(translation_quota));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(CircuitBreaker::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/translated/pikachu?strict=true").reply(&f).await;
+
+    // The call is rejected locally, without burning the Fun Translations quota.
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.headers()["retry-after"], "360");
+    assert_eq!(res.body(), "{\"error\":\"Translation rate limit reached\"}");
+    assert!(mock_servers.funtranslations.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_records_translation_call() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+    let translation_quota_clone = Arc::clone(&translation_quota);
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota_clone.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+
+    let res = warp::test::request().path("/translated/pikachu").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(translation_quota.lock().unwrap().hour_calls, 1);
+    assert_eq!(translation_quota.lock().unwrap().day_calls, 1);
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_rate_limited() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation_rate_limited(&mock_servers.funtranslations, "shakespeare").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/translated/pikachu?strict=true").reply(&f).await;
+
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.body(), "{\"error\":\"Translation failed\"}");
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_keeps_original_description_when_translation_fails() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation_rate_limited(&mock_servers.funtranslations, "shakespeare").await;
+
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache_clone = translation_cache.clone();
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(move || translation_cache_clone.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(TranslationQuota::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(CircuitBreaker::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/translated/pikachu").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["cache-control"], "no-store");
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["description"], "When several of these POKéMON gather, their electricity could build and cause lightning storms.");
+    assert_eq!(body["translated"], false);
+    assert_eq!(body["translation_applied"], false);
+    assert!(!body.as_object().unwrap().contains_key("translation"));
+    assert!(translation_cache.lock().unwrap().is_empty());
+
+    let res = warp::test::request().path("/translated/pikachu?strict=maybe").reply(&f).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn test_get_translation_falls_back_when_rate_limited() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("TRANSLATION_FALLBACK", "true");
+    mock_servers::mock_translation_rate_limited(&mock_servers.funtranslations, "yoda").await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare",
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.").await;
+
+    let translation_quota = Mutex::new(TranslationQuota::new());
+    let translation = get_translation(
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.",
+        &TranslatorSelection { habitat: Some("cave"), is_legendary: false, types: &[] },
+        &translation_quota, &Config::default()
+    ).await.unwrap();
+
+    assert_eq!(translation.text, "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.");
+    assert_eq!(translation.translator, TranslatorKind::Shakespeare);
+    // The rate limited call is recorded and the fallback took a token.
+    assert_eq!(translation_quota.lock().unwrap().hour_calls, 1);
+    assert!(translation_quota.lock().unwrap().tokens < get_translation_tokens_per_hour());
+
+    // Without a token left the fallback is not called.
+    mock_servers.set_env("TRANSLATION_QUEUE_SECONDS", "0");
+    let mut translation_quota = TranslationQuota::new();
+    translation_quota.tokens = 0.0;
+    let translation = get_translation(
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.",
+        &TranslatorSelection { habitat: Some("cave"), is_legendary: false, types: &[] },
+        &Mutex::new(translation_quota), &Config::default()
+    ).await;
+    assert_eq!(translation.unwrap_err().status(), Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+    let shakespeare_requests = mock_servers.funtranslations.received_requests().await.unwrap().iter().filter(|request| request.url.path() == "/translate/shakespeare").count();
+    assert_eq!(shakespeare_requests, 1);
+}
+
+#[tokio::test]
+async fn test_translate_pokemon_doesnt_cache_fallback_translation() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("TRANSLATION_FALLBACK", "true");
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.", "cave", false).await;
+    mock_servers::mock_translation_rate_limited(&mock_servers.funtranslations, "yoda").await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "Forms colonies in perpetually dark places. Useth ultrasonic waves to identify and approach targets.").await;
+
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let pokemon = translate_pokemon(
+        "zubat".to_string(),
+        false,
+        true,
+        None,
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        translation_cache.clone(),
+        Arc::new(Mutex::new(TranslationQuota::new())),
+        Arc::new(Mutex::new(CircuitBreaker::new())), Arc::new(Config::default()),
+    ).await.unwrap();
+
+    // The cave pokemon falls back to shakespeare, the next request tries yoda again.
+    assert_eq!(pokemon.translation, Some(TranslatorKind::Shakespeare));
+    assert!(translation_cache.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_translation_without_fallback_when_rate_limited() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_translation_rate_limited(&mock_servers.funtranslations, "yoda").await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare",
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.").await;
+
+    let translation = get_translation(
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.",
+        &TranslatorSelection { habitat: Some("cave"), is_legendary: false, types: &[] },
+        &Mutex::new(TranslationQuota::new()), &Config::default()
+    ).await;
+
+    assert_eq!(translation.unwrap_err().status(), Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+}
+
+#[tokio::test]
+async fn test_get_pokemon_content_type_has_charset() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+
+    let res = warp::test::request().path("/pokemon/NoPokemon").reply(&f).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+
+    // The rejections of warp itself, like the unknown routes, are JSON too.
+    let routes = build_routes(&Config::default());
+    let res = warp::test::request().path("/unknown").reply(&routes).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+    assert_eq!(res.body(), "{\"error\":\"Not found\"}");
+
+    let res = warp::test::request().method("DELETE").path("/pokemon/pikachu").reply(&routes).await;
+    assert_eq!(res.status(), 405);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+}
+
+#[tokio::test]
+async fn test_handle_rejection_of_invalid_requests() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+    let routes = build_routes(&Config::default());
+
+    let res = warp::test::request().method("POST").path("/pokemon/batch").header("content-type", "application/json").body("{\"names\": [").reply(&routes).await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+    assert_eq!(res.body(), "{\"error\":\"Invalid request body\"}");
+
+    let res = warp::test::request().path("/pokemon/pikachu?lang=en&lang=fr").reply(&routes).await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "{\"error\":\"Invalid query string\"}");
+
+    let res = warp::test::request().method("POST").path("/pokemon/batch").header("content-type", "text/plain").body("pikachu").reply(&routes).await;
+    assert_eq!(res.status(), 415);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_moves_with_level_up_method() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    let mut pikachu = mock_servers::pokemon_json(&base_url, "pikachu");
+    pikachu["moves"] = json!([
+        mock_servers::pokemon_move_json(&base_url, "thunder-shock", &[("level-up", 1, "scarlet-violet")]),
+        mock_servers::pokemon_move_json(&base_url, "thunderbolt", &[("machine", 0, "scarlet-violet"), ("level-up", 36, "scarlet-violet")]),
+        mock_servers::pokemon_move_json(&base_url, "volt-tackle", &[("egg", 0, "scarlet-violet")])
+    ]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", pikachu).await;
+
+    let moves_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || moves_cache.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_moves);
+
+    let res = warp::test::request().path("/pokemon/pikachu/moves?method=level-up").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    let moves = body["moves"].as_array().unwrap();
+    assert_eq!(moves.len(), 2);
+    assert!(moves.contains(&json!({ "name": "thunderbolt", "learn_method": "level-up", "level": 36, "version_group": "scarlet-violet", "type": null, "power": null, "accuracy": null })));
+    assert!(moves.iter().all(|pokemon_move| pokemon_move["learn_method"] == "level-up"));
+
+    let res = warp::test::request().path("/pokemon/pikachu/moves").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["moves"].as_array().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_moves_upstream_failure() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_resource_error(&mock_servers.pokeapi, "pokemon", "pikachu", 500).await;
+
+    let moves_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || moves_cache.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_moves)
+        .recover(handle_rejection);
+
+    // PokeAPI failing is not the pokemon not found.
+    let res = warp::test::request().path("/pokemon/pikachu/moves").reply(&f).await;
+    assert_eq!(res.status(), 502);
+    assert_eq!(res.body(), "{\"error\":\"PokeAPI failed to return the pokemon\"}");
+
+    let res = warp::test::request().path("/pokemon/nopokemon/moves").reply(&f).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_moves_by_national_dex_number_and_in_dry_run() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    let mut pikachu = mock_servers::pokemon_json(&base_url, "pikachu");
+    pikachu["moves"] = json!([mock_servers::pokemon_move_json(&base_url, "thunder-shock", &[("level-up", 1, "scarlet-violet")])]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "25", pikachu).await;
+
+    let moves_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || moves_cache.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_moves)
+        .recover(handle_rejection);
+
+    // The national dex number is looked up by id, like the pokemon endpoint.
+    let res = warp::test::request().path("/pokemon/25/moves").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["moves"][0]["name"], "thunder-shock");
+
+    // The fixtures have no moves, PokeAPI is not called in the dry run mode.
+    let received_requests = mock_servers.pokeapi.received_requests().await.unwrap().len();
+    mock_servers.set_env("DRY_RUN", "true");
+
+    let res = warp::test::request().path("/pokemon/pikachu/moves").reply(&f).await;
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.body(), "{\"error\":\"PokeAPI is not called in the dry run mode\"}");
+
+    let res = warp::test::request().path("/pokemon/ditto/moves").reply(&f).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), received_requests);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_moves_with_unknown_method() {
+    let moves_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || moves_cache.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_moves);
+
+    let res = warp::test::request().path("/pokemon/pikachu/moves?method=teleport").reply(&f).await;
+
+    assert_eq!(res.status(), 400);
+
+    let res = warp::test::request().path("/pokemon/pikachu/moves?version_group=scarlet_violet").reply(&f).await;
+
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_moves_with_version_group_and_move_details() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    let mut pikachu = mock_servers::pokemon_json(&base_url, "pikachu");
+    pikachu["moves"] = json!([
+        mock_servers::pokemon_move_json(&base_url, "thunderbolt", &[("level-up", 26, "red-blue"), ("machine", 0, "red-blue"), ("level-up", 36, "scarlet-violet")]),
+        mock_servers::pokemon_move_json(&base_url, "thunder-wave", &[("level-up", 4, "scarlet-violet")])
+    ]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", pikachu).await;
+    let mut raichu = mock_servers::pokemon_json(&base_url, "raichu");
+    raichu["moves"] = json!([mock_servers::pokemon_move_json(&base_url, "thunderbolt", &[("machine", 0, "scarlet-violet")])]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "raichu", raichu).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "move", "thunderbolt", mock_servers::move_json(&base_url, "thunderbolt", "electric", Some(90), Some(100))).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "move", "thunder-wave", mock_servers::move_json(&base_url, "thunder-wave", "electric", None, Some(90))).await;
+
+    let moves_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let moves_cache_clone = moves_cache.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || moves_cache_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_moves);
+
+    // Without a version group, the level is the one of the most recent version group.
+    let res = warp::test::request().path("/pokemon/pikachu/moves?method=level-up").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["moves"], json!([
+        { "name": "thunderbolt", "learn_method": "level-up", "level": 36, "version_group": "scarlet-violet", "type": "electric", "power": 90, "accuracy": 100 },
+        { "name": "thunder-wave", "learn_method": "level-up", "level": 4, "version_group": "scarlet-violet", "type": "electric", "power": null, "accuracy": 90 }
+    ]));
+
+    let res = warp::test::request().path("/pokemon/pikachu/moves?version_group=Red-Blue").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    let moves = body["moves"].as_array().unwrap();
+    assert_eq!(moves.len(), 2);
+    assert!(moves.contains(&json!({ "name": "thunderbolt", "learn_method": "level-up", "level": 26, "version_group": "red-blue", "type": "electric", "power": 90, "accuracy": 100 })));
+    assert!(moves.iter().all(|pokemon_move| pokemon_move["version_group"] == "red-blue"));
+    assert!(moves_cache.lock().unwrap().contains_key(&("pikachu/red-blue".to_string(), ResourceKind::Moves)));
+
+    // The data of thunderbolt is cached, raichu doesn't fetch it again.
+    let move_requests = |requests: Vec<wiremock::Request>| requests.iter().filter(|request| request.url.path().starts_with("/api/v2/move/thunderbolt")).count();
+    assert_eq!(move_requests(mock_servers.pokeapi.received_requests().await.unwrap()), 1);
+    let res = warp::test::request().path("/pokemon/raichu/moves").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["moves"][0]["power"], 90);
+    assert_eq!(move_requests(mock_servers.pokeapi.received_requests().await.unwrap()), 1);
+    assert!(moves_cache.lock().unwrap().contains_key(&("thunderbolt".to_string(), ResourceKind::MoveDetails)));
+}
+
+#[tokio::test]
+async fn test_load_species_names_runs_loader_once_under_concurrent_access() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let species_names: Arc<OnceCell<Vec<String>>> = Arc::new(OnceCell::new());
+    let loader_calls = Arc::new(AtomicUsize::new(0));
+
+    let mut tasks = Vec::new();
+    for _ in 0..10 {
+        let species_names = Arc::clone(&species_names);
+        let loader_calls = Arc::clone(&loader_calls);
+        tasks.push(tokio::spawn(async move {
+            let names = load_species_names(&species_names, || async {
+                loader_calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok::<_, ()>(vec!["bulbasaur".to_string(), "pikachu".to_string()])
+            }).await.unwrap();
+            names.len()
+        }));
+    }
+
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), 2);
+    }
+    assert_eq!(loader_calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_load_species_names_does_not_store_failed_load() {
+    let species_names: OnceCell<Vec<String>> = OnceCell::new();
+
+    let names = load_species_names(&species_names, || async { Err::<Vec<String>, _>("PokeAPI unreachable") }).await;
+    assert!(names.is_err());
+    assert!(species_names.get().is_none());
+
+    let names = load_species_names(&species_names, || async { Ok::<_, &str>(vec!["pikachu".to_string()]) }).await;
+    assert_eq!(names.unwrap(), &vec!["pikachu".to_string()]);
+}
+
+#[tokio::test]
+async fn test_request_id_is_echoed_or_generated() {
+    let moves_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || moves_cache.clone()))
+        .and(with_request_context(Arc::new(Config::default())))
+        .and_then(|pokemon_name, query, cache_moves, context| handle_with_request_context(context, get_pokemon_moves(pokemon_name, query, cache_moves, Arc::new(Config::default()))));
+
+    let res = warp::test::request()
+        .path("/pokemon/pikachu/moves?method=teleport")
+        .header("x-request-id", "my-request-id")
+        .reply(&f)
+        .await;
+    assert_eq!(res.headers()["x-request-id"], "my-request-id");
+
+    let res = warp::test::request().path("/pokemon/pikachu/moves?method=teleport").reply(&f).await;
+    let request_id = res.headers()["x-request-id"].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(request_id).is_ok());
+}
+
+#[test]
+fn test_select_translator_with_cave_pokemon() {
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("cave"), is_legendary: false, types: &[] }, &HashMap::new()), (TranslatorKind::Yoda, TranslatorReason::CaveHabitat));
+}
+
+#[test]
+fn test_select_translator_with_legendary_pokemon() {
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("rare"), is_legendary: true, types: &[] }, &HashMap::new()), (TranslatorKind::Yoda, TranslatorReason::Legendary));
+    assert_eq!(select_translator(&TranslatorSelection { habitat: None, is_legendary: true, types: &[] }, &HashMap::new()), (TranslatorKind::Yoda, TranslatorReason::Legendary));
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("cave"), is_legendary: true, types: &[] }, &HashMap::new()), (TranslatorKind::Yoda, TranslatorReason::Legendary));
+}
+
+#[test]
+fn test_select_translator_by_primary_type() {
+    let type_rules = HashMap::from([("psychic".to_string(), TranslatorKind::Yoda), ("ghost".to_string(), TranslatorKind::Shakespeare)]);
+    let types = vec!["psychic".to_string(), "fairy".to_string()];
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("urban"), is_legendary: false, types: &types }, &type_rules), (TranslatorKind::Yoda, TranslatorReason::PrimaryType("psychic".to_string())));
+
+    // The type rule wins over the habitat and the legendary flag.
+    let types = vec!["ghost".to_string()];
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("cave"), is_legendary: true, types: &types }, &type_rules), (TranslatorKind::Shakespeare, TranslatorReason::PrimaryType("ghost".to_string())));
+
+    // Only the primary type is checked, without a rule the default selection is used.
+    let types = vec!["normal".to_string(), "psychic".to_string()];
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("cave"), is_legendary: false, types: &types }, &type_rules), (TranslatorKind::Yoda, TranslatorReason::CaveHabitat));
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("forest"), is_legendary: false, types: &types }, &type_rules), (TranslatorKind::Shakespeare, TranslatorReason::Default));
+}
+
+#[test]
+fn test_select_translator_without_type_rules() {
+    let types = vec!["psychic".to_string()];
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("urban"), is_legendary: false, types: &types }, &HashMap::new()), (TranslatorKind::Shakespeare, TranslatorReason::Default));
+}
+
+#[tokio::test]
+async fn test_get_translator_type_rules() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    assert!(get_translator_type_rules().is_empty());
+
+    mock_servers.set_env("TRANSLATOR_BY_TYPE", "psychic=yoda, Ghost = shakespeare,fire=pirate,grass=elvish,water");
+    let type_rules = get_translator_type_rules();
+    assert_eq!(type_rules, HashMap::from([
+        ("psychic".to_string(), TranslatorKind::Yoda),
+        ("ghost".to_string(), TranslatorKind::Shakespeare),
+        ("fire".to_string(), TranslatorKind::Style("pirate")),
+    ]));
+}
+
+#[test]
+fn test_select_translator_with_common_pokemon() {
+    assert_eq!(select_translator(&TranslatorSelection { habitat: Some("forest"), is_legendary: false, types: &[] }, &HashMap::new()), (TranslatorKind::Shakespeare, TranslatorReason::Default));
+    assert_eq!(select_translator(&TranslatorSelection { habitat: None, is_legendary: false, types: &[] }, &HashMap::new()), (TranslatorKind::Shakespeare, TranslatorReason::Default));
+}
+
+#[test]
+fn test_translator_reason_explain() {
+    assert_eq!(TranslatorReason::Legendary.explain(TranslatorKind::Yoda), "legendary pokemon → yoda");
+    assert_eq!(TranslatorReason::CaveHabitat.explain(TranslatorKind::Yoda), "cave habitat → yoda");
+    assert_eq!(TranslatorReason::Default.explain(TranslatorKind::Shakespeare), "default → shakespeare");
+    assert_eq!(TranslatorReason::PrimaryType("psychic".to_string()).explain(TranslatorKind::Yoda), "psychic type rule → yoda");
+    assert_eq!(TranslatorReason::Requested.explain(TranslatorKind::Style("pirate")), "requested style → pirate");
+}
+
+#[test]
+fn test_translator_registry() {
+    struct UppercaseTranslator;
+
+    impl Translator for UppercaseTranslator {
+        fn name(&self) -> &'static str {
+            "uppercase"
+        }
+
+        fn translate<'a>(&'a self, text: &'a str, _config: &'a Config) -> futures::future::BoxFuture<'a, Result<String, TranslationError>> {
+            Box::pin(async move { Ok(text.to_uppercase()) })
+        }
+    }
+
+    let mut registry = TranslatorRegistry::default();
+    assert_eq!(registry.names()[..3], ["yoda", "shakespeare", "pirate"]);
+    assert!(registry.get("uppercase").is_none());
+
+    registry.register(Box::new(UppercaseTranslator));
+    registry.register(Box::new(FunTranslationsStyle("pirate")));
+    assert_eq!(registry.get("uppercase").unwrap().name(), "uppercase");
+    assert_eq!(registry.names().iter().filter(|name| **name == "pirate").count(), 1);
+
+    // The registered styles are translators of the requests, serialized with their name.
+    assert_eq!(TranslatorKind::from_name("pirate"), Some(TranslatorKind::Style("pirate")));
+    assert_eq!(TranslatorKind::from_name("elvish"), None);
+    assert_eq!(serde_json::to_value(TranslatorKind::Style("pirate")).unwrap(), json!("pirate"));
+    assert_eq!(serde_json::from_value::<TranslatorKind>(json!("yoda")).unwrap(), TranslatorKind::Yoda);
+    assert!(serde_json::from_value::<TranslatorKind>(json!("elvish")).is_err());
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_with_style() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "mewtwo", "It was created by a scientist after years of horrific gene splicing and DNA engineering experiments.", "rare", true).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "pirate", "It was created by a scientist after years o' horrific gene splicin' and dna engineerin' experiments.").await;
+
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache_clone = translation_cache.clone();
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(move || translation_cache_clone.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(TranslationQuota::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(CircuitBreaker::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    // The legendary pokemon is translated in the requested style instead of Yoda.
+    let res = warp::test::request().path("/translated/mewtwo?style=Pirate&explain=true").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(pokemon.description.as_deref(), Some("It was created by a scientist after years o' horrific gene splicin' and dna engineerin' experiments."));
+    assert_eq!(pokemon.translation, Some(TranslatorKind::Style("pirate")));
+    assert_eq!(pokemon.translation_reason.as_deref(), Some("requested style → pirate"));
+    assert!(translation_cache.lock().unwrap().contains_key("mewtwo:pirate"));
+    assert!(!translation_cache.lock().unwrap().contains_key("mewtwo"));
+
+    let res = warp::test::request().path("/translated/mewtwo?style=elvish").reply(&f).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_with_explain() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "mewtwo", "It was created by a scientist after years of horrific gene splicing and DNA engineering experiments.", "rare", true).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.", "cave", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "yoda", "Created by a scientist after years of horrific gene splicing and dna engineering experiments, it was.").await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(TranslationQuota::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(CircuitBreaker::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+
+    for (name, reason) in [("mewtwo", "legendary pokemon → yoda"), ("zubat", "cave habitat → yoda"), ("pikachu", "default → shakespeare")] {
+        let res = warp::test::request().path(&format!("/translated/{}?explain=true", name)).reply(&f).await;
+        assert_eq!(res.status(), 200);
+        let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(pokemon.translation_reason.as_deref(), Some(reason));
+    }
+
+    // Without explain=true the reason is not in the reply.
+    let res = warp::test::request().path("/translated/pikachu").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert!(!body.as_object().unwrap().contains_key("translation_reason"));
+}
+
+#[tokio::test]
+async fn test_json_body_rejects_oversized_body() {
+    let f = warp::post()
+        .and(json_body::<Value>())
+        .map(|body: Value| warp::reply::json(&body))
+        .recover(handle_rejection);
+
+    let res = warp::test::request()
+        .method("POST")
+        .json(&json!({ "names": ["pikachu"] }))
+        .reply(&f)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request()
+        .method("POST")
+        .json(&json!({ "names": vec!["pikachu"; 2000] }))
+        .reply(&f)
+        .await;
+    assert_eq!(res.status(), 413);
+    assert_eq!(res.body(), "{\"error\":\"Request body too large\"}");
+}
+
+#[tokio::test]
+async fn test_get_admin_cache_with_admin_token() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("ADMIN_TOKEN", "secret");
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    pokemon_cache.lock().unwrap().insert("pikachu".to_string(), CachedValue::new(PokemonResponse { name: "pikachu".to_string(), ..Default::default() }));
+    pokemon_cache.lock().unwrap().insert("mewtwo".to_string(), CachedValue::new(PokemonResponse { name: "mewtwo".to_string(), ..Default::default() }));
+    translation_cache.lock().unwrap().insert("mewtwo".to_string(), CachedValue::new(Translation { text: "".to_string(), translator: TranslatorKind::Yoda }));
+
+    let f = warp::path("admin")
+        .and(warp::path("cache"))
+        .and(warp::path::end())
+        .and(with_admin_token())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and_then(get_admin_cache)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/admin/cache").header("authorization", "Bearer secret").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "{\"pokemon\":{\"count\":2,\"names\":[\"mewtwo\",\"pikachu\"]},\"translations\":{\"count\":1,\"names\":[\"mewtwo\"]}}");
+}
+
+#[tokio::test]
+async fn test_get_admin_cache_without_admin_token() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("ADMIN_TOKEN", "secret");
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("admin")
+        .and(warp::path("cache"))
+        .and(warp::path::end())
+        .and(with_admin_token())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and_then(get_admin_cache)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/admin/cache").header("authorization", "Bearer wrong").reply(&f).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.body(), "{\"error\":\"Unauthorized\"}");
+
+    let res = warp::test::request().path("/admin/cache").reply(&f).await;
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn test_post_admin_preload() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("ADMIN_TOKEN", "secret");
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "It keeps its tail raised.", "forest", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places.", "cave", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let preload_jobs: PreloadJobs = Arc::new(Mutex::new(HashMap::new()));
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+    let preload_jobs_clone = Arc::clone(&preload_jobs);
+
+    let preload = warp::post()
+        .and(warp::path("admin"))
+        .and(warp::path("preload"))
+        .and(warp::path::end())
+        .and(with_admin_token())
+        .and(json_body::<PreloadRequest>())
+        .and(warp::any().map(get_preload_semaphore))
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(move || preload_jobs_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(post_admin_preload);
+    let preload_status = warp::get()
+        .and(warp::path("admin"))
+        .and(warp::path("preload"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(with_admin_token())
+        .and(warp::any().map(move || preload_jobs.clone()))
+        .and_then(get_admin_preload);
+    let f = preload.or(preload_status).recover(handle_rejection);
+
+    let body = json!({ "names": ["pikachu", "zubat", "missingno", "bad name!"] });
+    let res = warp::test::request().method("POST").path("/admin/preload").json(&body).reply(&f).await;
+    assert_eq!(res.status(), 401);
+
+    let res = warp::test::request().method("POST").path("/admin/preload").header("authorization", "Bearer secret").json(&body).reply(&f).await;
+    assert_eq!(res.status(), 202);
+    let job: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(job["status"], "running");
+    let status_path = format!("/admin/preload/{}", job["job_id"].as_str().unwrap());
+
+    // Poll the job until the background fetches are done.
+    for _ in 0..50 {
+        let res = warp::test::request().path(&status_path).header("authorization", "Bearer secret").reply(&f).await;
+        assert_eq!(res.status(), 200);
+        let status: Value = serde_json::from_slice(res.body()).unwrap();
+        if status["status"] == "done" {
+            assert_eq!(status["results"], json!({ "bad name!": "invalid_name", "missingno": "not_found", "pikachu": "cached", "zubat": "cached" }));
+            assert!(pokemon_cache.lock().unwrap().contains_key("pikachu"));
+            assert!(pokemon_cache.lock().unwrap().contains_key("zubat"));
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The preload job was not done");
+}
+
+#[tokio::test]
+async fn test_delete_admin_cache() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("ADMIN_TOKEN", "secret");
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    pokemon_cache.lock().unwrap().insert("pikachu".to_string(), CachedValue::new(PokemonResponse { name: "pikachu".to_string(), ..Default::default() }));
+    pokemon_cache.lock().unwrap().insert("mewtwo".to_string(), CachedValue::new(PokemonResponse { name: "mewtwo".to_string(), ..Default::default() }));
+    translation_cache.lock().unwrap().insert("mewtwo".to_string(), CachedValue::new(Translation { text: "".to_string(), translator: TranslatorKind::Yoda }));
+
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+    let translation_cache_clone = Arc::clone(&translation_cache);
+
+    let f = warp::delete()
+        .and(warp::path("admin"))
+        .and(warp::path("cache"))
+        .and(warp::path::end())
+        .and(with_admin_token())
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(move || translation_cache_clone.clone()))
+        .and_then(delete_admin_cache)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().method("DELETE").path("/admin/cache").reply(&f).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(pokemon_cache.lock().unwrap().len(), 2);
+
+    let res = warp::test::request().method("DELETE").path("/admin/cache").header("authorization", "Bearer secret").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "{\"backend\":0,\"pokemon\":2,\"removed\":3,\"translations\":1}");
+    assert!(pokemon_cache.lock().unwrap().is_empty());
+    assert!(translation_cache.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_normalize_text_with_pokemon_artifact() {
+    assert_eq!(
+        normalize_text("When several of these POKéMON gather, their electricity could build and cause lightning storms."),
+        "When several of these Pokémon gather, their electricity could build and cause lightning storms."
+    );
+    assert_eq!(normalize_text("It is stored in a POKé BALL."), "It is stored in a Poké Ball.");
+}
+
+#[test]
+fn test_normalize_text_leaves_other_words_untouched() {
+    let text = "It was created by a scientist after years of horrific gene splicing and DNA engineering experiments.";
+    assert_eq!(normalize_text(text), text);
+}
+
+#[test]
+fn test_collapse_whitespace() {
+    assert_eq!(collapse_whitespace("Forms  colonies"), "Forms colonies");
+    assert_eq!(collapse_whitespace("Forms   colonies"), "Forms colonies");
+    assert_eq!(collapse_whitespace("Forms    colonies"), "Forms colonies");
+    assert_eq!(collapse_whitespace("Forms\tcolonies \t in\ndark\x0Cplaces."), "Forms colonies in dark places.");
+    assert_eq!(collapse_whitespace("  Forms colonies.\n"), "Forms colonies.");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_normalize_text() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/pikachu?normalize_text=true").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["description"], "When several of these Pokémon gather, their electricity could build and cause lightning storms.");
+
+    // The cached data keeps the original description.
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["description"], "When several of these POKéMON gather, their electricity could build and cause lightning storms.");
+}
+
+#[tokio::test]
+async fn test_translation_request_has_user_agent() {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    let mock_servers = mock_servers::start_mock_servers().await;
+    Mock::given(method("POST"))
+        .and(path("/translate/yoda"))
+        .and(header("user-agent", "pokedex-api/0.1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "contents": { "translated": "Created by a scientist, it was." }
+        })))
+        .expect(1)
+        .mount(&mock_servers.funtranslations)
+        .await;
+
+    let translation = fetch_yoda_translation_from_api("It was created by a scientist.", &Config::default()).await.unwrap();
+    assert_eq!(translation, "Created by a scientist, it was.");
+}
+
+#[tokio::test]
+async fn test_translation_request_has_user_agent_from_env() {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("USER_AGENT", "my-pokedex/1.0");
+    Mock::given(method("POST"))
+        .and(path("/translate/shakespeare"))
+        .and(header("user-agent", "my-pokedex/1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "contents": { "translated": "Thee did create it." }
+        })))
+        .expect(1)
+        .mount(&mock_servers.funtranslations)
+        .await;
+
+    let translation = fetch_shakespeare_translation_from_api("You created it.", &Config::default()).await.unwrap();
+    assert_eq!(translation, "Thee did create it.");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_caches_alias_of_canonical_name() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let uri = mock_servers.pokeapi.uri();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "deoxys", mock_servers::pokemon_json(&uri, "deoxys-normal")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "deoxys-normal",
+        mock_servers::pokemon_species_json(&uri, "deoxys-normal", "A POKéMON that mutated from an extraterrestrial virus.", "rare", false)).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+    let alias_cache_clone = Arc::clone(&alias_cache);
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(move || alias_cache_clone.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/deoxys").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["name"], "deoxys-normal");
+
+    assert!(pokemon_cache.lock().unwrap().contains_key("deoxys-normal"));
+    assert!(!pokemon_cache.lock().unwrap().contains_key("deoxys"));
+    assert_eq!(alias_cache.lock().unwrap()["deoxys"], "deoxys-normal");
+
+    // Both the alias and the canonical name are served from the cache.
+    let res = warp::test::request().path("/pokemon/deoxys").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let res = warp::test::request().path("/pokemon/deoxys-normal").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let pokemon_requests = mock_servers.pokeapi.received_requests().await.unwrap()
+        .iter()
+        .filter(|request| request.url.path().starts_with("/api/v2/pokemon/"))
+        .count();
+    assert_eq!(pokemon_requests, 1);
+}
+
+#[test]
+fn test_get_pokemon_list_page() {
+    let names: Vec<String> = ["bulbasaur", "ivysaur", "venusaur", "charmander", "charmeleon"].iter().map(|name| name.to_string()).collect();
+
+    let page = get_pokemon_list_page(&names, 2, 0, "", "pokemon");
+    assert_eq!(page.count, 5);
+    assert_eq!(page.next.as_deref(), Some("/pokemon?limit=2&offset=2"));
+    assert_eq!(page.previous, None);
+    assert_eq!(page.results, vec![
+        PokemonListEntry { name: "bulbasaur".to_string(), url: "/pokemon/bulbasaur".to_string() },
+        PokemonListEntry { name: "ivysaur".to_string(), url: "/pokemon/ivysaur".to_string() },
+    ]);
+
+    let page = get_pokemon_list_page(&names, 2, 4, "/api/v1", "pokemon");
+    assert_eq!(page.next, None);
+    assert_eq!(page.previous.as_deref(), Some("/api/v1/pokemon?limit=2&offset=2"));
+    assert_eq!(page.results, vec![PokemonListEntry { name: "charmeleon".to_string(), url: "/api/v1/pokemon/charmeleon".to_string() }]);
+
+    let page = get_pokemon_list_page(&names, 2, 10, "", "type/fire");
+    assert!(page.results.is_empty());
+    assert_eq!(page.next, None);
+    assert_eq!(page.previous.as_deref(), Some("/type/fire?limit=2&offset=8"));
+}
+
+#[tokio::test]
+async fn test_get_pokemon_list() {
+    let species_names = Arc::new(OnceCell::new_with(Some(vec!["bulbasaur".to_string(), "ivysaur".to_string(), "venusaur".to_string()])));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::end())
+        .and(warp::query::<PokemonListQuery>())
+        .and(warp::any().map(String::new))
+        .and(warp::any().map(move || species_names.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_list);
+
+    let res = warp::test::request().path("/pokemon?limit=1&offset=1").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, json!({
+        "count": 3,
+        "next": "/pokemon?limit=1&offset=2",
+        "previous": "/pokemon?limit=1&offset=0",
+        "results": [{ "name": "ivysaur", "url": "/pokemon/ivysaur" }]
+    }));
+
+    let res = warp::test::request().path("/pokemon").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["results"].as_array().unwrap().len(), 3);
+
+    let res = warp::test::request().path("/pokemon?limit=101&offset=-1").reply(&f).await;
+
+    assert_eq!(res.status(), 400);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["errors"][0]["param"], "limit");
+    assert_eq!(body["errors"][1]["param"], "offset");
+}
+
+#[test]
+fn test_search_species_names() {
+    let species_names: Vec<String> = ["bulbasaur", "charmander", "charmeleon", "charizard", "pikachu", "mr-mime", "pichu", "mime-jr"].iter().map(|name| name.to_string()).collect();
+
+    assert_eq!(search_species_names("char", SearchMode::Prefix, &species_names), vec!["charmander", "charmeleon", "charizard"]);
+    assert_eq!(search_species_names("chu", SearchMode::Prefix, &species_names), Vec::<&String>::new());
+    assert_eq!(search_species_names("chu", SearchMode::Substring, &species_names), vec!["pikachu", "pichu"]);
+    // The names starting with the query come first.
+    assert_eq!(search_species_names("mime", SearchMode::Substring, &species_names), vec!["mime-jr", "mr-mime"]);
+    assert_eq!(search_species_names("charmandr", SearchMode::Fuzzy, &species_names), vec!["charmander"]);
+    assert_eq!(search_species_names("picahu", SearchMode::Fuzzy, &species_names), vec!["pichu", "pikachu"]);
+}
+
+#[tokio::test]
+async fn test_get_search() {
+    let species_names = Arc::new(OnceCell::new_with(Some(vec!["charmander".to_string(), "charmeleon".to_string(), "mr-mime".to_string()])));
+
+    let f = warp::path("search")
+        .and(warp::path::end())
+        .and(warp::query::<SearchQuery>())
+        .and(warp::any().map(|| "/api/v1".to_string()))
+        .and(warp::any().map(move || species_names.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_search);
+
+    let res = warp::test::request().path("/search?q=Char&mode=prefix").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, json!({
+        "count": 2,
+        "results": [
+            { "name": "charmander", "url": "/api/v1/pokemon/charmander" },
+            { "name": "charmeleon", "url": "/api/v1/pokemon/charmeleon" }
+        ]
+    }));
+
+    let res = warp::test::request().path("/search?q=Mr.%20Mime").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["results"][0]["name"], "mr-mime");
+
+    let res = warp::test::request().path("/search?mode=exact").reply(&f).await;
+
+    assert_eq!(res.status(), 400);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["errors"][0]["param"], "q");
+    assert_eq!(body["errors"][1]["param"], "mode");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_include_sprites() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let uri = mock_servers.pokeapi.uri();
+    let mut pokemon = mock_servers::pokemon_json(&uri, "pikachu");
+    pokemon["sprites"]["front_default"] = json!(format!("{}/sprites/pokemon/25.png", uri));
+    pokemon["sprites"]["other"]["official-artwork"]["front_default"] = json!(format!("{}/sprites/pokemon/other/official-artwork/25.png", uri));
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", pokemon).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "pikachu",
+        mock_servers::pokemon_species_json(&uri, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false)).await;
+
+    let sprites_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let sprites_cache_clone = sprites_cache.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(move || sprites_cache_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    // The default payload has no sprites.
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert!(body.get("sprites").is_none());
+
+    let res = warp::test::request().path("/pokemon/pikachu?include=sprites").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["sprites"], json!({
+        "front_default": format!("{}/sprites/pokemon/25.png", uri),
+        "front_shiny": null,
+        "official_artwork": format!("{}/sprites/pokemon/other/official-artwork/25.png", uri),
+        "official_artwork_shiny": null
+    }));
+    assert!(sprites_cache.lock().unwrap().contains_key(&("pikachu".to_string(), ResourceKind::Sprites)));
+
+    let res = warp::test::request().path("/pokemon/pikachu?include=sprites,evolutions").reply(&f).await;
+    assert_eq!(res.status(), 400);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["errors"][0]["message"], "Unknown include evolutions, use one of: sprites, stats");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_include_stats() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let uri = mock_servers.pokeapi.uri();
+    let mut pokemon = mock_servers::pokemon_json(&uri, "pikachu");
+    let base_stats = [("hp", 35), ("attack", 55), ("defense", 40), ("special-attack", 50), ("special-defense", 50), ("speed", 90)];
+    pokemon["stats"] = base_stats.iter()
+        .map(|(stat, base_stat)| json!({ "base_stat": base_stat, "effort": 0, "stat": mock_servers::named_resource(&uri, "stat", stat) }))
+        .collect();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", pokemon).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "pikachu",
+        mock_servers::pokemon_species_json(&uri, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false)).await;
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/pikachu?include=stats").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["stats"], json!({
+        "hp": 35,
+        "attack": 55,
+        "defense": 40,
+        "special_attack": 50,
+        "special_defense": 50,
+        "speed": 90,
+        "total": 320
+    }));
+    assert!(body.get("sprites").is_none());
+}
+
+#[tokio::test]
+async fn test_get_pokemon_by_dex_number() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let uri = mock_servers.pokeapi.uri();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "25", mock_servers::pokemon_json(&uri, "pikachu")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "pikachu",
+        mock_servers::pokemon_species_json(&uri, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false)).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+    let alias_cache_clone = Arc::clone(&alias_cache);
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(move || alias_cache_clone.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/25").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["name"], "pikachu");
+
+    // The number is an alias of the name, so both share the cache entry.
+    assert!(pokemon_cache.lock().unwrap().contains_key("pikachu"));
+    assert!(!pokemon_cache.lock().unwrap().contains_key("25"));
+    assert_eq!(alias_cache.lock().unwrap()["25"], "pikachu");
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let res = warp::test::request().path("/pokemon/25").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let pokemon_requests = mock_servers.pokeapi.received_requests().await.unwrap()
+        .iter()
+        .filter(|request| request.url.path().starts_with("/api/v2/pokemon/"))
+        .count();
+    assert_eq!(pokemon_requests, 1);
+}
+
+#[tokio::test]
+async fn test_get_metrics_records_latency() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let request_stats: Arc<RequestStats> = Arc::new(RequestStats::new());
+    let request_stats_clone = Arc::clone(&request_stats);
+
+    let pokemon = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(with_request_stats(request_stats.clone(), "pokemon"))
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon)
+        .with(warp::wrap_fn(with_request_latency(request_stats.clone(), "pokemon")));
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::any().map(move || request_stats_clone.clone()))
+        .and_then(get_metrics);
+    let f = pokemon.or(metrics);
+
+    let res = warp::test::request().path("/metrics").reply(&f).await;
+    let body = String::from_utf8(res.body().to_vec()).unwrap();
+    assert!(body.contains("pokedex_request_duration_seconds_count{endpoint=\"pokemon\"} 0\n"));
+
+    warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+
+    let res = warp::test::request().path("/metrics").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/plain; version=0.0.4");
+    let body = String::from_utf8(res.body().to_vec()).unwrap();
+    assert!(body.contains("pokedex_requests_total{endpoint=\"pokemon\"} 1\n"));
+    assert!(body.contains("pokedex_request_duration_seconds_bucket{endpoint=\"pokemon\",le=\"+Inf\"} 1\n"));
+    assert!(body.contains("pokedex_request_duration_seconds_count{endpoint=\"pokemon\"} 1\n"));
+    assert!(body.contains("pokedex_request_duration_seconds_count{endpoint=\"schema\"} 0\n"));
+}
+
+#[test]
+fn test_latency_histogram_buckets() {
+    let request_stats = RequestStats::new();
+    request_stats.record_latency("schema", Duration::from_millis(3));
+    request_stats.record_latency("schema", Duration::from_millis(200));
+    request_stats.record_latency("schema", Duration::from_secs(30));
+
+    let metrics = render_metrics(&request_stats);
+    assert!(metrics.contains("pokedex_request_duration_seconds_bucket{endpoint=\"schema\",le=\"0.005\"} 1\n"));
+    assert!(metrics.contains("pokedex_request_duration_seconds_bucket{endpoint=\"schema\",le=\"0.25\"} 2\n"));
+    assert!(metrics.contains("pokedex_request_duration_seconds_bucket{endpoint=\"schema\",le=\"10\"} 2\n"));
+    assert!(metrics.contains("pokedex_request_duration_seconds_bucket{endpoint=\"schema\",le=\"+Inf\"} 3\n"));
+    assert!(metrics.contains("pokedex_request_duration_seconds_sum{endpoint=\"schema\"} 30.203\n"));
+}
+
+#[tokio::test]
+async fn test_get_stats_counts_requests() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let request_stats: Arc<RequestStats> = Arc::new(RequestStats::new());
+    let request_stats_clone = Arc::clone(&request_stats);
+    let start_time = Instant::now();
+
+    let pokemon = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(with_request_stats(request_stats_clone, "pokemon"))
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+    let stats = warp::path("stats")
+        .and(warp::path::end())
+        .and(warp::any().map(move || start_time))
+        .and(warp::any().map(move || request_stats.clone()))
+        .and_then(get_stats);
+    let f = pokemon.or(stats);
+
+    let res = warp::test::request().path("/stats").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["total_requests"], 0);
+    assert_eq!(body["endpoints"]["pokemon"], 0);
+
+    warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    warp::test::request().path("/pokemon/NoPokemon").reply(&f).await;
+
+    let res = warp::test::request().path("/stats").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["total_requests"], 2);
+    assert_eq!(body["endpoints"]["pokemon"], 2);
+    assert_eq!(body["endpoints"]["translated"], 0);
+    assert!(body["uptime_seconds"].is_u64());
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_skips_short_description() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "ditto", "It can transform.", "urban", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+    let translation_quota_clone = Arc::clone(&translation_quota);
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota_clone.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+
+    let res = warp::test::request().path("/translated/ditto").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "{\"name\":\"ditto\",\"description\":\"It can transform.\",\"habitat\":\"urban\",\"is_legendary\":false,\"translated\":false}");
+    assert_eq!(translation_quota.lock().unwrap().hour_calls, 0);
+    assert!(mock_servers.funtranslations.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_translates_long_description() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("TRANSLATION_MIN_WORDS", "3");
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "ditto", "It can transform.", "urban", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "It can transformeth.").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+
+    let res = warp::test::request().path("/translated/ditto").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["description"], "It can transformeth.");
+    assert_eq!(body["translated"], true);
+    assert_eq!(body["translation"], "shakespeare");
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_with_translation_cache_disabled() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("DISABLE_TRANSLATION_CACHE", "true");
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+    let translation_cache_clone = Arc::clone(&translation_cache);
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache_clone.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+
+    for _ in 0..2 {
+        let res = warp::test::request().path("/translated/pikachu").reply(&f).await;
+        assert_eq!(res.status(), 200);
+    }
+
+    // Both requests are translated, only the pokemon comes from the cache the second time.
+    assert_eq!(mock_servers.funtranslations.received_requests().await.unwrap().len(), 2);
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), 2);
+    assert!(translation_cache.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_with_malformed_translation() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .and(wiremock::matchers::path("/translate/shakespeare"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_string("<html><body>Service Unavailable</body></html>"))
+        .mount(&mock_servers.funtranslations)
+        .await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/translated/pikachu?strict=true").reply(&f).await;
+
+    assert_eq!(res.status(), 502);
+    assert_eq!(res.body(), "{\"error\":\"Invalid translation response\"}");
+}
+
+#[test]
+fn test_parse_translated_text() {
+    let body = json!({ "success": { "total": 1 }, "contents": { "translated": "Lightning storms,  they cause." } }).to_string();
+    assert_eq!(parse_translated_text(&body).unwrap(), "Lightning storms, they cause.");
+
+    assert!(matches!(parse_translated_text("<html></html>"), Err(TranslationError::Malformed(_))));
+    assert!(matches!(parse_translated_text("{\"contents\": {}}"), Err(TranslationError::Malformed(_))));
+
+    let body = json!({ "error": { "code": 429, "message": "Too Many Requests: Rate limit of 10 requests per hour exceeded." } }).to_string();
+    let error = parse_translated_text(&body).unwrap_err();
+    assert!(matches!(&error, TranslationError::Api { code: 429, .. }));
+    assert_eq!(error.status(), Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_with_error_body() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.", "cave", false).await;
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .and(wiremock::matchers::path("/translate/shakespeare"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(json!({
+            "error": { "code": 429, "message": "Too Many Requests: Rate limit of 10 requests per hour exceeded." }
+        })))
+        .mount(&mock_servers.funtranslations)
+        .await;
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .and(wiremock::matchers::path("/translate/yoda"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(json!({
+            "error": { "code": 500, "message": "Internal Server Error" }
+        })))
+        .mount(&mock_servers.funtranslations)
+        .await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    // The rate limit in the body is a 429, like the rate limit status.
+    let res = warp::test::request().path("/translated/pikachu?strict=true").reply(&f).await;
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.body(), "{\"error\":\"Translation failed\"}");
+
+    // The other errors in the body are upstream failures.
+    let res = warp::test::request().path("/translated/zubat?strict=true").reply(&f).await;
+    assert_eq!(res.status(), 502);
+    assert_eq!(res.body(), "{\"error\":\"Translation failed\"}");
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_names_yoda_for_cave_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places.", "cave", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "yoda", "In perpetually dark places, colonies it forms.").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+
+    let res = warp::test::request().path("/translated/zubat").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["description"], "In perpetually dark places, colonies it forms.");
+    assert_eq!(body["translated"], true);
+    assert_eq!(body["translation"], "yoda");
+}
+
+#[test]
+fn test_pokemon_response_without_habitat() {
+    let pokemon = PokemonResponse {
+        name: "sprigatito".to_string(),
+        description: Some("The sweet scent its body gives off mesmerizes those around it.".to_string()),
+        habitat: None,
+        is_legendary: false,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        serde_json::to_string(&pokemon).unwrap(),
+        "{\"name\":\"sprigatito\",\"description\":\"The sweet scent its body gives off mesmerizes those around it.\",\"habitat\":null,\"is_legendary\":false}"
+    );
+}
+
+#[test]
+fn test_circuit_breaker_opens_after_consecutive_failures() {
+    let mut translation_breaker = CircuitBreaker::new();
+
+    for _ in 0..4 {
+        translation_breaker.record_result(false);
+    }
+    assert_eq!(translation_breaker.state(), CircuitState::Closed);
+
+    // A success resets the consecutive failures.
+    translation_breaker.record_result(true);
+    for _ in 0..4 {
+        translation_breaker.record_result(false);
+    }
+    assert_eq!(translation_breaker.state(), CircuitState::Closed);
+
+    translation_breaker.record_result(false);
+    assert_eq!(translation_breaker.state(), CircuitState::Open);
+    assert!(!translation_breaker.allow_request());
+}
+
+#[test]
+fn test_circuit_breaker_half_open_after_cooldown() {
+    let mut translation_breaker = CircuitBreaker::new();
+    translation_breaker.consecutive_failures = 5;
+    translation_breaker.opened_at = Some(Instant::now() - Duration::from_secs(60));
+    assert_eq!(translation_breaker.state(), CircuitState::HalfOpen);
+    assert!(translation_breaker.allow_request());
+
+    // A failed test request opens the circuit again.
+    translation_breaker.record_result(false);
+    assert_eq!(translation_breaker.state(), CircuitState::Open);
+
+    // A successful test request closes it.
+    translation_breaker.opened_at = Some(Instant::now() - Duration::from_secs(60));
+    translation_breaker.record_result(true);
+    assert_eq!(translation_breaker.state(), CircuitState::Closed);
+    assert_eq!(translation_breaker.consecutive_failures, 0);
+}
+
+#[test]
+fn test_circuit_breaker_half_open_allows_single_request() {
+    let mut translation_breaker = CircuitBreaker::new();
+    translation_breaker.consecutive_failures = 5;
+    translation_breaker.opened_at = Some(Instant::now() - Duration::from_secs(60));
+    let translation_breaker = Arc::new(Mutex::new(translation_breaker));
+
+    // Two concurrent requests, only one is the test request.
+    let requests: Vec<_> = (0..2)
+        .map(|_| {
+            let translation_breaker = Arc::clone(&translation_breaker);
+            std::thread::spawn(move || translation_breaker.lock().unwrap().allow_request())
+        })
+        .collect();
+    let allowed = requests.into_iter().map(|request| request.join().unwrap()).filter(|allowed| *allowed).count();
+    assert_eq!(allowed, 1);
+
+    // Once its result is recorded the circuit takes the requests again.
+    translation_breaker.lock().unwrap().record_result(true);
+    assert!(translation_breaker.lock().unwrap().allow_request());
+
+    // The test request without a result is replaced after another cooldown.
+    let mut translation_breaker = translation_breaker.lock().unwrap();
+    translation_breaker.opened_at = Some(Instant::now() - Duration::from_secs(120));
+    translation_breaker.probe_started_at = Some(Instant::now() - Duration::from_secs(60));
+    assert!(translation_breaker.allow_request());
+    assert!(!translation_breaker.allow_request());
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_half_open_releases_throttled_request() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("TRANSLATION_QUEUE_SECONDS", "0");
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let mut translation_breaker = CircuitBreaker::new();
+    translation_breaker.consecutive_failures = 5;
+    translation_breaker.opened_at = Some(Instant::now() - Duration::from_secs(60));
+    let translation_breaker = Arc::new(Mutex::new(translation_breaker));
+    let mut translation_quota = TranslationQuota::new();
+    translation_quota.tokens = 0.0;
+
+    let pokemon = translate_pokemon(
+        "pikachu".to_string(),
+        false,
+        true,
+        None,
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(translation_quota)),
+        Arc::clone(&translation_breaker), Arc::new(Config::default()),
+    ).await;
+
+    // The test request was throttled before calling the API, the next request can be the test request.
+    assert_eq!(pokemon.unwrap_err().status(), 429);
+    assert!(translation_breaker.lock().unwrap().allow_request());
+}
+
+#[tokio::test]
+async fn test_get_translated_pokemon_with_circuit_breaker() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("CIRCUIT_BREAKER_FAILURES", "2");
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation_rate_limited(&mock_servers.funtranslations, "shakespeare").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+    let translation_breaker_clone = Arc::clone(&translation_breaker);
+
+    let f = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon)
+        .recover(handle_rejection);
+
+    // Closed: the failures reach the API until the threshold.
+    for _ in 0..2 {
+        let res = warp::test::request().path("/translated/pikachu?strict=true").reply(&f).await;
+        assert_eq!(res.status(), 429);
+    }
+    assert_eq!(translation_breaker.lock().unwrap().state(), CircuitState::Open);
+
+    // Open: the request fails without calling the API.
+    let res = warp::test::request().path("/translated/pikachu?strict=true").reply(&f).await;
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.body(), "{\"error\":\"Translation unavailable\"}");
+    assert_eq!(mock_servers.funtranslations.received_requests().await.unwrap().len(), 2);
+
+    // Half-open: the API recovered, the test request succeeds and closes the circuit.
+    mock_servers.funtranslations.reset().await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+    translation_breaker.lock().unwrap().opened_at = Some(Instant::now() - Duration::from_secs(60));
+    assert_eq!(translation_breaker.lock().unwrap().state(), CircuitState::HalfOpen);
+
+    let res = warp::test::request().path("/translated/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(translation_breaker.lock().unwrap().state(), CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_no_cache() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+
+    // A stale pokemon in the cache.
+    pokemon_cache.lock().unwrap().insert("pikachu".to_string(), CachedValue::new(PokemonResponse {
+        name: "pikachu".to_string(),
+        description: Some("Stale description.".to_string()),
+        ..Default::default()
+    }));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["description"], "Stale description.");
+    assert!(mock_servers.pokeapi.received_requests().await.unwrap().is_empty());
+
+    let res = warp::test::request().path("/pokemon/pikachu?no_cache=true").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["description"], "When several of these POKéMON gather, their electricity could build and cause lightning storms.");
+    assert!(!mock_servers.pokeapi.received_requests().await.unwrap().is_empty());
+
+    // The fresh pokemon replaces the stale one in the cache.
+    assert_eq!(pokemon_cache.lock().unwrap()["pikachu"].get().description.as_deref(), Some("When several of these POKéMON gather, their electricity could build and cause lightning storms."));
+}
+
+#[tokio::test]
+async fn test_cached_value_compressed_round_trip() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("COMPRESS_CACHE", "true");
+
+    let pokemon = PokemonResponse {
+        name: "pikachu".to_string(),
+        description: Some("When several of these POKéMON gather, their electricity could build and cause lightning storms.".to_string()),
+        description_available: None,
+        description_language: None,
+        habitat: Some("forest".to_string()),
+        is_legendary: false,
+        types: vec!["electric".to_string()],
+        translation: Some(TranslatorKind::Shakespeare),
+        translated: Some(true),
+        translation_applied: None,
+        cached_at: None,
+        translation_reason: None,
+        sprites: None,
+        stats: None,
+    };
+    let cached_pokemon = CachedValue::new(pokemon.clone());
+    assert!(matches!(cached_pokemon.data, CachedData::Compressed(_)));
+    assert_eq!(cached_pokemon.get(), pokemon);
+
+    let moves = vec![json!({ "name": "thunderbolt", "learn_method": "level-up", "level": 36 })];
+    let cached_moves = CachedValue::new(moves.clone());
+    assert!(matches!(cached_moves.data, CachedData::Compressed(_)));
+    assert_eq!(cached_moves.get(), moves);
+}
+
+#[tokio::test]
+async fn test_cached_value_without_compression() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+
+    let pokemon = PokemonResponse { name: "pikachu".to_string(), ..Default::default() };
+    let cached_pokemon = CachedValue::new(pokemon.clone());
+    assert!(matches!(cached_pokemon.data, CachedData::Plain(_)));
+    assert_eq!(cached_pokemon.get(), pokemon);
+}
+
+#[test]
+fn test_normalize_pokemon_name() {
+    assert_eq!(normalize_pokemon_name("pikachu"), "pikachu");
+    assert_eq!(normalize_pokemon_name("Pikachu"), "pikachu");
+    assert_eq!(normalize_pokemon_name("farfetch%27d"), "farfetchd");
+    assert_eq!(normalize_pokemon_name("farfetch'd"), "farfetchd");
+    assert_eq!(normalize_pokemon_name("nidoran%E2%99%80"), "nidoran-f");
+    assert_eq!(normalize_pokemon_name("nidoran♂"), "nidoran-m");
+    assert_eq!(normalize_pokemon_name("nidoran-f"), "nidoran-f");
+    assert_eq!(normalize_pokemon_name("Mr.%20Mime"), "mr-mime");
+    assert_eq!(normalize_pokemon_name("type: null"), "type-null");
+    assert_eq!(normalize_pokemon_name("Flab%C3%A9b%C3%A9"), "flabebe");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_url_encoded_names() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "farfetchd", "The sprig of green onions it holds is its weapon.", "grassland", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "nidoran-f", "Although small, its venomous barbs render this POKéMON dangerous.", "grassland", false).await;
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/farfetch%27d").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["name"], "farfetchd");
+
+    let res = warp::test::request().path("/pokemon/nidoran%E2%99%80").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["name"], "nidoran-f");
+}
+
+#[test]
+fn test_validate_pokemon_name_with_plausible_names() {
+    assert_eq!(validate_pokemon_name("pikachu"), Ok(()));
+    assert_eq!(validate_pokemon_name("porygon2"), Ok(()));
+    assert_eq!(validate_pokemon_name("porygon-z"), Ok(()));
+    assert_eq!(validate_pokemon_name("NoPokemon"), Ok(()));
+    assert_eq!(validate_pokemon_name("25"), Ok(()));
+}
+
+#[test]
+fn test_validate_pokemon_name_with_impossible_names() {
+    assert_eq!(validate_pokemon_name(&"a".repeat(41)), Err("name_too_long"));
+    assert_eq!(validate_pokemon_name("pika_chu"), Err("invalid_characters"));
+    assert_eq!(validate_pokemon_name("pikachu!"), Err("invalid_characters"));
+    assert_eq!(validate_pokemon_name("-pikachu"), Err("invalid_hyphens"));
+    assert_eq!(validate_pokemon_name("mr--mime"), Err("invalid_hyphens"));
+    assert_eq!(validate_pokemon_name("123abc"), Err("invalid_pattern"));
+}
+
+#[tokio::test]
+async fn test_get_pokemon_invalid_name_and_not_found() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/123abc").reply(&f).await;
+    assert_eq!(res.status(), 422);
+    assert_eq!(res.body(), "{\"code\":\"invalid_pattern\",\"error\":\"Invalid pokemon name\"}");
+
+    let res = warp::test::request().path("/pokemon/notapokemon").reply(&f).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), "{\"error\":\"Pokemon not found\"}");
+}
+
+#[tokio::test]
+async fn test_with_allowed_query() {
+    let f = warp::path("pokemon")
+        .and(warp::path::end())
+        .and(with_allowed_query(&["normalize_text", "no_cache"]))
+        .map(warp::reply)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon?normalize_text=true").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/pokemon").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/pokemon?no_cahce=true&normalize_text=true&lang=it").reply(&f).await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "{\"error\":\"Unexpected query parameters\",\"parameters\":[\"lang\",\"no_cahce\"]}");
+}
+
+#[tokio::test]
+async fn test_routes_with_base_path() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("BASE_PATH", "/api/v1/");
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let pokemon = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+    let config = Config::load("missing_config.toml").unwrap();
+    let f = with_base_path(&config.base_path).and(pokemon);
+
+    let res = warp::test::request().path("/api/v1/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_with_base_path_without_prefix() {
+    let f = with_base_path("").and(warp::path("stats")).and(warp::path::end()).map(warp::reply);
+
+    let res = warp::test::request().path("/stats").reply(&f).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn test_with_client_rate_limit() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("TRANSLATED_REQUESTS_PER_MINUTE", "2");
+
+    let rate_limiter: Arc<Mutex<ClientRateLimiter>> = Arc::new(Mutex::new(ClientRateLimiter::new()));
+
+    let f = warp::path("translated")
+        .and(with_client_rate_limit(rate_limiter))
+        .map(warp::reply)
+        .recover(handle_rejection);
+
+    let client: std::net::SocketAddr = "192.168.1.10:50000".parse().unwrap();
+    for _ in 0..2 {
+        let res = warp::test::request().path("/translated").remote_addr(client).reply(&f).await;
+        assert_eq!(res.status(), 200);
+    }
+
+    let res = warp::test::request().path("/translated").remote_addr(client).reply(&f).await;
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.body(), "{\"error\":\"Too many requests\"}");
+
+    // The other clients have their own limit.
+    let other_client: std::net::SocketAddr = "192.168.1.11:50000".parse().unwrap();
+    let res = warp::test::request().path("/translated").remote_addr(other_client).reply(&f).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn test_with_max_path_length() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+
+    let f = with_max_path_length()
+        .and(warp::path("pokemon"))
+        .and(warp::path::param::<String>())
+        .map(|_pokemon_name: String| warp::reply())
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path(&format!("/pokemon/{}", "a".repeat(20_000))).reply(&f).await;
+    assert_eq!(res.status(), 414);
+    assert_eq!(res.body(), "{\"error\":\"Request path too long\"}");
+
+    mock_servers.set_env("MAX_PATH_LENGTH", "12");
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 414);
+}
+
+#[tokio::test]
+async fn test_with_in_flight_limit() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("MAX_IN_FLIGHT_REQUESTS", "2");
+
+    let in_flight_semaphore = get_in_flight_semaphore();
+    let release = Arc::new(Semaphore::new(0));
+    let release_clone = Arc::clone(&release);
+
+    // The slow requests stay in flight until they are released.
+    let f = with_in_flight_limit(Arc::clone(&in_flight_semaphore))
+        .and(warp::path("slow"))
+        .and_then(move |permit: OwnedSemaphorePermit| {
+            let release = release_clone.clone();
+            async move {
+                let _released = release.acquire().await.unwrap();
+                Ok::<_, warp::Rejection>((permit, warp::reply()))
+            }
+        })
+        .untuple_one()
+        .map(|_permit: OwnedSemaphorePermit, reply| reply)
+        .recover(handle_rejection);
+
+    let mut slow_requests = Vec::new();
+    for _ in 0..2 {
+        let f = f.clone();
+        slow_requests.push(tokio::spawn(async move { warp::test::request().path("/slow").reply(&f).await }));
+    }
+    while in_flight_semaphore.available_permits() > 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let res = warp::test::request().path("/slow").reply(&f).await;
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.headers()["retry-after"], "1");
+    assert_eq!(res.body(), "{\"error\":\"Server overloaded\"}");
+
+    // Once the requests in flight reply, their permits are released.
+    release.add_permits(2);
+    for slow_request in slow_requests {
+        assert_eq!(slow_request.await.unwrap().status(), 200);
+    }
+    assert_eq!(in_flight_semaphore.available_permits(), 2);
+}
+
+#[test]
+fn test_client_rate_limiter_sliding_window() {
+    let mut rate_limiter = ClientRateLimiter::new();
+    let client_ip: IpAddr = "192.168.1.10".parse().unwrap();
+    let old_request = Instant::now() - Duration::from_secs(60);
+    rate_limiter.requests.insert(client_ip, VecDeque::from(vec![old_request; 10]));
+
+    // The requests older than a minute don't count.
+    assert!(rate_limiter.check(client_ip));
+    assert_eq!(rate_limiter.requests[&client_ip].len(), 1);
+}
+
+#[tokio::test]
+async fn test_get_health_live() {
+    let f = warp::path!("health" / "live").and_then(get_health_live);
+
+    let res = warp::test::request().path("/health/live").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["cache-control"], "no-store");
+    assert_eq!(res.body(), "{\"status\":\"ok\"}");
+}
+
+#[tokio::test]
+async fn test_get_health_ready_caches_the_check() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_resource_list(&mock_servers.pokeapi, "pokemon", &["bulbasaur"]).await;
+
+    let readiness: Arc<Mutex<ReadinessCheck>> = Arc::new(Mutex::new(ReadinessCheck::new()));
+    let f = warp::path!("health" / "ready")
+        .and(warp::any().map(move || readiness.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_health_ready);
+
+    for _ in 0..2 {
+        let res = warp::test::request().path("/health/ready").reply(&f).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.body(), "{\"pokeapi\":\"pass\",\"status\":\"ready\"}");
+    }
+
+    // The second probe reuses the result of the first check.
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_get_health_ready_when_pokeapi_fails() {
+    // The mock PokeAPI has nothing mounted, the check gets a 404.
+    let _mock_servers = mock_servers::start_mock_servers().await;
+
+    let f = warp::path!("health" / "ready")
+        .and(warp::any().map(|| Arc::new(Mutex::new(ReadinessCheck::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_health_ready);
+
+    let res = warp::test::request().path("/health/ready").reply(&f).await;
+
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.body(), "{\"pokeapi\":\"fail\",\"status\":\"not_ready\"}");
+}
+
+#[tokio::test]
+async fn test_serve_until_shuts_down() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+    let config = Config { port: 0, ..Config::default() };
+
+    // The server stops on the shutdown signal, without in-flight requests it doesn't wait for the timeout.
+    let served = tokio::time::timeout(Duration::from_secs(5), serve_until(config, async {})).await;
+    assert!(served.is_ok());
+}
+
+#[tokio::test]
+async fn test_config_load_defaults() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+
+    let config = Config::load("missing_config.toml").unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(config.port, 3030);
+}
+
+#[tokio::test]
+async fn test_config_load_from_file() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+    let config_path = std::env::temp_dir().join(format!("pokedex_config_{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&config_path, "port = 8080\nbase_path = \"/api/v1/\"\ncors_allowed_origins = [\"http://localhost:5173\"]\n").unwrap();
+
+    let config = Config::load(config_path.to_str().unwrap());
+    std::fs::remove_file(&config_path).unwrap();
+
+    assert_eq!(config.unwrap(), Config {
+        port: 8080,
+        base_path: "api/v1".to_string(),
+        cors_allowed_origins: vec!["http://localhost:5173".to_string()],
+        ..Config::default()
+    });
+}
+
+#[tokio::test]
+async fn test_config_load_env_overrides_file() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("PORT", "9090");
+    mock_servers.set_env("CORS_ALLOWED_ORIGINS", "https://pokedex.example.com, http://localhost:5173");
+    let config_path = std::env::temp_dir().join(format!("pokedex_config_{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&config_path, "port = 8080\nbase_path = \"api\"\n").unwrap();
+
+    let config = Config::load(config_path.to_str().unwrap());
+    std::fs::remove_file(&config_path).unwrap();
+
+    // The values missing from the env variables keep the file values.
+    assert_eq!(config.unwrap(), Config {
+        port: 9090,
+        base_path: "api".to_string(),
+        cors_allowed_origins: vec!["https://pokedex.example.com".to_string(), "http://localhost:5173".to_string()],
+        ..Config::default()
+    });
+}
+
+#[tokio::test]
+async fn test_config_load_listen_address_and_settings() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("LISTEN_ADDRESS", "127.0.0.1");
+    let config_path = std::env::temp_dir().join(format!("pokedex_config_{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&config_path, "listen_address = \"::\"\npokeapi_base_url = \"http://localhost:8080/api/v2/\"\ntranslation_cache_ttl_seconds = 3600\n").unwrap();
+
+    let config = Config::load(config_path.to_str().unwrap());
+    std::fs::remove_file(&config_path).unwrap();
+
+    let config = config.unwrap();
+    assert_eq!(config.listen_address, IpAddr::from([127, 0, 0, 1]));
+    assert_eq!(config.pokeapi_base_url.as_deref(), Some("http://localhost:8080/api/v2/"));
+    assert_eq!(config.translation_cache_ttl_seconds, Some(3600));
+
+    // The file settings are the defaults of the env variables, the ones already set are kept.
+    assert_eq!(get_translation_cache_ttl(&config), Duration::from_secs(3600));
+    assert_eq!(get_pokeapi_base_url(&config).unwrap(), format!("{}/api/v2/", mock_servers.pokeapi.uri()));
+    assert!(std::env::var("TRANSLATION_CACHE_TTL_SECONDS").is_err());
+
+    mock_servers.set_env("LISTEN_ADDRESS", "localhost");
+    assert!(Config::load("missing_config.toml").is_err());
+}
+
+#[tokio::test]
+async fn test_config_load_rejects_unknown_key() {
+    let _mock_servers = mock_servers::start_mock_servers().await;
+    let config_path = std::env::temp_dir().join(format!("pokedex_config_{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&config_path, "prot = 8080\n").unwrap();
+
+    let config = Config::load(config_path.to_str().unwrap());
+    std::fs::remove_file(&config_path).unwrap();
+
+    assert!(config.is_err());
+}
+
+#[tokio::test]
+async fn test_post_translated_batch_with_rate_limited_name() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.", "cave", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+    mock_servers::mock_translation_rate_limited(&mock_servers.funtranslations, "yoda").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+    let translation_cache_clone = Arc::clone(&translation_cache);
+
+    let f = warp::post()
+        .and(warp::path("translated"))
+        .and(warp::path("batch"))
+        .and(warp::path::end())
+        .and(json_body::<TranslatedBatchRequest>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache_clone.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(post_translated_batch);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/translated/batch")
+        .json(&json!({ "names": ["pikachu", "zubat", "NoPokemon", "Zubat"] }))
+        .reply(&f)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["results"]["pikachu"]["description"], "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.");
+    assert_eq!(body["results"]["pikachu"]["translation"], "shakespeare");
+    assert_eq!(body["results"]["zubat"], json!({ "error": "Translation failed", "status": 429 }));
+    // The duplicate name shares the result, the rate limited translation is not requested again.
+    assert_eq!(body["results"]["Zubat"], body["results"]["zubat"]);
+    let yoda_requests = mock_servers.funtranslations.received_requests().await.unwrap().iter().filter(|request| request.url.path() == "/translate/yoda").count();
+    assert_eq!(yoda_requests, 1);
+    assert_eq!(body["results"]["NoPokemon"], json!({ "error": "Pokemon not found", "status": 404 }));
+
+    // The successful translation is cached for the translated endpoint.
+    assert!(translation_cache.lock().unwrap().contains_key("pikachu"));
+}
+
+#[tokio::test]
+async fn test_post_pokemon_batch() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.", "cave", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+
+    let f = warp::post()
+        .and(warp::path("pokemon"))
+        .and(warp::path("batch"))
+        .and(warp::path::end())
+        .and(json_body::<PokemonBatchRequest>())
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(post_pokemon_batch);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/pokemon/batch")
+        .json(&json!({ "names": ["pikachu", "Zubat", "nopokemon", "pika$chu"] }))
+        .reply(&f)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["results"]["pikachu"]["habitat"], "forest");
+    assert_eq!(body["results"]["Zubat"]["name"], "zubat");
+    assert_eq!(body["results"]["nopokemon"], json!({ "error": "Pokemon not found", "status": 404 }));
+    assert_eq!(body["results"]["pika$chu"]["status"], 422);
+
+    // The fetched pokemon are cached for the pokemon endpoint.
+    assert!(pokemon_cache.lock().unwrap().contains_key("pikachu"));
+    assert!(pokemon_cache.lock().unwrap().contains_key("zubat"));
+}
+
+#[tokio::test]
+async fn test_post_pokemon_batch_too_many_names() {
+    let f = warp::post()
+        .and(warp::path("pokemon"))
+        .and(warp::path("batch"))
+        .and(warp::path::end())
+        .and(json_body::<PokemonBatchRequest>())
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(post_pokemon_batch);
+
+    let names: Vec<String> = (0..=MAX_POKEMON_BATCH_SIZE).map(|index| format!("pokemon{}", index)).collect();
+    let res = warp::test::request().method("POST").path("/pokemon/batch").json(&json!({ "names": names })).reply(&f).await;
+    assert_eq!(res.status(), 400);
+
+    let res = warp::test::request().method("POST").path("/pokemon/batch").json(&json!({ "names": [] })).reply(&f).await;
+    assert_eq!(res.status(), 400);
+}
+
+/// Build the graphql endpoint filter for the tests, with empty caches.
+fn graphql_filter() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let schema = build_schema(GraphqlState {
+        cache_pokemon: Arc::new(Mutex::new(HashMap::new())),
+        cache_aliases: Arc::new(Mutex::new(HashMap::new())),
+        cache_not_found: Arc::new(Mutex::new(HashMap::new())),
+        cache_translation: Arc::new(Mutex::new(HashMap::new())),
+        translation_quota: Arc::new(Mutex::new(TranslationQuota::new())),
+        translation_breaker: Arc::new(Mutex::new(CircuitBreaker::new())),
+        config: Arc::new(Config::default()),
+    });
+
+    warp::post()
+        .and(warp::path("graphql"))
+        .and(warp::path::end())
+        .and(json_body::<async_graphql::Request>())
+        .and(warp::any().map(move || schema.clone()))
+        .and_then(post_graphql)
+}
+
+#[tokio::test]
+async fn test_post_graphql_selects_the_fields() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let query = "{ pokemon(name: \"pikachu\") { name habitat isLegendary } translatedPokemon(name: \"pikachu\") { description translation } }";
+    let res = warp::test::request()
+        .method("POST")
+        .path("/graphql")
+        .json(&json!({ "query": query }))
+        .reply(&graphql_filter())
+        .await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, json!({
+        "data": {
+            "pokemon": { "name": "pikachu", "habitat": "forest", "isLegendary": false },
+            "translatedPokemon": {
+                "description": "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.",
+                "translation": "shakespeare"
+            }
+        }
+    }));
+}
+
+#[tokio::test]
+async fn test_post_graphql_errors_have_the_status() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let query = "{ pokemon(name: \"pikachu\") { name } missing: pokemon(name: \"nopokemon\") { name } invalid: pokemon(name: \"pika$chu\") { name } }";
+    let res = warp::test::request()
+        .method("POST")
+        .path("/graphql")
+        .json(&json!({ "query": query }))
+        .reply(&graphql_filter())
+        .await;
+
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    // The failed queries are null, the others still have their data.
+    assert_eq!(body["data"]["pokemon"], json!({ "name": "pikachu" }));
+    assert!(body["data"]["missing"].is_null());
+    assert!(body["data"]["invalid"].is_null());
+
+    let errors = body["errors"].as_array().unwrap();
+    let missing = errors.iter().find(|error| error["path"] == json!(["missing"])).unwrap();
+    assert_eq!(missing["message"], "Pokemon not found");
+    assert_eq!(missing["extensions"]["status"], 404);
+    let invalid = errors.iter().find(|error| error["path"] == json!(["invalid"])).unwrap();
+    assert_eq!(invalid["message"], "Invalid pokemon name");
+    assert_eq!(invalid["extensions"]["status"], 422);
+}
+
+/// Build the translate endpoint filter for the tests.
+#[cfg(test)]
+fn translate_filter(translation_cache: Cache<String, Translation>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    warp::post()
+        .and(warp::path("translate"))
+        .and(warp::path::end())
+        .and(json_body::<TranslateRequest>())
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(post_translate)
+}
+
+#[tokio::test]
+async fn test_post_translate_both_styles() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "yoda", "Strong with the force, you are.").await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "Thou art strong with the force.").await;
+
+    let f = translate_filter(Arc::new(Mutex::new(HashMap::new())));
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/translate")
+        .json(&json!({ "text": "You are strong with the force.", "style": "yoda" }))
+        .reply(&f)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "{\"style\":\"yoda\",\"text\":\"You are strong with the force.\",\"translated\":\"Strong with the force, you are.\"}");
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/translate")
+        .json(&json!({ "text": "You are strong with the force.", "style": "shakespeare" }))
+        .reply(&f)
+        .await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["translated"], "Thou art strong with the force.");
+    assert_eq!(body["style"], "shakespeare");
+
+    // The text is sent as JSON, so the quotes in it don't break the request.
+    let requests = mock_servers.funtranslations.received_requests().await.unwrap();
+    let request_body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(request_body["text"], "You are strong with the force.");
+}
+
+#[tokio::test]
+async fn test_post_translate_from_cache() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "yoda", "Strong with the force, you are.").await;
+
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let f = translate_filter(translation_cache.clone());
+
+    // The style is case-insensitive, both requests use the yoda translation.
+    for style in ["yoda", "Yoda"] {
+        let res = warp::test::request()
+            .method("POST")
+            .path("/translate")
+            .json(&json!({ "text": "You are strong with the force.", "style": style }))
+            .reply(&f)
+            .await;
+        assert_eq!(res.status(), 200);
+        let body: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["translated"], "Strong with the force, you are.");
+    }
+
+    assert_eq!(mock_servers.funtranslations.received_requests().await.unwrap().len(), 1);
+    assert!(translation_cache.lock().unwrap().contains_key("text:yoda:You are strong with the force."));
+}
+
+#[tokio::test]
+async fn test_post_translate_unknown_style() {
+    let f = translate_filter(Arc::new(Mutex::new(HashMap::new())));
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/translate")
+        .json(&json!({ "text": "You are strong with the force.", "style": "elvish" }))
+        .reply(&f)
+        .await;
+
+    assert_eq!(res.status(), 400);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["error"], "Unknown translation style");
+    assert_eq!(body["styles"][0], "yoda");
+    assert_eq!(body["styles"][1], "shakespeare");
+    assert!(body["styles"].as_array().unwrap().contains(&json!("pirate")));
+}
+
+#[tokio::test]
+async fn test_post_translated_batch_too_many_names() {
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+
+    let f = warp::post()
+        .and(json_body::<TranslatedBatchRequest>())
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(post_translated_batch);
+
+    let res = warp::test::request()
+        .method("POST")
+        .json(&json!({ "names": vec!["pikachu"; 11] }))
+        .reply(&f)
+        .await;
+
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "{\"error\":\"The batch must have between 1 and 10 names\"}");
+}
+
+#[tokio::test]
+async fn test_get_schema() {
+    let f = warp::path("schema").and(warp::path::end()).and_then(get_schema);
+
+    let res = warp::test::request().path("/schema").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let schema: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(schema["title"], "PokemonResponse");
+    assert_eq!(schema["properties"]["name"]["type"], "string");
+    assert_eq!(schema["properties"]["description"]["type"], json!(["string", "null"]));
+    assert_eq!(schema["properties"]["habitat"]["type"], json!(["string", "null"]));
+    assert_eq!(schema["properties"]["is_legendary"]["type"], "boolean");
+    assert_eq!(schema["required"], json!(["is_legendary", "name"]));
+}
+
+#[tokio::test]
+async fn test_get_openapi() {
+    let document = Arc::new(build_openapi_document("api/v1"));
+    let f = warp::path("openapi.json").and(warp::path::end()).and(warp::any().map(move || document.clone())).and_then(get_openapi);
+
+    let res = warp::test::request().path("/openapi.json").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    let document: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(document["openapi"], "3.0.3");
+    assert_eq!(document["servers"][0]["url"], "/api/v1");
+    assert_eq!(document["paths"]["/pokemon/{pokemon_name}"]["get"]["responses"]["200"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/PokemonResponse");
+    assert_eq!(document["paths"]["/translated/{pokemon_name}"]["get"]["parameters"][0]["name"], "pokemon_name");
+    assert_eq!(document["paths"]["/pokemon/batch"]["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/PokemonBatchRequest");
+    assert_eq!(document["components"]["schemas"]["PokemonResponse"]["properties"]["name"]["type"], "string");
+    assert_eq!(document["components"]["schemas"]["ApiError"]["type"], "object");
+}
+
+#[tokio::test]
+async fn test_get_docs() {
+    let f = warp::path("docs").and(warp::path::end()).and_then(get_docs);
+
+    let res = warp::test::request().path("/docs").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert!(res.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+    let page = String::from_utf8(res.body().to_vec()).unwrap();
+    assert!(page.contains("swagger-ui"));
+    assert!(page.contains("url: \"openapi.json\""));
+}
+
+#[tokio::test]
+async fn test_cache_control_of_pokemon_and_translated() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    mock_servers::mock_translation(&mock_servers.funtranslations, "shakespeare", "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.").await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    let translation_quota: Arc<Mutex<TranslationQuota>> = Arc::new(Mutex::new(TranslationQuota::new()));
+    let translation_breaker: Arc<Mutex<CircuitBreaker>> = Arc::new(Mutex::new(CircuitBreaker::new()));
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+    let alias_cache_clone = Arc::clone(&alias_cache);
+    let not_found_cache_clone = Arc::clone(&not_found_cache);
+
+    let pokemon = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+    let translated = warp::path("translated")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<TranslatedQuery>())
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(move || alias_cache_clone.clone()))
+        .and(warp::any().map(move || not_found_cache_clone.clone()))
+        .and(warp::any().map(move || translation_cache.clone()))
+        .and(warp::any().map(move || translation_quota.clone()))
+        .and(warp::any().map(move || translation_breaker.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_translated_pokemon);
+    // The rejections are replied once both routes are tried.
+    let f = pokemon.or(translated).recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.headers()["cache-control"], "public, max-age=86400");
+
+    let res = warp::test::request().path("/translated/pikachu").reply(&f).await;
+    assert_eq!(res.headers()["cache-control"], "public, max-age=3600");
+
+    let res = warp::test::request().path("/pokemon/NoPokemon").reply(&f).await;
+    assert_eq!(res.headers()["cache-control"], "no-store");
+}
+
+#[test]
+fn test_parse_warm_up_list() {
+    assert_eq!(parse_warm_up_list("pikachu, Zubat\nmewtwo\n\n"), vec!["pikachu", "zubat", "mewtwo"]);
+    assert!(parse_warm_up_list("").is_empty());
+}
+
+#[tokio::test]
+async fn test_warm_up_pokemon_cache() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "It keeps its tail raised.", "forest", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places.", "cave", false).await;
+
+    let warm_up_file = std::env::temp_dir().join(format!("warmup-{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&warm_up_file, "zubat\nbad name!\n").unwrap();
+    mock_servers.set_env("WARMUP_LIST", "pikachu,missingno");
+    mock_servers.set_env("WARMUP_LIST_FILE", warm_up_file.to_str().unwrap());
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    warm_up_pokemon_cache(get_warm_up_list(), get_preload_semaphore(), pokemon_cache.clone(), Arc::new(Mutex::new(HashMap::new())), Arc::new(Mutex::new(HashMap::new())), Arc::new(Config::default())).await;
+    std::fs::remove_file(&warm_up_file).unwrap();
+
+    let mut cached_names: Vec<String> = pokemon_cache.lock().unwrap().keys().cloned().collect();
+    cached_names.sort();
+    assert_eq!(cached_names, vec!["pikachu", "zubat"]);
+}
+
+#[tokio::test]
+async fn test_species_names_load_on_demand_after_failed_warm_up() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let species_names: Arc<OnceCell<Vec<String>>> = Arc::new(OnceCell::new());
+
+    // The PokeAPI has no species list yet, like when it is unreachable at startup.
+    warm_up_species_names(Arc::clone(&species_names), Arc::new(Config::default())).await;
+    assert!(species_names.get().is_none());
+
+    let uri = mock_servers.pokeapi.uri();
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .and(wiremock::matchers::path_regex("^/api/v2/pokemon-species/?$"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(json!({
+            "count": 2,
+            "next": null,
+            "previous": null,
+            "results": [
+                mock_servers::named_resource(&uri, "pokemon-species", "bulbasaur"),
+                mock_servers::named_resource(&uri, "pokemon-species", "pikachu")
+            ]
+        })))
+        .mount(&mock_servers.pokeapi)
+        .await;
+
+    let names = get_species_names(&species_names, &Config::default()).await.unwrap();
+    assert_eq!(names, &vec!["bulbasaur".to_string(), "pikachu".to_string()]);
+}
+
+#[tokio::test]
+async fn test_retry_with_backoff() {
+    let calls = AtomicU64::new(0);
+
+    // Fails twice, then succeeds on the third attempt.
+    let result = retry_with_backoff(3, Duration::from_millis(1), || async {
+        match calls.fetch_add(1, Ordering::SeqCst) {
+            0 | 1 => Err("PokeAPI unreachable"),
+            _ => Ok("pikachu"),
+        }
+    }).await;
+    assert_eq!(result, Ok("pikachu"));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // Gives up after the last attempt.
+    calls.store(0, Ordering::SeqCst);
+    let result = retry_with_backoff(2, Duration::from_millis(1), || async {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Err::<&str, _>("PokeAPI unreachable")
+    }).await;
+    assert_eq!(result, Err("PokeAPI unreachable"));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_fetch_species_names_retries_and_uses_disk_cache() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let cache_dir = std::env::temp_dir().join(format!("pokedex_species_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&cache_dir).unwrap();
+    mock_servers.set_env("SPECIES_LIST_CACHE_DIR", cache_dir.to_str().unwrap());
+    mock_servers.set_env("SPECIES_LIST_CACHE_VERSION", "test");
+    mock_servers.set_env("SPECIES_LIST_RETRY_DELAY_MS", "1");
+
+    // The first request fails, like a transient PokeAPI failure, the retry gets the list.
+    let uri = mock_servers.pokeapi.uri();
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .and(wiremock::matchers::path_regex("^/api/v2/pokemon-species/?$"))
+        .respond_with(wiremock::ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock_servers.pokeapi)
+        .await;
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .and(wiremock::matchers::path_regex("^/api/v2/pokemon-species/?$"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(json!({
+            "count": 2,
+            "next": null,
+            "previous": null,
+            "results": [
+                mock_servers::named_resource(&uri, "pokemon-species", "bulbasaur"),
+                mock_servers::named_resource(&uri, "pokemon-species", "pikachu")
+            ]
+        })))
+        .mount(&mock_servers.pokeapi)
+        .await;
+
+    let names = fetch_species_names_with_disk_cache(&Config::default()).await.unwrap();
+    assert_eq!(names, vec!["bulbasaur".to_string(), "pikachu".to_string()]);
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), 2);
+    assert!(cache_dir.join("species-test.json").exists());
+
+    // After a restart the list comes from the disk cache, without calling PokeAPI.
+    mock_servers.pokeapi.reset().await;
+    let names = fetch_species_names_with_disk_cache(&Config::default()).await.unwrap();
+    assert_eq!(names, vec!["bulbasaur".to_string(), "pikachu".to_string()]);
+    assert!(mock_servers.pokeapi.received_requests().await.unwrap().is_empty());
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[test]
+fn test_choose_random_species_picks_from_the_list() {
+    let species_names = vec!["bulbasaur".to_string(), "pikachu".to_string(), "zubat".to_string()];
+
+    for _ in 0..100 {
+        let name = choose_random_species(&species_names, None).unwrap();
+        assert!(species_names.contains(name));
+    }
+
+    assert_eq!(choose_random_species(&[], None), None);
+    assert_eq!(choose_random_species(&[], Some(123)), None);
+}
+
+#[test]
+fn test_choose_random_species_with_seed() {
+    let species_names: Vec<String> = (1..=100).map(|number| format!("pokemon-{}", number)).collect();
+
+    // The same seed always picks the same name.
+    let name = choose_random_species(&species_names, Some(123)).unwrap();
+    for _ in 0..10 {
+        assert_eq!(choose_random_species(&species_names, Some(123)), Some(name));
+    }
+
+    // The different seeds usually pick different names, 10 seeds picking the same name out of 100 is practically impossible.
+    let names: std::collections::HashSet<&String> = (0..10).map(|seed| choose_random_species(&species_names, Some(seed)).unwrap()).collect();
+    assert!(names.len() > 1);
+}
+
+#[tokio::test]
+async fn test_get_random_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "It keeps its tail raised to monitor its surroundings.", "forest", false).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places.", "cave", false).await;
+
+    let species_names = vec!["pikachu".to_string(), "zubat".to_string()];
+    let species_names_clone = species_names.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path("random"))
+        .and(warp::path::end())
+        .and(warp::query::<RandomQuery>())
+        .and(warp::any().map(move || Arc::new(OnceCell::new_with(Some(species_names_clone.clone())))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_random_pokemon);
+
+    for _ in 0..10 {
+        let res = warp::test::request().path("/pokemon/random").reply(&f).await;
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["cache-control"], "no-store");
+        let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+        assert!(species_names.contains(&pokemon.name));
+    }
+
+    // The same seed returns the same pokemon.
+    let res = warp::test::request().path("/pokemon/random?seed=123").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let seeded_pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+    for _ in 0..5 {
+        let res = warp::test::request().path("/pokemon/random?seed=123").reply(&f).await;
+        let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(pokemon.name, seeded_pokemon.name);
+    }
+
+    // The different seeds usually return different pokemon, with 2 species some of 20 seeds pick the other one.
+    let mut names = std::collections::HashSet::new();
+    for seed in 0..20 {
+        let res = warp::test::request().path(&format!("/pokemon/random?seed={}", seed)).reply(&f).await;
+        let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+        names.insert(pokemon.name);
+    }
+    assert_eq!(names.len(), 2);
+
+    let res = warp::test::request().path("/pokemon/random?seed=abc").reply(&f).await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "{\"error\":\"Invalid query parameters\",\"errors\":[{\"message\":\"Expected an unsigned number, got abc\",\"param\":\"seed\"}]}");
+}
+
+#[tokio::test]
+async fn test_get_random_pokemon_with_empty_species_list() {
+    let f = warp::path("pokemon")
+        .and(warp::path("random"))
+        .and(warp::path::end())
+        .and(warp::query::<RandomQuery>())
+        .and(warp::any().map(|| Arc::new(OnceCell::new_with(Some(Vec::new())))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_random_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/random").reply(&f).await;
+
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.body(), "{\"error\":\"Species list unavailable\"}");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_accept_plain_text() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "It keeps its tail raised to monitor its surroundings.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/pikachu").header("accept", "text/plain").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+    assert_eq!(res.body(), "It keeps its tail raised to monitor its surroundings.");
+
+    let res = warp::test::request().path("/pokemon/pikachu").header("accept", "application/json").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+    assert_eq!(res.body(), "{\"name\":\"pikachu\",\"description\":\"It keeps its tail raised to monitor its surroundings.\",\"habitat\":\"forest\",\"is_legendary\":false}");
+}
+
+#[test]
+fn test_accepts_plain_text() {
+    assert!(!accepts_plain_text(None));
+    assert!(accepts_plain_text(Some("text/plain")));
+    assert!(accepts_plain_text(Some("text/plain;q=0.9, application/json;q=0.8")));
+    assert!(!accepts_plain_text(Some("application/json, text/plain")));
+    assert!(!accepts_plain_text(Some("*/*")));
+    assert!(!accepts_plain_text(Some("text/html")));
+}
+
+#[tokio::test]
+async fn test_pretty_json_reply() {
+    let f = warp::path("schema")
+        .and(warp::path::end())
+        .and(with_request_context(Arc::new(Config::default())))
+        .and_then(|context| handle_with_request_context(context, get_schema()));
+
+    let res = warp::test::request().path("/schema?pretty=true").reply(&f).await;
+    let body = std::str::from_utf8(res.body()).unwrap();
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+    assert!(body.contains("\n  \""));
+
+    let res = warp::test::request().path("/schema").reply(&f).await;
+    let body = std::str::from_utf8(res.body()).unwrap();
+    assert!(!body.contains('\n'));
+    assert!(!body.contains("  "));
+}
+
+#[tokio::test]
+async fn test_pretty_json_error_reply() {
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(with_allowed_query(&["normalize_text", "no_cache"]))
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_request_context(Arc::new(Config::default())))
+        .and_then(|pokemon_name, query, accept, cache, cache_aliases, cache_not_found, cache_descriptions, cache_sub_resources, context| {
+            handle_with_request_context(context, get_pokemon(pokemon_name, query, accept, cache, cache_aliases, cache_not_found, cache_descriptions, cache_sub_resources, Arc::new(Config::default())))
+        });
+
+    let res = warp::test::request().path("/pokemon/pika$chu?pretty=true").reply(&f).await;
+
+    assert_eq!(res.status(), 422);
+    assert_eq!(res.body(), "{\n  \"code\": \"invalid_characters\",\n  \"error\": \"Invalid pokemon name\"\n}");
+}
+
+#[tokio::test]
+async fn test_pokemon_without_english_description() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let uri = mock_servers.pokeapi.uri();
+    let mut species = mock_servers::pokemon_species_json(&uri, "sprigatito", "", "grassland", false);
+    species["flavor_text_entries"] = json!([
+        {
+            "flavor_text": "Il suo pelo soffice è composto da fibre simili a quelle delle piante.",
+            "language": mock_servers::named_resource(&uri, "language", "it"),
+            "version": mock_servers::named_resource(&uri, "version", "scarlet")
+        }
+    ]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "sprigatito", mock_servers::pokemon_json(&uri, "sprigatito")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "sprigatito", species).await;
+
+    let pokemon = fetch_pokemon_from_api("sprigatito".to_string(), &Config::default()).await.unwrap();
+    assert_eq!(pokemon.description, None);
+    assert_eq!(pokemon.description_available, Some(false));
+    assert_eq!(
+        serde_json::to_string(&pokemon).unwrap(),
+        "{\"name\":\"sprigatito\",\"description\":null,\"description_available\":false,\"habitat\":\"grassland\",\"is_legendary\":false}"
+    );
+
+    // The translated endpoint doesn't call Fun Translations, no translation is mounted on the mock server.
+    let pokemon = translate_pokemon(
+        "sprigatito".to_string(),
+        false,
+        true,
+        None,
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(TranslationQuota::new())),
+        Arc::new(Mutex::new(CircuitBreaker::new())), Arc::new(Config::default()),
+    ).await.unwrap();
+    assert_eq!(pokemon.description, None);
+    assert_eq!(pokemon.translated, Some(false));
+    assert_eq!(pokemon.translation, None);
+    assert!(mock_servers.funtranslations.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_pokemon_without_english_description_default_description() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("DEFAULT_DESCRIPTION_TEMPLATE", "No description available for {name}.");
+    let uri = mock_servers.pokeapi.uri();
+    let mut species = mock_servers::pokemon_species_json(&uri, "sprigatito", "", "grassland", false);
+    species["flavor_text_entries"] = json!([]);
+    species["habitat"] = Value::Null;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "sprigatito", mock_servers::pokemon_json(&uri, "sprigatito")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "sprigatito", species).await;
+
+    let pokemon = fetch_pokemon_from_api("sprigatito".to_string(), &Config::default()).await.unwrap();
+    assert_eq!(
+        serde_json::to_string(&pokemon).unwrap(),
+        "{\"name\":\"sprigatito\",\"description\":\"No description available for sprigatito.\",\"description_available\":false,\"habitat\":null,\"is_legendary\":false}"
+    );
+
+    // The placeholder is long enough to be translated, but it is not, no translation is mounted on the mock server.
+    let pokemon = translate_pokemon(
+        "sprigatito".to_string(),
+        false,
+        true,
+        None,
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(HashMap::new())),
+        Arc::new(Mutex::new(TranslationQuota::new())),
+        Arc::new(Mutex::new(CircuitBreaker::new())), Arc::new(Config::default()),
+    ).await.unwrap();
+    assert_eq!(pokemon.description.as_deref(), Some("No description available for sprigatito."));
+    assert_eq!(pokemon.translated, Some(false));
+    assert!(mock_servers.funtranslations.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_pokemon_uppercase_name() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let pokemon_cache_clone = Arc::clone(&pokemon_cache);
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache_clone.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    for path in ["/pokemon/PIKACHU", "/pokemon/Pikachu"] {
+        let res = warp::test::request().path(path).reply(&f).await;
+        assert_eq!(res.status(), 200);
+        let body: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["name"], "pikachu");
+    }
+
+    // Both spellings share the cached pokemon.
+    assert_eq!(pokemon_cache.lock().unwrap().keys().collect::<Vec<_>>(), vec!["pikachu"]);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_include_meta() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    // Through the real routes, so the query parameter must be allowed by the pokemon route.
+    let f = build_routes(&Config::default());
+
+    // Without the query parameter there is no cached_at.
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert!(body.get("cached_at").is_none());
+
+    let res = warp::test::request().path("/pokemon/pikachu?include_meta=true").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    let cached_at = humantime::parse_rfc3339(body["cached_at"].as_str().unwrap()).unwrap();
+    let age = SystemTime::now().duration_since(cached_at).unwrap();
+    assert!(age < Duration::from_secs(60), "cached {:?} ago", age);
+}
+
+#[tokio::test]
+async fn test_head_pokemon() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = get_or_head()
+        .and(warp::path("pokemon"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and_then(|method, pokemon_name, query, accept, cache, cache_aliases, cache_not_found, cache_descriptions, cache_sub_resources| async move {
+            let reply = match get_pokemon(pokemon_name, query, accept, cache, cache_aliases, cache_not_found, cache_descriptions, cache_sub_resources, Arc::new(Config::default())).await {
+                Ok(reply) => reply,
+                Err(rejection) => handle_rejection(rejection).await?,
+            };
+            Ok::<_, warp::Rejection>(without_body_for_head(&method, reply))
+        });
+
+    let res = warp::test::request().method("HEAD").path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+    assert!(res.body().is_empty());
+
+    let res = warp::test::request().method("HEAD").path("/pokemon/missingno").reply(&f).await;
+    assert_eq!(res.status(), 404);
+    assert!(res.body().is_empty());
+
+    // The GET requests still get the body, from the cache filled by the HEAD request.
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    assert!(!res.body().is_empty());
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().iter().filter(|request| request.url.path().starts_with("/api/v2/pokemon/pikachu")).count(), 1);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_trailing_slash() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    // warp skips the slash after the last matched segment, so `path::end()` already matches a single trailing slash.
+    let f = with_base_path("api/v1")
+        .and(warp::path("pokemon"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(with_allowed_query(&["normalize_text", "no_cache"]))
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/api/v1/pokemon/pikachu").reply(&f).await;
+    let res_with_slash = warp::test::request().path("/api/v1/pokemon/pikachu/").reply(&f).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res_with_slash.status(), 200);
+    assert_eq!(res_with_slash.body(), res.body());
+
+    let res_with_slash = warp::test::request().path("/api/v1/pokemon/pikachu/?normalize_text=true").reply(&f).await;
+    assert_eq!(res_with_slash.status(), 200);
+}
+
+#[tokio::test]
+async fn test_get_cache_freshness() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    // The cached values without jitter, to check the TTLs exactly.
+    let cached_for = |age: Duration| {
+        let mut cached_value = CachedValue::new(());
+        cached_value.cached_at = Instant::now() - age;
+        cached_value.ttl_jitter = 1.0;
+        cached_value
+    };
+    assert_eq!(get_cache_freshness(&cached_for(Duration::from_secs(60)), &Config::default()), CacheFreshness::Fresh);
+
+    mock_servers.set_env("POKEMON_CACHE_SOFT_TTL_SECONDS", "10");
+    mock_servers.set_env("POKEMON_CACHE_HARD_TTL_SECONDS", "20");
+    assert_eq!(get_cache_freshness(&cached_for(Duration::from_secs(9)), &Config::default()), CacheFreshness::Fresh);
+    assert_eq!(get_cache_freshness(&cached_for(Duration::from_secs(10)), &Config::default()), CacheFreshness::Stale);
+    assert_eq!(get_cache_freshness(&cached_for(Duration::from_secs(20)), &Config::default()), CacheFreshness::Expired);
+}
+
+#[tokio::test]
+async fn test_evict_expired_values() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("TRANSLATION_CACHE_TTL_SECONDS", "60");
+    let cached_for = |age: Duration| {
+        let mut cached_value = CachedValue::new(Translation { text: "Lightning storms, several of these pokémon cause.".to_string(), translator: TranslatorKind::Yoda });
+        cached_value.cached_at = Instant::now() - age;
+        cached_value.ttl_jitter = 1.0;
+        cached_value
+    };
+    let translation_cache: Cache<String, Translation> = Arc::new(Mutex::new(HashMap::new()));
+    translation_cache.lock().unwrap().insert("pikachu".to_string(), cached_for(Duration::from_secs(30)));
+    translation_cache.lock().unwrap().insert("zubat".to_string(), cached_for(Duration::from_secs(90)));
+
+    // The expired translation is not served even before it is evicted.
+    assert!(get_translation_from_cache("pikachu".to_string(), translation_cache.clone(), &Config::default()).is_some());
+    assert!(get_translation_from_cache("zubat".to_string(), translation_cache.clone(), &Config::default()).is_none());
+
+    assert_eq!(evict_expired_values(&translation_cache, get_translation_cache_ttl(&Config::default())), 1);
+    let cached_names: Vec<String> = translation_cache.lock().unwrap().keys().cloned().collect();
+    assert_eq!(cached_names, vec!["pikachu".to_string()]);
+}
+
+#[tokio::test]
+async fn test_evict_expired_entries_in_background() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("CACHE_EVICTION_INTERVAL_SECONDS", "1");
+    mock_servers.set_env("POKEMON_CACHE_HARD_TTL_SECONDS", "60");
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let mut expired_pokemon = CachedValue::new(PokemonResponse { name: "deoxys-normal".to_string(), ..Default::default() });
+    expired_pokemon.cached_at = Instant::now() - Duration::from_secs(120);
+    pokemon_cache.lock().unwrap().insert("deoxys-normal".to_string(), expired_pokemon);
+    pokemon_cache.lock().unwrap().insert("pikachu".to_string(), CachedValue::new(PokemonResponse { name: "pikachu".to_string(), ..Default::default() }));
+    alias_cache.lock().unwrap().insert("deoxys".to_string(), "deoxys-normal".to_string());
+    not_found_cache.lock().unwrap().insert("missingno".to_string(), Instant::now() - Duration::from_secs(120));
+
+    let eviction = tokio::spawn(evict_expired_entries(pokemon_cache.clone(), alias_cache.clone(), not_found_cache.clone(), Arc::new(Mutex::new(HashMap::new())), Arc::new(Mutex::new(HashMap::new())), Arc::new(Mutex::new(HashMap::new())), Arc::new(Config::default())));
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    eviction.abort();
+
+    let cached_names: Vec<String> = pokemon_cache.lock().unwrap().keys().cloned().collect();
+    assert_eq!(cached_names, vec!["pikachu".to_string()]);
+    // The alias of the evicted pokemon and the expired not found name are dropped with it.
+    assert!(alias_cache.lock().unwrap().is_empty());
+    assert!(not_found_cache.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_memory_backend_shares_nothing() {
+    // Without CACHE_BACKEND the values are only in the in-memory caches of the instance.
+    set_in_cache_backend("pokemon:pikachu", &PokemonResponse { name: "pikachu".to_string(), ..Default::default() }, Duration::from_secs(60)).await;
+    assert_eq!(get_from_cache_backend::<PokemonResponse>("pokemon:pikachu").await, None);
+    assert_eq!(delete_prefix_from_cache_backend("pokemon:").await, 0);
+}
+
+#[test]
+fn test_cache_fetched_value_with_concurrent_inserts() {
+    let cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let fetch_started = Instant::now();
+    let barrier = Arc::new(std::sync::Barrier::new(8));
+
+    // The requests missed the cache together and cache their own copy at the same time.
+    let threads: Vec<_> = (0..8)
+        .map(|request| {
+            let cache = cache.clone();
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                let pokemon = PokemonResponse { name: "pikachu".to_string(), habitat: Some(format!("habitat-{}", request)), ..Default::default() };
+                barrier.wait();
+                cache_fetched_value(&cache, "pikachu".to_string(), pokemon, fetch_started)
+            })
+        })
+        .collect();
+    let replies: Vec<PokemonResponse> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+
+    // Only the first write wins, and all the requests reply with it.
+    let cached_pokemon = cache.lock().unwrap()["pikachu"].get();
+    assert!(replies.iter().all(|pokemon| *pokemon == cached_pokemon));
+
+    // The values cached before the fetch started are replaced.
+    std::thread::sleep(Duration::from_millis(1));
+    let fetch_started = Instant::now();
+    let pokemon = PokemonResponse { name: "pikachu".to_string(), habitat: Some("refreshed".to_string()), ..Default::default() };
+    assert_eq!(cache_fetched_value(&cache, "pikachu".to_string(), pokemon.clone(), fetch_started), pokemon);
+    assert_eq!(cache.lock().unwrap()["pikachu"].get(), pokemon);
+}
+
+#[test]
+fn test_cached_values_have_jittered_expiry() {
+    let cached_at = Instant::now();
+    let ttl = Duration::from_secs(24 * 60 * 60);
+    let expiries: Vec<Instant> = (0..10)
+        .map(|_| {
+            let mut cached_value = CachedValue::new(PokemonResponse { name: "pikachu".to_string(), ..Default::default() });
+            cached_value.cached_at = cached_at;
+            cached_value.expires_at(ttl)
+        })
+        .collect();
+
+    assert!(expiries.iter().any(|expiry| *expiry != expiries[0]));
+    for expiry in expiries {
+        assert!(expiry >= cached_at + ttl.mul_f64(0.9) && expiry <= cached_at + ttl.mul_f64(1.1));
+    }
+}
+
+#[tokio::test]
+async fn test_stale_pokemon_is_served_and_refreshed_in_background() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("POKEMON_CACHE_SOFT_TTL_SECONDS", "60");
+    mock_servers.set_env("POKEMON_CACHE_HARD_TTL_SECONDS", "3600");
+
+    // The slow PokeAPI shows that the stale pokemon is not waiting for the refresh.
+    let uri = mock_servers.pokeapi.uri();
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .and(wiremock::matchers::path_regex("^/api/v2/pokemon/pikachu/?$"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(mock_servers::pokemon_json(&uri, "pikachu")).set_delay(Duration::from_millis(500)))
+        .mount(&mock_servers.pokeapi)
+        .await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "pikachu",
+        mock_servers::pokemon_species_json(&uri, "pikachu", "Fresh description.", "forest", false)).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let stale_pokemon = PokemonResponse {
+        name: "pikachu".to_string(),
+        description: Some("Stale description.".to_string()),
+        ..Default::default()
+    };
+    let mut cached_pokemon = CachedValue::new(stale_pokemon.clone());
+    cached_pokemon.cached_at = Instant::now() - Duration::from_secs(120);
+    pokemon_cache.lock().unwrap().insert("pikachu".to_string(), cached_pokemon);
+
+    let start = Instant::now();
+    let pokemon = get_or_fetch_pokemon("pikachu".to_string(), false, pokemon_cache.clone(), Arc::new(Mutex::new(HashMap::new())), Arc::new(Mutex::new(HashMap::new())), Arc::new(Config::default())).await;
+    assert_eq!(pokemon, Ok(stale_pokemon));
+    assert!(start.elapsed() < Duration::from_millis(500));
+
+    // Wait for the background refresh to replace the stale pokemon.
+    for _ in 0..50 {
+        if pokemon_cache.lock().unwrap()["pikachu"].get().description.as_deref() == Some("Fresh description.") {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The stale pokemon was not refreshed");
+}
+
+#[tokio::test]
+async fn test_stale_pokemon_is_refreshed_once_by_concurrent_hits() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("POKEMON_CACHE_SOFT_TTL_SECONDS", "60");
+    mock_servers.set_env("POKEMON_CACHE_HARD_TTL_SECONDS", "3600");
+
+    // The slow PokeAPI keeps the first refresh in flight while the other stale hits come in.
+    let uri = mock_servers.pokeapi.uri();
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .and(wiremock::matchers::path_regex("^/api/v2/pokemon/pikachu/?$"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(mock_servers::pokemon_json(&uri, "pikachu")).set_delay(Duration::from_millis(500)))
+        .expect(1)
+        .mount(&mock_servers.pokeapi)
+        .await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "pikachu",
+        mock_servers::pokemon_species_json(&uri, "pikachu", "Fresh description.", "forest", false)).await;
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let mut cached_pokemon = CachedValue::new(PokemonResponse { name: "pikachu".to_string(), ..Default::default() });
+    cached_pokemon.cached_at = Instant::now() - Duration::from_secs(120);
+    pokemon_cache.lock().unwrap().insert("pikachu".to_string(), cached_pokemon);
+
+    for _ in 0..5 {
+        get_or_fetch_pokemon("pikachu".to_string(), false, pokemon_cache.clone(), Arc::new(Mutex::new(HashMap::new())), Arc::new(Mutex::new(HashMap::new())), Arc::new(Config::default())).await.unwrap();
+    }
+
+    for _ in 0..50 {
+        if pokemon_cache.lock().unwrap()["pikachu"].get().description.as_deref() == Some("Fresh description.") {
+            // The mock servers check on drop that PokeAPI was called once.
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The stale pokemon was not refreshed");
+}
+
+#[tokio::test]
+async fn test_handler_panic_is_replied_with_500() {
+    async fn panicking_handler() -> Result<warp::reply::Json, warp::Rejection> {
+        let flavor_text_entries: Vec<&str> = Vec::new();
+        Ok(warp::reply::json(&flavor_text_entries[0]))
+    }
+
+    let f = warp::path("panic")
+        .and(warp::path::end())
+        .and(with_request_context(Arc::new(Config::default())))
+        .and_then(|context| handle_with_request_context(context, panicking_handler()));
+
+    let res = warp::test::request().path("/panic").header("x-request-id", "my-request-id").reply(&f).await;
+
+    assert_eq!(res.status(), 500);
+    assert_eq!(res.headers()["content-type"], "application/json; charset=utf-8");
+    assert_eq!(res.headers()["x-request-id"], "my-request-id");
+    assert_eq!(res.body(), "{\"error\":\"Internal server error\"}");
+}
+
+#[tokio::test]
+async fn test_get_log_format() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    assert_eq!(get_log_format(), LogFormat::Text);
+
+    mock_servers.set_env("LOG_FORMAT", "json");
+    assert_eq!(get_log_format(), LogFormat::Json);
+
+    mock_servers.set_env("LOG_FORMAT", "JSON");
+    assert_eq!(get_log_format(), LogFormat::Json);
+
+    mock_servers.set_env("LOG_FORMAT", "pretty");
+    assert_eq!(get_log_format(), LogFormat::Pretty);
+
+    mock_servers.set_env("LOG_FORMAT", "text");
+    assert_eq!(get_log_format(), LogFormat::Text);
+
+    mock_servers.set_env("LOG_FORMAT", "yaml");
+    assert_eq!(get_log_format(), LogFormat::Text);
+}
+
+#[test]
+fn test_parse_run_mode() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+
+    assert_eq!(parse_run_mode(args(&[])), Ok(RunMode::Serve));
+    assert_eq!(parse_run_mode(args(&["--check"])), Ok(RunMode::Check));
+    assert_eq!(parse_run_mode(args(&["--serve"])), Err("Unknown argument --serve, usage: pokedex_api [--check]".to_string()));
+}
+
+/// A writer keeping the log lines in memory, for the tests of the log lines.
+#[derive(Clone, Default)]
+struct LogLines(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogLines {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for LogLines {
+    type Writer = LogLines;
+
+    fn make_writer(&'a self) -> LogLines {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn test_log_lines_have_the_request_id_and_the_call_timing() {
+    async fn calling_handler() -> Result<&'static str, warp::Rejection> {
+        let mut span = crate::telemetry::Span::start("fetch_pokemon_from_api");
+        span.record("pokemon.name", "pikachu");
+        span.record_result::<(), String>(&Ok(()));
+        Ok("ok")
+    }
+
+    let log_lines = LogLines::default();
+    let subscriber = tracing_subscriber::fmt().json().flatten_event(true).with_current_span(false).with_span_list(true).with_writer(log_lines.clone()).finish();
+    let _subscriber = tracing::subscriber::set_default(subscriber);
+
+    let f = warp::path("call")
+        .and(warp::path::end())
+        .and(with_request_context(Arc::new(Config::default())))
+        .and_then(|context| handle_with_request_context(context, calling_handler()));
+    warp::test::request().path("/call").header("x-request-id", "my-request-id").reply(&f).await;
+
+    let log_lines = String::from_utf8(log_lines.0.lock().unwrap().clone()).unwrap();
+    let line = log_lines.lines().find(|line| line.contains("Upstream call succeeded")).unwrap();
+    let line: Value = serde_json::from_str(line).unwrap();
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["spans"][0]["request_id"], "my-request-id");
+    assert_eq!(line["spans"][1]["function"], "fetch_pokemon_from_api");
+    assert_eq!(line["spans"][1]["pokemon.name"], "pikachu");
+    assert!(line["elapsed_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn test_get_pokemon_descriptions() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    let mut species = mock_servers::pokemon_species_json(&base_url, "charizard", "", "mountain", false);
+    species["flavor_text_entries"] = json!([
+        {
+            "flavor_text": "Spits fire that\nis hot enough to\nmelt boulders.",
+            "language": mock_servers::named_resource(&base_url, "language", "en"),
+            "version": mock_servers::named_resource(&base_url, "version", "red")
+        },
+        {
+            "flavor_text": "Crache du feu si chaud qu’il fait fondre les rochers.",
+            "language": mock_servers::named_resource(&base_url, "language", "fr"),
+            "version": mock_servers::named_resource(&base_url, "version", "x")
+        },
+        {
+            "flavor_text": "It flies around the sky in search of powerful opponents.",
+            "language": mock_servers::named_resource(&base_url, "language", "en"),
+            "version": mock_servers::named_resource(&base_url, "version", "x")
+        }
+    ]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "charizard", mock_servers::pokemon_json(&base_url, "charizard")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "charizard", species).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+
+    let flavor_texts_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let flavor_texts_cache_clone = flavor_texts_cache.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("descriptions"))
+        .and(warp::path::end())
+        .and(warp::query::<DescriptionsQuery>())
+        .and(warp::any().map(move || flavor_texts_cache_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_descriptions)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/charizard/descriptions").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["name"], "charizard");
+    assert_eq!(body["descriptions"], json!([
+        { "description": "Spits fire that is hot enough to melt boulders.", "language": "en", "version": "red" },
+        { "description": "It flies around the sky in search of powerful opponents.", "language": "en", "version": "x" }
+    ]));
+    assert!(flavor_texts_cache.lock().unwrap().contains_key(&("charizard".to_string(), ResourceKind::FlavorTexts)));
+
+    let res = warp::test::request().path("/pokemon/charizard/descriptions?lang=all").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["descriptions"].as_array().unwrap().len(), 3);
+
+    // A single flavor text is still an array.
+    let res = warp::test::request().path("/pokemon/pikachu/descriptions").reply(&f).await;
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["descriptions"].as_array().unwrap().len(), 1);
+
+    let res = warp::test::request().path("/pokemon/charizard/descriptions?lang=klingon").reply(&f).await;
+    assert_eq!(res.status(), 400);
+
+    let res = warp::test::request().path("/pokemon/nopokemon/descriptions").reply(&f).await;
+    assert_eq!(res.status(), 404);
+
+    // The species can't be followed, PokeAPI failing is not the pokemon not found.
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "zubat", mock_servers::pokemon_json(&base_url, "zubat")).await;
+    let res = warp::test::request().path("/pokemon/zubat/descriptions").reply(&f).await;
+    assert_eq!(res.status(), 502);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_cry() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", mock_servers::pokemon_json(&base_url, "pikachu")).await;
+    let mut sprigatito = mock_servers::pokemon_json(&base_url, "sprigatito");
+    sprigatito["cries"]["legacy"] = Value::Null;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "sprigatito", sprigatito).await;
+
+    let cries_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let cries_cache_clone = cries_cache.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("cry"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || cries_cache_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_cry)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/pikachu/cry").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["name"], "pikachu");
+    assert_eq!(body["latest"], format!("{}/cries/pikachu/latest.ogg", base_url));
+    assert!(cries_cache.lock().unwrap().contains_key(&("pikachu".to_string(), ResourceKind::Cries)));
+
+    // The missing cries are explicit nulls.
+    let res = warp::test::request().path("/pokemon/sprigatito/cry").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["legacy"], Value::Null);
+    assert!(body.as_object().unwrap().contains_key("legacy"));
+
+    let res = warp::test::request().path("/pokemon/nopokemon/cry").reply(&f).await;
+    assert_eq!(res.status(), 404);
+
+    // PokeAPI failing is not the pokemon not found.
+    mock_servers::mock_resource_error(&mock_servers.pokeapi, "pokemon", "zubat", 500).await;
+    let res = warp::test::request().path("/pokemon/zubat/cry").reply(&f).await;
+    assert_eq!(res.status(), 502);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_abilities() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    let pokemon_ability = |name: &str, is_hidden: bool, slot: i64| json!({ "is_hidden": is_hidden, "slot": slot, "ability": mock_servers::named_resource(&base_url, "ability", name) });
+    let mut pikachu = mock_servers::pokemon_json(&base_url, "pikachu");
+    pikachu["abilities"] = json!([pokemon_ability("lightning-rod", true, 3), pokemon_ability("static", false, 1)]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", pikachu).await;
+    let mut raichu = mock_servers::pokemon_json(&base_url, "raichu");
+    raichu["abilities"] = json!([pokemon_ability("static", false, 1)]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "raichu", raichu).await;
+    // The lightning-rod ability isn't mocked, its effects can't be fetched.
+    mock_servers::mock_resource(&mock_servers.pokeapi, "ability", "static", mock_servers::ability_json(&base_url, "static", "Contact with the  Pokemon\nmay cause paralysis.", "May paralyze on contact.")).await;
+
+    let abilities_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let abilities_cache_clone = abilities_cache.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("abilities"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || abilities_cache_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_abilities)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/Pikachu/abilities").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["name"], "pikachu");
+    assert_eq!(body["abilities"], json!([
+        { "name": "static", "is_hidden": false, "effect": "Contact with the Pokemon may cause paralysis.", "short_effect": "May paralyze on contact." },
+        { "name": "lightning-rod", "is_hidden": true, "effect": null, "short_effect": null }
+    ]));
+    assert!(abilities_cache.lock().unwrap().contains_key(&("pikachu".to_string(), ResourceKind::Abilities)));
+    assert!(abilities_cache.lock().unwrap().contains_key(&("static".to_string(), ResourceKind::AbilityEffect)));
+
+    // The effects of static are cached, raichu doesn't fetch them again.
+    let ability_requests = |requests: Vec<wiremock::Request>| requests.iter().filter(|request| request.url.path().starts_with("/api/v2/ability/static")).count();
+    assert_eq!(ability_requests(mock_servers.pokeapi.received_requests().await.unwrap()), 1);
+    let res = warp::test::request().path("/pokemon/raichu/abilities").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["abilities"][0]["short_effect"], "May paralyze on contact.");
+    assert_eq!(ability_requests(mock_servers.pokeapi.received_requests().await.unwrap()), 1);
+
+    let res = warp::test::request().path("/pokemon/nopokemon/abilities").reply(&f).await;
+    assert_eq!(res.status(), 404);
+
+    // PokeAPI failing is not the pokemon not found.
+    mock_servers::mock_resource_error(&mock_servers.pokeapi, "pokemon", "zubat", 500).await;
+    let res = warp::test::request().path("/pokemon/zubat/abilities").reply(&f).await;
+    assert_eq!(res.status(), 502);
+}
+
+#[tokio::test]
+async fn test_get_habitat() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-habitat", "cave", mock_servers::pokemon_habitat_json(&base_url, "cave", &["zubat", "golbat"])).await;
+
+    let habitat_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let habitat_cache_clone = habitat_cache.clone();
+
+    let f = warp::path("habitat")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::any().map(String::new))
+        .and(warp::any().map(move || habitat_cache_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_habitat)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/habitat/Cave").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, json!({
+        "name": "cave",
+        "count": 2,
+        "pokemon": [
+            { "name": "zubat", "url": "/pokemon/zubat" },
+            { "name": "golbat", "url": "/pokemon/golbat" }
+        ]
+    }));
+    assert!(habitat_cache.lock().unwrap().contains_key(&("cave".to_string(), ResourceKind::HabitatSpecies)));
+
+    // The second request is served from the cache.
+    let res = warp::test::request().path("/habitat/cave").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), 1);
+
+    // The unknown habitats don't call PokeAPI.
+    let res = warp::test::request().path("/habitat/volcano").reply(&f).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), "{\"error\":\"Habitat not found\"}");
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), 1);
+
+    // The habitat without its resource on PokeAPI is an upstream failure.
+    let res = warp::test::request().path("/habitat/sea").reply(&f).await;
+    assert_eq!(res.status(), 502);
+}
+
+#[tokio::test]
+async fn test_get_type() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "type", "fire", mock_servers::type_json(&base_url, "fire", &["charmander", "charmeleon", "charizard", "charizard-mega-y"])).await;
+
+    let type_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("type")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonListQuery>())
+        .and(warp::any().map(String::new))
+        .and(warp::any().map(move || type_cache.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_type)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/type/fire?limit=2&offset=2").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, json!({
+        "count": 4,
+        "next": null,
+        "previous": "/type/fire?limit=2&offset=0",
+        "results": [
+            { "name": "charizard", "url": "/pokemon/charizard" },
+            { "name": "charizard-mega-y", "url": "/pokemon/charizard-mega-y" }
+        ]
+    }));
+
+    // The other pages are served from the cache.
+    let res = warp::test::request().path("/type/Fire").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["results"].as_array().unwrap().len(), 4);
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), 1);
+
+    let res = warp::test::request().path("/type/fire?limit=0").reply(&f).await;
+    assert_eq!(res.status(), 400);
+
+    let res = warp::test::request().path("/type/plasma").reply(&f).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), "{\"error\":\"Type not found\"}");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_evolution_chain() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "pikachu", "When several of these POKéMON gather, their electricity could build and cause lightning storms.", "forest", false).await;
+    let raichu = mock_servers::evolution_link_json(&base_url, "raichu", Some(("use-item", Some("thunder-stone"), None)), vec![]);
+    let pikachu = mock_servers::evolution_link_json(&base_url, "pikachu", Some(("level-up", None, None)), vec![raichu]);
+    let pichu = mock_servers::evolution_link_json(&base_url, "pichu", None, vec![pikachu]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "evolution-chain", "1", mock_servers::evolution_chain_json(pichu)).await;
+
+    let evolution_chain_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let evolution_chain_cache_clone = evolution_chain_cache.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("evolution-chain"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || evolution_chain_cache_clone.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_evolution_chain)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/pikachu/evolution-chain").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, json!({
+        "name": "pikachu",
+        "stages": [
+            { "name": "pichu", "stage": 1, "evolves_from": null, "trigger": null, "item": null, "min_level": null },
+            { "name": "pikachu", "stage": 2, "evolves_from": "pichu", "trigger": "level-up", "item": null, "min_level": null },
+            { "name": "raichu", "stage": 3, "evolves_from": "pikachu", "trigger": "use-item", "item": "thunder-stone", "min_level": null }
+        ]
+    }));
+    assert!(evolution_chain_cache.lock().unwrap().contains_key(&("pikachu".to_string(), ResourceKind::EvolutionChain)));
+
+    let res = warp::test::request().path("/pokemon/nopokemon/evolution-chain").reply(&f).await;
+    assert_eq!(res.status(), 404);
+
+    // The evolution chain can't be followed, PokeAPI failing is not the pokemon not found.
+    let mut species = mock_servers::pokemon_species_json(&base_url, "zubat", "Forms colonies in perpetually dark places.", "cave", false);
+    species["evolution_chain"] = json!({ "url": format!("{}/api/v2/evolution-chain/2/", base_url) });
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "zubat", mock_servers::pokemon_json(&base_url, "zubat")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "zubat", species).await;
+    mock_servers::mock_resource_error(&mock_servers.pokeapi, "evolution-chain", "2", 500).await;
+    let res = warp::test::request().path("/pokemon/zubat/evolution-chain").reply(&f).await;
+    assert_eq!(res.status(), 502);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_evolution_chain_with_branches() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "wurmple", "Using the spikes on its rear end, WURMPLE peels the bark off trees and feeds on the oozing sap.", "forest", false).await;
+    let beautifly = mock_servers::evolution_link_json(&base_url, "beautifly", Some(("level-up", None, Some(10))), vec![]);
+    let silcoon = mock_servers::evolution_link_json(&base_url, "silcoon", Some(("level-up", None, Some(7))), vec![beautifly]);
+    let dustox = mock_servers::evolution_link_json(&base_url, "dustox", Some(("level-up", None, Some(10))), vec![]);
+    let cascoon = mock_servers::evolution_link_json(&base_url, "cascoon", Some(("level-up", None, Some(7))), vec![dustox]);
+    let wurmple = mock_servers::evolution_link_json(&base_url, "wurmple", None, vec![silcoon, cascoon]);
+    mock_servers::mock_resource(&mock_servers.pokeapi, "evolution-chain", "1", mock_servers::evolution_chain_json(wurmple)).await;
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("evolution-chain"))
+        .and(warp::path::end())
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_evolution_chain);
+
+    let res = warp::test::request().path("/pokemon/wurmple/evolution-chain").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+
+    // The stages are ordered by stage, the branches of the same stage in the PokeAPI order.
+    let stages: Vec<(&str, u64, Option<&str>, Option<i64>)> = body["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|stage| (stage["name"].as_str().unwrap(), stage["stage"].as_u64().unwrap(), stage["evolves_from"].as_str(), stage["min_level"].as_i64()))
+        .collect();
+    assert_eq!(stages, vec![
+        ("wurmple", 1, None, None),
+        ("silcoon", 2, Some("wurmple"), Some(7)),
+        ("cascoon", 2, Some("wurmple"), Some(7)),
+        ("beautifly", 3, Some("silcoon"), Some(10)),
+        ("dustox", 3, Some("cascoon"), Some(10)),
+    ]);
+}
+
+#[tokio::test]
+async fn test_sub_resource_cache_hit() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", mock_servers::pokemon_json(&base_url, "pikachu")).await;
+
+    let sub_resource_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+    let sub_resource_cache_moves = sub_resource_cache.clone();
+    let sub_resource_cache_cry = sub_resource_cache.clone();
+
+    let moves = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("moves"))
+        .and(warp::path::end())
+        .and(warp::query::<MovesQuery>())
+        .and(warp::any().map(move || sub_resource_cache_moves.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_moves);
+    let cry = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("cry"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || sub_resource_cache_cry.clone()))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon_cry);
+
+    let first = warp::test::request().path("/pokemon/pikachu/cry").reply(&cry).await;
+    let requests_after_first_call = mock_servers.pokeapi.received_requests().await.unwrap().len();
+    let second = warp::test::request().path("/pokemon/pikachu/cry").reply(&cry).await;
+
+    // The second call is a cache hit, PokeAPI is not called again and the reply is the same.
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), requests_after_first_call);
+    assert_eq!(first.body(), second.body());
+
+    // The moves of the same pokemon are another resource in the shared cache.
+    let res = warp::test::request().path("/pokemon/pikachu/moves").reply(&moves).await;
+    assert_eq!(res.status(), 200);
+    let requests_after_moves = mock_servers.pokeapi.received_requests().await.unwrap().len();
+    assert!(requests_after_moves > requests_after_first_call);
+    warp::test::request().path("/pokemon/pikachu/moves").reply(&moves).await;
+    assert_eq!(mock_servers.pokeapi.received_requests().await.unwrap().len(), requests_after_moves);
+
+    let cache = sub_resource_cache.lock().unwrap();
+    assert!(cache.contains_key(&("pikachu".to_string(), ResourceKind::Cries)));
+    assert!(cache.contains_key(&("pikachu".to_string(), ResourceKind::Moves)));
+}
+
+#[tokio::test]
+async fn test_slow_handler_is_replied_with_504() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("REQUEST_TIMEOUT_SECONDS", "1");
+    let base_url = mock_servers.pokeapi.uri();
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .and(wiremock::matchers::path_regex("^/api/v2/pokemon/pikachu/?$"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(mock_servers::pokemon_json(&base_url, "pikachu")).set_delay(Duration::from_secs(3)))
+        .mount(&mock_servers.pokeapi)
+        .await;
+
+    let cries_cache: SubResourceCache = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path("cry"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || cries_cache.clone()))
+        .and(with_request_context(Arc::new(Config::default())))
+        .and_then(|pokemon_name, cache_cries, context| handle_with_request_context(context, get_pokemon_cry(pokemon_name, cache_cries, Arc::new(Config::default()))));
+
+    let start = Instant::now();
+    let res = warp::test::request().path("/pokemon/pikachu/cry").reply(&f).await;
+
+    assert_eq!(res.status(), 504);
+    assert_eq!(res.body(), "{\"error\":\"Request timed out\"}");
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn test_pokedex_error_is_replied_with_request_context() {
+    let f = warp::path("fails")
+        .and(with_request_context(Arc::new(Config::default())))
+        .and_then(|context| handle_with_request_context(context, async { Err::<String, _>(warp::reject::custom(PokedexError::UpstreamUnavailable("Translation unavailable"))) }));
+
+    let res = warp::test::request().path("/fails?pretty=true").header("x-request-id", "req-123").reply(&f).await;
+
+    assert_eq!(res.status(), 503);
+    assert_eq!(res.headers()["x-request-id"], "req-123");
+    assert_eq!(res.headers()["cache-control"], "no-store");
+    assert_eq!(res.body(), "{\n  \"error\": \"Translation unavailable\"\n}");
+}
+
+#[test]
+fn test_pokedex_error_status() {
+    assert_eq!(PokedexError::from(PokemonFetchError::NotFound).status(), 404);
+    assert_eq!(PokedexError::from(PokemonFetchError::Upstream).status(), 502);
+    assert_eq!(PokedexError::from(TranslationError::Api { code: 429, message: "Too Many Requests".to_string() }), PokedexError::RateLimited("Translation failed"));
+    assert_eq!(PokedexError::from(TranslationError::Malformed("not JSON".to_string())).status(), 502);
+    assert_eq!(PokedexError::Timeout.status(), 504);
+    assert_eq!(PokedexError::Timeout.message(), "Request timed out");
+    assert_eq!(PokedexError::NotFoundWithSuggestions(vec!["pikachu".to_string()]).status(), 404);
+    assert_eq!(PokedexError::NotFoundWithSuggestions(vec!["pikachu".to_string()]).suggestions(), Some(&["pikachu".to_string()][..]));
+}
+
+#[tokio::test]
+async fn test_pokedex_error_from_translation_request_error() {
+    let server = wiremock::MockServer::start().await;
+    for status in [429, 500] {
+        wiremock::Mock::given(wiremock::matchers::path(format!("/{}", status)))
+            .respond_with(wiremock::ResponseTemplate::new(status))
+            .mount(&server)
+            .await;
+    }
+    let request_error = |path: &'static str| {
+        let url = format!("{}{}", server.uri(), path);
+        async move { reqwest::get(url).await.unwrap().error_for_status().unwrap_err() }
+    };
+
+    let error = TranslationError::Request(request_error("/429").await);
+    assert_eq!(PokedexError::from(error), PokedexError::RateLimited("Translation failed"));
+
+    let error = TranslationError::Request(request_error("/500").await);
+    assert_eq!(PokedexError::from(error), PokedexError::UpstreamFailed("Translation failed"));
+
+    // Nothing listens on the port, the request has no reply.
+    let error = TranslationError::Request(reqwest::get("http://127.0.0.1:1").await.unwrap_err());
+    assert_eq!(PokedexError::from(error), PokedexError::UpstreamUnavailable("Translation unavailable"));
+}
+
+#[test]
+fn test_get_description_for_language() {
+    let descriptions = HashMap::from([
+        ("en".to_string(), "English description.".to_string()),
+        ("de".to_string(), "Deutsche Beschreibung.".to_string()),
+    ]);
+
+    // The first language is missing, the second one is used.
+    let languages = vec!["it".to_string(), "de".to_string(), "en".to_string()];
+    assert_eq!(get_description_for_language(&descriptions, &languages, None).as_deref(), Some("Deutsche Beschreibung."));
+
+    // None of the languages is present, the english description is used.
+    let languages = vec!["it".to_string(), "fr".to_string()];
+    assert_eq!(get_description_for_language(&descriptions, &languages, None).as_deref(), Some("English description."));
+
+    // The default language comes after the requested languages and before english.
+    assert_eq!(get_description_for_language(&descriptions, &[], Some("de")).as_deref(), Some("Deutsche Beschreibung."));
+    assert_eq!(get_description_for_language(&descriptions, &["en".to_string()], Some("de")).as_deref(), Some("English description."));
+    assert_eq!(get_description_for_language(&descriptions, &languages, Some("ja")).as_deref(), Some("English description."));
+
+    assert_eq!(get_description_for_language(&HashMap::new(), &languages, None), None);
+}
+
+#[test]
+fn test_get_description_language() {
+    let descriptions = HashMap::from([
+        ("en".to_string(), "English description.".to_string()),
+        ("de".to_string(), "Deutsche Beschreibung.".to_string()),
+    ]);
+
+    assert_eq!(get_description_language(&descriptions, &["it".to_string(), "de".to_string()], None), Some("de"));
+    assert_eq!(get_description_language(&descriptions, &["ja".to_string()], Some("de")), Some("de"));
+    assert_eq!(get_description_language(&descriptions, &["ja".to_string()], None), Some("en"));
+    assert_eq!(get_description_language(&HashMap::new(), &["ja".to_string()], None), None);
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_default_language() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("DEFAULT_LANGUAGE", "DE");
+    let base_url = mock_servers.pokeapi.uri();
+    let mut species = mock_servers::pokemon_species_json(&base_url, "pikachu", "When several of these POKéMON gather,\ntheir electricity could build and cause lightning storms.", "forest", false);
+    species["flavor_text_entries"].as_array_mut().unwrap().push(json!({
+        "flavor_text": "Wenn mehrere dieser POKéMON zusammenkommen,\nkönnen sie Gewitter verursachen.",
+        "language": mock_servers::named_resource(&base_url, "language", "de"),
+        "version": mock_servers::named_resource(&base_url, "version", "red")
+    }));
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", mock_servers::pokemon_json(&base_url, "pikachu")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "pikachu", species).await;
+    mock_servers::mock_pokemon(&mock_servers.pokeapi, "zubat", "Forms colonies in perpetually dark places.", "cave", false).await;
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    // Without the lang query parameter the description is in the default language.
+    let res = warp::test::request().path("/pokemon/pikachu").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(pokemon.description.as_deref(), Some("Wenn mehrere dieser POKéMON zusammenkommen, können sie Gewitter verursachen."));
+
+    // The lang query parameter still wins over the default language.
+    let res = warp::test::request().path("/pokemon/pikachu?lang=en").reply(&f).await;
+    let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(pokemon.description.as_deref(), Some("When several of these POKéMON gather, their electricity could build and cause lightning storms."));
+
+    // The pokemon without a description in the default language fall back to english.
+    let res = warp::test::request().path("/pokemon/zubat").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(pokemon.description.as_deref(), Some("Forms colonies in perpetually dark places."));
+    assert_eq!(pokemon.description_available, None);
+}
+
+#[test]
+fn test_parse_description_languages() {
+    assert_eq!(parse_description_languages("it,de,en"), Ok(vec!["it".to_string(), "de".to_string(), "en".to_string()]));
+    assert_eq!(parse_description_languages(" IT, pt-br ,"), Ok(vec!["it".to_string(), "pt-BR".to_string()]));
+    assert_eq!(parse_description_languages("it,klingon,de"), Err("klingon".to_string()));
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_lang_fallback() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    let base_url = mock_servers.pokeapi.uri();
+    let mut species = mock_servers::pokemon_species_json(&base_url, "pikachu", "When several of these POKéMON gather,\ntheir electricity could build and cause lightning storms.", "forest", false);
+    species["flavor_text_entries"].as_array_mut().unwrap().push(json!({
+        "flavor_text": "Wenn mehrere dieser POKéMON zusammenkommen,\nkönnen sie Gewitter verursachen.",
+        "language": mock_servers::named_resource(&base_url, "language", "de"),
+        "version": mock_servers::named_resource(&base_url, "version", "red")
+    }));
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon", "pikachu", mock_servers::pokemon_json(&base_url, "pikachu")).await;
+    mock_servers::mock_resource(&mock_servers.pokeapi, "pokemon-species", "pikachu", species).await;
+
+    let descriptions_cache: Cache<String, Descriptions> = Arc::new(Mutex::new(HashMap::new()));
+    let descriptions_cache_clone = descriptions_cache.clone();
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(move || descriptions_cache_clone.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    let res = warp::test::request().path("/pokemon/pikachu?lang=it,de,en").reply(&f).await;
+    assert_eq!(res.status(), 200);
+    let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(pokemon.description.as_deref(), Some("Wenn mehrere dieser POKéMON zusammenkommen, können sie Gewitter verursachen."));
+    assert_eq!(pokemon.description_language.as_deref(), Some("de"));
+    assert!(descriptions_cache.lock().unwrap().contains_key("pikachu"));
+
+    // Without a description in the requested languages the english one is returned, and reported.
+    let res = warp::test::request().path("/pokemon/pikachu?lang=it,fr").reply(&f).await;
+    let pokemon: PokemonResponse = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(pokemon.description.as_deref(), Some("When several of these POKéMON gather, their electricity could build and cause lightning storms."));
+    assert_eq!(pokemon.description_language.as_deref(), Some("en"));
+
+    let res = warp::test::request().path("/pokemon/pikachu?lang=it,xx").reply(&f).await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "{\"error\":\"Invalid query parameters\",\"errors\":[{\"message\":\"Unknown language xx, use one of: cs, de, en, es, fr, it, ja, ja-Hrkt, ko, pt-BR, roomaji, zh-Hans, zh-Hant\",\"param\":\"lang\"}]}");
+}
+
+#[tokio::test]
+async fn test_get_pokemon_with_several_invalid_parameters() {
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon);
+
+    // Both errors are reported, before calling PokeAPI.
+    let res = warp::test::request().path("/pokemon/pikachu?no_cache=yes&lang=xx").reply(&f).await;
+    assert_eq!(res.status(), 400);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["error"], "Invalid query parameters");
+    assert_eq!(body["errors"], json!([
+        { "param": "no_cache", "message": "Expected true or false, got yes" },
+        { "param": "lang", "message": "Unknown language xx, use one of: cs, de, en, es, fr, it, ja, ja-Hrkt, ko, pt-BR, roomaji, zh-Hans, zh-Hant" }
+    ]));
+}
+
+#[tokio::test]
+async fn test_dry_run_serves_fixtures() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("DRY_RUN", "true");
+
+    let pokemon = fetch_pokemon_from_api("pikachu".to_string(), &Config::default()).await.unwrap();
+    assert_eq!(pokemon.name, "pikachu");
+    assert_eq!(pokemon.habitat.as_deref(), Some("forest"));
+    assert_eq!(pokemon.description.as_deref(), Some("When several of these POKéMON gather, their electricity could build and cause lightning storms."));
+
+    let translation = get_translation(pokemon.description.as_deref().unwrap(), &TranslatorSelection::from_pokemon(&pokemon), &Mutex::new(TranslationQuota::new()), &Config::default()).await.unwrap();
+    assert_eq!(translation.translator, TranslatorKind::Shakespeare);
+    assert_eq!(translation.text, "At which hour several of these pokémon gather, their electricity couldst buildeth and cause lightning storms.");
+
+    let species_names = fetch_species_names_from_api(&Config::default()).await.unwrap();
+    assert!(species_names.contains(&"mewtwo".to_string()));
+
+    let descriptions = fetch_pokemon_descriptions_from_api("pikachu".to_string(), &Config::default()).await.unwrap();
+    assert_eq!(descriptions.get("en"), pokemon.description.as_ref());
+    assert!(fetch_pokemon_sprites_from_api("pikachu".to_string(), &Config::default()).await.unwrap_err().is::<DryRunUnavailable>());
+
+    // The mock servers have nothing mounted, the fixtures are served without calling them.
+    assert!(mock_servers.pokeapi.received_requests().await.unwrap().is_empty());
+    assert!(mock_servers.funtranslations.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_translation_with_fixtures_provider() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    assert_eq!(TranslationProvider::from_env(), TranslationProvider::FunTranslations);
+
+    mock_servers.set_env("TRANSLATION_PROVIDER", "Fixtures");
+    assert_eq!(TranslationProvider::from_env(), TranslationProvider::Fixtures);
+
+    // The translation comes from the fixtures, no translation is mounted on the mock server.
+    let translation = get_translation(
+        "Forms colonies in perpetually dark places. Uses ultrasonic waves to identify and approach targets.",
+        &TranslatorSelection { habitat: Some("cave"), is_legendary: false, types: &[] },
+        &Mutex::new(TranslationQuota::new()), &Config::default()
+    ).await.unwrap();
+    assert_eq!(translation.translator, TranslatorKind::Yoda);
+    assert_eq!(translation.text, "Forms colonies in perpetually dark places.Ultrasonic waves to identify and approach targets, uses.");
+    assert!(mock_servers.funtranslations.received_requests().await.unwrap().is_empty());
+
+    mock_servers.set_env("TRANSLATION_PROVIDER", "babelfish");
+    assert_eq!(TranslationProvider::from_env(), TranslationProvider::FunTranslations);
+}
+
+#[tokio::test]
+async fn test_dry_run_unknown_pokemon_not_found() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+    mock_servers.set_env("DRY_RUN", "true");
+
+    let pokemon_cache: Cache<String, PokemonResponse> = Arc::new(Mutex::new(HashMap::new()));
+    let alias_cache: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+    let not_found_cache: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let f = warp::path("pokemon")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<PokemonQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::any().map(move || pokemon_cache.clone()))
+        .and(warp::any().map(move || alias_cache.clone()))
+        .and(warp::any().map(move || not_found_cache.clone()))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(warp::any().map(|| Arc::new(Mutex::new(HashMap::new()))))
+        .and(with_config(Arc::new(Config::default())))
+        .and_then(get_pokemon)
+        .recover(handle_rejection);
+
+    let res = warp::test::request().path("/pokemon/mewtwo").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/pokemon/ditto").reply(&f).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.body(), "{\"error\":\"Pokemon not found\"}");
+
+    assert!(mock_servers.pokeapi.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_api_key() {
+    let mock_servers = mock_servers::start_mock_servers().await;
+
+    let f = warp::path("schema")
+        .and(warp::path::end())
+        .and(with_api_key())
+        .and_then(get_schema)
+        .recover(handle_rejection);
+
+    // Without the API_KEY env variable the endpoints are open.
+    let res = warp::test::request().path("/schema").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    mock_servers.set_env("API_KEY", "secret");
+
+    let res = warp::test::request().path("/schema").header("x-api-key", "secret").reply(&f).await;
+    assert_eq!(res.status(), 200);
+
+    let res = warp::test::request().path("/schema").header("x-api-key", "wrong").reply(&f).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.body(), "{\"error\":\"Unauthorized\"}");
+
+    let res = warp::test::request().path("/schema").reply(&f).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.body(), "{\"error\":\"Unauthorized\"}");
+}
@@ -0,0 +1,187 @@
+//! The small helpers shared by the handlers: the JSON replies, the text normalization and the pokemon name validation.
+
+use std::time::Duration;
+use serde::Serialize;
+use serde_json::json;
+use tracing::warn;
+use crate::filters::PRETTY_JSON;
+
+/// Build a JSON reply with the status code.
+/// 
+/// The descriptions contain accented and non-Latin characters, so every reply declares the UTF-8 charset
+/// with the `Content-Type: application/json; charset=utf-8` header, including the error replies.
+/// The JSON is compact, unless the request has the `pretty=true` query parameter, see `with_request_context`.
+pub(crate) fn json_reply<T: Serialize>(value: &T, status: warp::http::StatusCode) -> warp::reply::WithHeader<warp::reply::WithStatus<String>> {
+    let pretty_json = PRETTY_JSON.try_with(|pretty_json| *pretty_json).unwrap_or(false);
+    let body = if pretty_json {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    // The replies are built from our own types, so the serialization can't fail.
+    let reply = warp::reply::with_status(body.expect("Failed to serialize the reply"), status);
+    warp::reply::with_header(reply, "content-type", "application/json; charset=utf-8")
+}
+
+/// Check if the `Accept` header prefers plain text to JSON.
+/// 
+/// The media types are checked in order, ignoring the quality values: the first one between `text/plain`,
+/// `application/json` and the wildcards wins. Without the header the reply is JSON.
+pub(crate) fn accepts_plain_text(accept: Option<&str>) -> bool {
+    let Some(accept) = accept else {
+        return false;
+    };
+
+    for media_type in accept.split(',') {
+        let media_type = media_type.split(';').next().unwrap_or("").trim().to_lowercase();
+        match media_type.as_str() {
+            "text/plain" => return true,
+            "application/json" | "application/*" | "*/*" => return false,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// Add the `Cache-Control` header to the reply, `public` with the max age or `no-store` without it.
+/// 
+/// Only the successful replies should be cached, the error replies use `no-store`.
+pub(crate) fn with_cache_control<R: warp::Reply>(reply: R, max_age_seconds: Option<u32>) -> warp::reply::WithHeader<R> {
+    let cache_control = match max_age_seconds {
+        Some(max_age_seconds) => format!("public, max-age={}", max_age_seconds),
+        None => "no-store".to_string(),
+    };
+    warp::reply::with_header(reply, "cache-control", cache_control)
+}
+
+/// Normalize the "POKéMON" artifacts of the Gen 1 flavor texts, e.g. "POKéMON" to "Pokémon".
+/// 
+/// Only the known artifacts are replaced, the other capitalized words are left untouched.
+pub(crate) fn normalize_text(text: &str) -> String {
+    const ARTIFACTS: [(&str, &str); 4] = [
+        ("POKéMON", "Pokémon"),
+        ("POKé BALL", "Poké Ball"),
+        ("POKéDEX", "Pokédex"),
+        ("POKé", "Poké"),
+    ];
+
+    let mut normalized_text = text.to_string();
+    for (artifact, replacement) in ARTIFACTS {
+        normalized_text = normalized_text.replace(artifact, replacement);
+    }
+    normalized_text
+}
+
+/// Collapse every run of whitespace to a single space and trim the ends.
+/// 
+/// The flavor texts have newlines and form feeds for the game text boxes, and the translations sometimes have repeated spaces.
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// The Levenshtein distance of the two names: the fewest insertions, deletions and substitutions of characters
+/// turning one into the other, e.g. 1 between "picachu" and "pikachu".
+pub(crate) fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            row[j + 1] = substitution.min(previous_row[j + 1] + 1).min(row[j] + 1);
+        }
+        previous_row = row;
+    }
+    previous_row[b.len()]
+}
+
+/// Normalize the requested name to the PokeAPI name, before validating it.
+/// 
+/// The names in the path arrive percent-encoded, like `farfetch%27d`, so they are decoded first.
+/// Then the names are lowercased and the species names with symbols are mapped to their PokeAPI names:
+/// the apostrophes, periods and colons are dropped (`farfetch'd` is `farfetchd`, `mr. mime` is `mr-mime`),
+/// the gender symbols become a suffix (`nidoran♀` is `nidoran-f`), `é` is `e` and the spaces are hyphens.
+pub(crate) fn normalize_pokemon_name(pokemon_name: &str) -> String {
+    let decoded = percent_encoding::percent_decode_str(pokemon_name).decode_utf8_lossy();
+
+    let mut normalized = String::with_capacity(decoded.len());
+    for c in decoded.trim().to_lowercase().chars() {
+        match c {
+            '\'' | '’' | '.' | ':' => {}
+            '♀' => normalized.push_str("-f"),
+            '♂' => normalized.push_str("-m"),
+            'é' => normalized.push('e'),
+            ' ' => normalized.push('-'),
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// The longest name accepted before calling the PokeAPI, the longest real names are less than 30 characters.
+pub(crate) const MAX_POKEMON_NAME_LENGTH: usize = 40;
+
+/// Check if the name can be a real pokemon name or id, before calling the PokeAPI.
+/// 
+/// The PokeAPI names are made of letters, digits and single hyphens, like "porygon2" or "porygon-z",
+/// and the ids are only digits. The names failing the check can't be a pokemon, so they get a 422
+/// with the error code instead of a 404, which is reserved for the plausible but missing names.
+pub(crate) fn validate_pokemon_name(pokemon_name: &str) -> Result<(), &'static str> {
+    if pokemon_name.len() > MAX_POKEMON_NAME_LENGTH {
+        return Err("name_too_long");
+    }
+    if !pokemon_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err("invalid_characters");
+    }
+    if pokemon_name.starts_with('-') || pokemon_name.ends_with('-') || pokemon_name.contains("--") {
+        return Err("invalid_hyphens");
+    }
+    // No pokemon name starts with a digit, only the ids do.
+    let starts_with_digit = pokemon_name.starts_with(|c: char| c.is_ascii_digit());
+    if starts_with_digit && !pokemon_name.chars().all(|c| c.is_ascii_digit()) {
+        return Err("invalid_pattern");
+    }
+    Ok(())
+}
+
+/// Build the 422 reply for a name failing `validate_pokemon_name`.
+pub(crate) fn invalid_name_reply(code: &str) -> warp::reply::WithHeader<warp::reply::WithStatus<String>> {
+    let reply = json!({
+        "error": "Invalid pokemon name",
+        "code": code
+    });
+    json_reply(&reply, warp::http::StatusCode::UNPROCESSABLE_ENTITY)
+}
+
+/// Run the operation until it succeeds, at most `attempts` times, doubling the delay between the attempts.
+/// 
+/// The error of the last attempt is returned.
+pub(crate) async fn retry_with_backoff<F, Fut, T, E>(attempts: u32, initial_delay: Duration, mut operation: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let mut delay = initial_delay;
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(error) if attempt < attempts => {
+                warn!("Attempt {} of {} failed, retrying in {:?}: {}", attempt, attempts, delay, error);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Get a number from an env variable, or the default if the variable is not set or not a valid number.
+pub(crate) fn get_env_u32(name: &str, default: u32) -> u32 {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(default)
+}